@@ -0,0 +1,242 @@
+use crate::agent::AgentLoop;
+use crate::bus::{AgentEvent, AgentEventKind, InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::ChannelCapabilities;
+use crate::config::AppConfig;
+use crate::cron::CronService;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::io::{IsTerminal, Read, Write};
+
+const CHANNEL: &str = "cli";
+/// Exit status when no provider route produced an answer.
+pub const EXIT_PROVIDER_FAILURE: i32 = 3;
+
+pub struct AskOptions {
+    pub prompt: Option<String>,
+    /// Append stdin to a prompt given as an argument.
+    pub stdin: bool,
+    /// Selects the memory namespace. Each run starts without history, since
+    /// the process exits after one turn.
+    pub session: String,
+    pub model: Option<String>,
+    pub tools: bool,
+    pub json: bool,
+}
+
+/// Everything observed during the turn besides the answer.
+#[derive(Default)]
+struct TurnLog {
+    tools: Vec<Value>,
+    route: Option<(String, bool)>,
+    usage: (u64, u64),
+    messages: Vec<OutboundMessage>,
+}
+
+impl TurnLog {
+    fn record(&mut self, event: AgentEvent, session: &str) {
+        if event.channel != CHANNEL || event.chat_id != session {
+            return;
+        }
+        match event.kind {
+            AgentEventKind::ToolCall { name, args } => self.tools.push(json!({
+                "name": name,
+                "args": args,
+                "result": Value::Null,
+            })),
+            AgentEventKind::ToolResult { name, result } => {
+                if let Some(call) = self
+                    .tools
+                    .iter_mut()
+                    .rev()
+                    .find(|call| call["name"] == name.as_str() && call["result"].is_null())
+                {
+                    call["result"] = Value::String(result);
+                }
+            }
+            AgentEventKind::TurnCompleted {
+                route,
+                fallback,
+                input_tokens,
+                output_tokens,
+            } => {
+                self.route = Some((route, fallback));
+                self.usage = (input_tokens, output_tokens);
+            }
+        }
+    }
+}
+
+/// Run one agent turn in-process and print the answer. Returns the exit
+/// status: 0, or [`EXIT_PROVIDER_FAILURE`] when every route failed.
+pub async fn run(opts: AskOptions) -> Result<i32> {
+    // Reading stdin when a prompt was given would hang under ssh or CI
+    // runners that leave it open, so that takes `--stdin`.
+    let piped = if !reads_stdin(opts.prompt.as_deref(), opts.stdin)
+        || (!opts.stdin && std::io::stdin().is_terminal())
+    {
+        None
+    } else {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Some(input)
+    };
+    let prompt = compose_prompt(opts.prompt.as_deref(), piped.as_deref())
+        .ok_or_else(|| anyhow!("no prompt given; pass it as an argument or on stdin"))?;
+
+    let mut cfg = AppConfig::load()?;
+    if let Some(model) = &opts.model {
+        if !cfg.pin_model(model) {
+            return Err(anyhow!("invalid model '{model}'"));
+        }
+    }
+
+    let bus = MessageBus::new();
+    // Messages the agent sends to this session with `send_message` or
+    // `send_file` are printed with the answer.
+    let mut outbound = bus.register_channel(
+        CHANNEL,
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: true,
+        },
+    );
+    let mut events = bus.subscribe_events();
+    // Jobs scheduled during the turn are stored and run by the daemon.
+    let cron_service = CronService::new(&cfg, bus.clone());
+    let agent = if opts.tools {
+        AgentLoop::new(cfg, bus.clone(), cron_service)
+    } else {
+        AgentLoop::without_tools(cfg, bus.clone(), cron_service)
+    };
+
+    let turn = agent.ask(InboundMessage {
+        channel: CHANNEL.to_string(),
+        chat_id: opts.session.clone(),
+        sender_id: CHANNEL.to_string(),
+        content: prompt,
+        ..Default::default()
+    });
+    tokio::pin!(turn);
+    let mut log = TurnLog::default();
+    let result = loop {
+        tokio::select! {
+            result = &mut turn => break result,
+            Ok(event) = events.recv() => log.record(event, &opts.session),
+            Some(msg) = outbound.recv() => log.messages.push(msg),
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        log.record(event, &opts.session);
+    }
+    while let Ok(msg) = outbound.try_recv() {
+        log.messages.push(msg);
+    }
+    log.messages.retain(|msg| msg.chat_id == opts.session);
+
+    let status = if result.is_ok() {
+        0
+    } else {
+        EXIT_PROVIDER_FAILURE
+    };
+    let mut stdout = std::io::stdout().lock();
+    if opts.json {
+        writeln!(
+            stdout,
+            "{}",
+            serde_json::to_string_pretty(&report(&opts.session, &result, &log))?
+        )?;
+    } else {
+        for msg in &log.messages {
+            writeln!(stdout, "{}\n", format_message(msg))?;
+        }
+        match &result {
+            Ok(answer) => writeln!(stdout, "{}", answer.trim())?,
+            Err(err) => eprintln!("lightclaw ask: {err}"),
+        }
+    }
+    stdout.flush()?;
+    // Summaries are written in the background; finish them before exiting.
+    agent.wait_for_memory().await;
+    Ok(status)
+}
+
+/// Whether the prompt (also) comes from stdin: without a prompt argument,
+/// with `-`, or when `--stdin` asks for it.
+fn reads_stdin(arg: Option<&str>, stdin: bool) -> bool {
+    stdin || arg.is_none_or(|arg| arg.trim().is_empty() || arg.trim() == "-")
+}
+
+/// The prompt argument, with piped input appended below it. `-` or no
+/// argument means the prompt is read from stdin alone.
+fn compose_prompt(arg: Option<&str>, piped: Option<&str>) -> Option<String> {
+    let arg = arg
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty() && *arg != "-");
+    let piped = piped.map(str::trim).filter(|input| !input.is_empty());
+    match (arg, piped) {
+        (Some(arg), Some(piped)) => Some(format!("{arg}\n\n{piped}")),
+        (Some(text), None) | (None, Some(text)) => Some(text.to_string()),
+        (None, None) => None,
+    }
+}
+
+fn format_message(msg: &OutboundMessage) -> String {
+    let mut lines = vec![msg.content.trim().to_string()];
+    lines.extend(
+        msg.attachments
+            .iter()
+            .map(|attachment| format!("[file] {}", attachment.path.display())),
+    );
+    lines.retain(|line| !line.is_empty());
+    lines.join("\n")
+}
+
+fn report(session: &str, result: &Result<String, String>, log: &TurnLog) -> Value {
+    let (route, fallback) = match &log.route {
+        Some((route, fallback)) => (Value::String(route.clone()), *fallback),
+        None => (Value::Null, false),
+    };
+    let mut report = json!({
+        "session": session,
+        "route": route,
+        "fallback": fallback,
+        "usage": { "input_tokens": log.usage.0, "output_tokens": log.usage.1 },
+        "tool_calls": log.tools,
+        "messages": log.messages.iter().map(format_message).collect::<Vec<_>>(),
+    });
+    match result {
+        Ok(answer) => report["answer"] = Value::String(answer.trim().to_string()),
+        Err(err) => report["error"] = Value::String(err.clone()),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_prompt_argument_with_piped_input() {
+        assert_eq!(
+            compose_prompt(Some("Summarize this log"), Some("line 1\nline 2\n")).as_deref(),
+            Some("Summarize this log\n\nline 1\nline 2")
+        );
+        assert_eq!(
+            compose_prompt(Some("-"), Some("from stdin")).as_deref(),
+            Some("from stdin")
+        );
+        assert_eq!(
+            compose_prompt(Some("just ask"), Some("  ")).as_deref(),
+            Some("just ask")
+        );
+        assert_eq!(compose_prompt(None, None), None);
+    }
+
+    #[test]
+    fn reads_stdin_only_without_a_prompt_or_when_asked() {
+        assert!(reads_stdin(None, false));
+        assert!(reads_stdin(Some("-"), false));
+        assert!(!reads_stdin(Some("just ask"), false));
+        assert!(reads_stdin(Some("just ask"), true));
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Convert any ffmpeg-readable audio file to OGG/Opus, the format Telegram
+/// expects for voice messages.
+pub async fn convert_to_ogg_opus(ffmpeg: &str, input: &Path, output: &Path) -> Result<()> {
+    run_ffmpeg(
+        ffmpeg,
+        &[
+            OsStr::new("-i"),
+            input.as_os_str(),
+            OsStr::new("-c:a"),
+            OsStr::new("libopus"),
+            OsStr::new("-b:a"),
+            OsStr::new("32k"),
+            output.as_os_str(),
+        ],
+    )
+    .await
+}
+
+/// Convert audio to 16 kHz mono PCM WAV, the input format whisper.cpp and
+/// most other local speech-to-text tools expect.
+pub async fn convert_to_wav_16k_mono(ffmpeg: &str, input: &Path, output: &Path) -> Result<()> {
+    run_ffmpeg(
+        ffmpeg,
+        &[
+            OsStr::new("-i"),
+            input.as_os_str(),
+            OsStr::new("-ar"),
+            OsStr::new("16000"),
+            OsStr::new("-ac"),
+            OsStr::new("1"),
+            OsStr::new("-c:a"),
+            OsStr::new("pcm_s16le"),
+            output.as_os_str(),
+        ],
+    )
+    .await
+}
+
+/// Extract `duration_secs` of audio starting at `start_secs` as 16 kHz mono
+/// WAV. Past the end of the input this produces a header-only file.
+pub async fn extract_wav_segment(
+    ffmpeg: &str,
+    input: &Path,
+    output: &Path,
+    start_secs: u64,
+    duration_secs: u64,
+) -> Result<()> {
+    let start = start_secs.to_string();
+    let duration = duration_secs.to_string();
+    run_ffmpeg(
+        ffmpeg,
+        &[
+            OsStr::new("-ss"),
+            OsStr::new(&start),
+            OsStr::new("-t"),
+            OsStr::new(&duration),
+            OsStr::new("-i"),
+            input.as_os_str(),
+            OsStr::new("-ar"),
+            OsStr::new("16000"),
+            OsStr::new("-ac"),
+            OsStr::new("1"),
+            OsStr::new("-c:a"),
+            OsStr::new("pcm_s16le"),
+            output.as_os_str(),
+        ],
+    )
+    .await
+}
+
+async fn run_ffmpeg(ffmpeg: &str, args: &[&OsStr]) -> Result<()> {
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to launch {ffmpeg}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{ffmpeg} exited with {}: {}",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
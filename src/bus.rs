@@ -1,54 +1,172 @@
-use std::sync::Arc;
+use crate::channels::ChannelCapabilities;
+use crate::outbox::Outbox;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use tokio::sync::{broadcast, mpsc, Mutex};
 use tracing::warn;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct InboundMessage {
     pub channel: String,
     pub chat_id: String,
     pub sender_id: String,
+    /// Human-readable sender name, shown to the model in group chats.
+    pub sender_name: Option<String>,
     pub content: String,
+    /// Platform id of this message, used to thread the reply under it.
+    pub message_id: Option<String>,
+    /// Platform id of the message this one replies to.
+    pub reply_to: Option<String>,
+    /// Text of the replied-to message (or the selected quote), if any.
+    pub quoted_text: Option<String>,
+    /// Record the message as conversation context without replying to it
+    /// (group chats in "listen" mode when the bot is not addressed).
+    pub observe_only: bool,
+    /// Honor the built-in `/reset`, `/status` and `/model` commands. Only
+    /// set where the user deliberately issues commands: slash commands,
+    /// direct messages and local interfaces.
+    pub session_commands: bool,
+    /// A one-off request whose conversation is never continued: answer it
+    /// without keeping history or summarizing it into memory.
+    pub ephemeral: bool,
+    /// Tools this turn may call; `None` allows every enabled tool. Calls to
+    /// any other tool are refused without running it.
+    pub allowed_tools: Option<Vec<String>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OutboundMessage {
     pub channel: String,
     pub chat_id: String,
     pub content: String,
+    /// Files to deliver alongside (or instead of) `content`.
+    pub attachments: Vec<OutboundAttachment>,
+    /// Platform message id to reply to, so the answer is threaded under the
+    /// message that triggered it.
+    pub reply_to: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutboundAttachment {
+    pub path: PathBuf,
+}
+
+impl OutboundAttachment {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "attachment".to_string())
+    }
+
+    /// Whether the attachment looks like an image that chat platforms can
+    /// render inline rather than as a generic document.
+    pub fn is_image(&self) -> bool {
+        let ext = self
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp")
+    }
+
+    /// MIME type guessed from the file extension.
+    pub fn mime_type(&self) -> &'static str {
+        let ext = self
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "pdf" => "application/pdf",
+            "txt" => "text/plain",
+            "md" => "text/markdown",
+            "csv" => "text/csv",
+            "json" => "application/json",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+/// Progress of an agent turn, for channels that show it live. Events are
+/// best-effort: they are not persisted and are dropped when nobody listens.
+#[derive(Clone, Debug)]
+pub struct AgentEvent {
+    pub channel: String,
+    pub chat_id: String,
+    pub kind: AgentEventKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum AgentEventKind {
+    ToolCall {
+        name: String,
+        args: String,
+    },
+    ToolResult {
+        name: String,
+        result: String,
+    },
+    /// A turn finished; `fallback` is set when the first-choice route failed.
+    TurnCompleted {
+        route: String,
+        fallback: bool,
+        input_tokens: u64,
+        output_tokens: u64,
+    },
+}
+
+/// Capacity of each channel's outbound sink. A full sink only slows down
+/// publishers to that channel.
+const CHANNEL_SINK_CAPACITY: usize = 100;
+/// Agent events buffered per subscriber; slow subscribers skip old ones.
+const AGENT_EVENT_CAPACITY: usize = 256;
+
+#[derive(Clone)]
+struct ChannelSink {
+    tx: mpsc::Sender<OutboundMessage>,
+    capabilities: ChannelCapabilities,
 }
 
 #[derive(Clone)]
 pub struct MessageBus {
     inbound_tx: mpsc::Sender<InboundMessage>,
-    outbound_tx: mpsc::Sender<OutboundMessage>,
     inbound_rx: Arc<Mutex<mpsc::Receiver<InboundMessage>>>,
-    outbound_broadcast_tx: broadcast::Sender<OutboundMessage>,
+    /// Outbound sinks of the registered channel adapters, keyed by channel
+    /// name.
+    sinks: Arc<RwLock<HashMap<String, ChannelSink>>>,
+    /// When set, outbound messages are persisted and delivered by the
+    /// outbox's per-channel workers; the sinks are only a fallback.
+    outbox: Option<Outbox>,
+    events: broadcast::Sender<AgentEvent>,
 }
 
 impl MessageBus {
     pub fn new() -> Self {
         let (inbound_tx, inbound_rx) = mpsc::channel(100);
-        let (outbound_tx, mut outbound_rx) = mpsc::channel(100);
-        let (outbound_broadcast_tx, _) = broadcast::channel(100);
-
-        let inbound_rx = Arc::new(Mutex::new(inbound_rx));
-
-        let bus = MessageBus {
+        MessageBus {
             inbound_tx,
-            outbound_tx,
-            inbound_rx: inbound_rx.clone(),
-            outbound_broadcast_tx: outbound_broadcast_tx.clone(),
-        };
-
-        tokio::spawn(async move {
-            while let Some(msg) = outbound_rx.recv().await {
-                if let Err(e) = outbound_broadcast_tx.send(msg) {
-                    warn!("Failed to broadcast outbound message: {e}");
-                }
-            }
-        });
+            inbound_rx: Arc::new(Mutex::new(inbound_rx)),
+            sinks: Arc::new(RwLock::new(HashMap::new())),
+            outbox: None,
+            events: broadcast::channel(AGENT_EVENT_CAPACITY).0,
+        }
+    }
 
-        bus
+    /// A bus whose outbound messages go through the durable `outbox`.
+    pub fn with_outbox(outbox: Outbox) -> Self {
+        Self {
+            outbox: Some(outbox),
+            ..Self::new()
+        }
     }
 
     pub async fn publish_inbound(&self, msg: InboundMessage) {
@@ -57,10 +175,49 @@ impl MessageBus {
         }
     }
 
-    pub async fn publish_outbound(&self, msg: OutboundMessage) {
-        if let Err(e) = self.outbound_tx.send(msg).await {
-            warn!("Failed to publish outbound message: {e}");
+    /// Route `msg` to its channel. Fails when no adapter is registered under
+    /// `msg.channel`, the adapter has shut down, or it cannot deliver the
+    /// message's attachments.
+    pub async fn publish_outbound(&self, msg: OutboundMessage) -> Result<()> {
+        let sink = self.sink(&msg.channel).ok_or_else(|| {
+            let available = self.channels();
+            anyhow!(
+                "unknown channel '{}' (available: {})",
+                msg.channel,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })?;
+        if !msg.attachments.is_empty() && !sink.capabilities.attachments {
+            return Err(anyhow!(
+                "channel '{}' does not support attachments",
+                msg.channel
+            ));
+        }
+        if let Some(outbox) = &self.outbox {
+            match outbox.enqueue(&msg).await {
+                Ok(_) => return Ok(()),
+                Err(e) => warn!("Failed to persist outbound message, delivering in-memory: {e}"),
+            }
         }
+        let channel = msg.channel.clone();
+        sink.tx
+            .send(msg)
+            .await
+            .map_err(|_| anyhow!("channel '{channel}' is no longer running"))
+    }
+
+    pub fn publish_event(&self, event: AgentEvent) {
+        // No subscribers is the common case and not an error.
+        let _ = self.events.send(event);
+    }
+
+    /// Live agent events for all channels; subscribers filter by channel.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<AgentEvent> {
+        self.events.subscribe()
     }
 
     pub async fn consume_inbound(&self) -> Option<InboundMessage> {
@@ -68,7 +225,148 @@ impl MessageBus {
         rx.recv().await
     }
 
-    pub fn subscribe_outbound(&self) -> broadcast::Receiver<OutboundMessage> {
-        self.outbound_broadcast_tx.subscribe()
+    /// Names of the registered channels, sorted.
+    pub fn channels(&self) -> Vec<String> {
+        let sinks = self.sinks.read().unwrap_or_else(|e| e.into_inner());
+        let mut names = sinks.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn sink(&self, channel: &str) -> Option<ChannelSink> {
+        let sinks = self.sinks.read().unwrap_or_else(|e| e.into_inner());
+        sinks
+            .get(channel)
+            .filter(|sink| !sink.tx.is_closed())
+            .cloned()
+    }
+
+    /// Register `channel` and return the receiving end of its outbound sink.
+    /// Registering a name again replaces the previous sink.
+    pub fn register_channel(
+        &self,
+        channel: &str,
+        capabilities: ChannelCapabilities,
+    ) -> mpsc::Receiver<OutboundMessage> {
+        let (tx, rx) = mpsc::channel(CHANNEL_SINK_CAPACITY);
+        let mut sinks = self.sinks.write().unwrap_or_else(|e| e.into_inner());
+        sinks.insert(channel.to_string(), ChannelSink { tx, capabilities });
+        rx
+    }
+
+    /// Remove `channel`, e.g. after its adapter failed to start. Its worker
+    /// stops and replies queued for it are dead-lettered, so they show up in
+    /// `outbox list --status dead` and can be replayed once it runs again.
+    pub fn unregister_channel(&self, channel: &str) {
+        let mut sinks = self.sinks.write().unwrap_or_else(|e| e.into_inner());
+        sinks.remove(channel);
+        if let Some(outbox) = self.outbox.clone() {
+            let channel = channel.to_string();
+            tokio::spawn(async move {
+                let reason = format!("channel '{channel}' failed to start");
+                match outbox.retire_channel(&channel, &reason).await {
+                    Ok(0) => {}
+                    Ok(count) => warn!("dead-lettered {count} queued {channel} replies: {reason}"),
+                    Err(err) => warn!("{channel} outbox update failed: {err}"),
+                }
+            });
+        }
+    }
+
+    /// Dead-letter queued replies for channels that are not registered, e.g.
+    /// ones removed from the config since the last run.
+    pub async fn dead_letter_unserved(&self) {
+        let Some(outbox) = &self.outbox else {
+            return;
+        };
+        match outbox.dead_letter_unserved(&self.channels()).await {
+            Ok(0) => {}
+            Ok(count) => {
+                warn!("dead-lettered {count} queued replies for channels that are not running")
+            }
+            Err(err) => warn!("outbox cleanup failed: {err}"),
+        }
+    }
+
+    /// Register `channel` and deliver its outbound messages through
+    /// `deliver`. With an outbox, failed deliveries are retried with backoff;
+    /// messages that reach the in-memory sink are delivered once and errors
+    /// are logged.
+    pub fn spawn_delivery_worker<F, Fut>(
+        &self,
+        channel: &str,
+        capabilities: ChannelCapabilities,
+        deliver: F,
+    ) where
+        F: Fn(OutboundMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let mut rx = self.register_channel(channel, capabilities);
+        let deliver = Arc::new(deliver);
+        if let Some(outbox) = &self.outbox {
+            let deliver = deliver.clone();
+            outbox.spawn_worker(channel, move |msg| deliver(msg));
+        }
+        let channel = channel.to_string();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let Err(e) = deliver(msg).await {
+                    warn!("{channel} delivery failed: {e:#}");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageBus, OutboundAttachment, OutboundMessage};
+    use crate::channels::ChannelCapabilities;
+
+    fn message(channel: &str) -> OutboundMessage {
+        OutboundMessage {
+            channel: channel.to_string(),
+            chat_id: "42".to_string(),
+            content: "hi".to_string(),
+            attachments: Vec::new(),
+            reply_to: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_outbound_only_to_the_addressed_channel() {
+        let bus = MessageBus::new();
+        let mut telegram = bus.register_channel("telegram", ChannelCapabilities::default());
+        let mut discord = bus.register_channel("discord", ChannelCapabilities::default());
+
+        bus.publish_outbound(message("telegram")).await.unwrap();
+        assert_eq!(telegram.recv().await.unwrap().channel, "telegram");
+        assert!(discord.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_channels_are_an_error() {
+        let bus = MessageBus::new();
+        let _tui = bus.register_channel("tui", ChannelCapabilities::default());
+
+        let err = bus
+            .publish_outbound(message("slack"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "unknown channel 'slack' (available: tui)");
+    }
+
+    #[tokio::test]
+    async fn attachments_require_channel_support() {
+        let bus = MessageBus::new();
+        let _text_only = bus.register_channel("sms", ChannelCapabilities::default());
+
+        let mut msg = message("sms");
+        msg.attachments.push(OutboundAttachment {
+            path: "report.pdf".into(),
+        });
+        let err = bus.publish_outbound(msg).await.unwrap_err().to_string();
+        assert_eq!(err, "channel 'sms' does not support attachments");
     }
 }
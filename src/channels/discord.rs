@@ -1,51 +1,128 @@
-use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundAttachment, OutboundMessage};
+use crate::channels::{self, Allowlist, ChannelCapabilities};
 use crate::config::AppConfig;
+use crate::transcription::Transcriber;
 use anyhow::{anyhow, Result};
+use dashmap::DashMap;
 use serenity::async_trait;
+use serenity::builder::{
+    CreateAttachment, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateThread, EditInteractionResponse,
+};
+use serenity::gateway::ShardManager;
 use serenity::http::Http;
-use serenity::model::channel::Message as DiscordMessage;
+use serenity::model::application::{
+    Command, CommandInteraction, CommandOptionType, Interaction, ResolvedValue,
+};
+use serenity::model::channel::{Attachment, Channel, Message as DiscordMessage, MessageReference};
 use serenity::model::gateway::Ready;
-use serenity::model::id::ChannelId;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use serenity::model::user::User;
 use serenity::prelude::*;
 use std::collections::HashSet;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
-const DISCORD_MESSAGE_LIMIT: usize = 2000;
+const CAPABILITIES: ChannelCapabilities = ChannelCapabilities {
+    max_message_chars: Some(2000),
+    attachments: true,
+};
+/// Longest prompt echoed when a slash command starts a thread.
+const QUOTE_LINE_CHARS: usize = 1000;
+/// Discord thread names are capped at 100 characters.
+const THREAD_NAME_CHARS: usize = 80;
+/// Interaction tokens expire after 15 minutes; answer normally after that.
+const INTERACTION_TTL: Duration = Duration::from_secs(14 * 60);
 
-pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
-    let token = cfg.channels.discord.bot_token.trim().to_string();
-    if token.is_empty() {
-        return Err(anyhow!("discord token is missing"));
+/// Deferred slash-command interactions awaiting the agent's reply, keyed by
+/// interaction id (sent as the inbound `message_id`, returned as `reply_to`).
+type PendingInteractions = Arc<DashMap<u64, (Instant, CommandInteraction)>>;
+
+/// Discord transport over the gateway.
+pub struct DiscordChannel {
+    cfg: AppConfig,
+    http: Arc<Http>,
+    pending: PendingInteractions,
+    /// Set once the gateway client is built; used to disconnect on shutdown.
+    shard_manager: OnceLock<Arc<ShardManager>>,
+}
+
+impl DiscordChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            http: Arc::new(Http::new(cfg.channels.discord.bot_token.trim())),
+            pending: Arc::new(DashMap::new()),
+            shard_manager: OnceLock::new(),
+        }
     }
+}
 
-    let intents = GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::DIRECT_MESSAGES
-        | GatewayIntents::MESSAGE_CONTENT;
+#[async_trait]
+impl channels::Channel for DiscordChannel {
+    fn name(&self) -> &'static str {
+        "discord"
+    }
 
-    let handler = DiscordHandler::new(&cfg, bus.clone());
-    let mut client = Client::builder(token, intents)
-        .event_handler(handler)
-        .await
-        .map_err(|err| anyhow!("discord client initialization failed: {err}"))?;
+    fn capabilities(&self) -> ChannelCapabilities {
+        CAPABILITIES
+    }
 
-    spawn_outbound_forwarder(client.http.clone(), bus.subscribe_outbound());
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let token = self.cfg.channels.discord.bot_token.trim().to_string();
+        if token.is_empty() {
+            return Err(anyhow!("discord token is missing"));
+        }
 
-    client
-        .start()
-        .await
-        .map_err(|err| anyhow!("discord runtime error: {err}"))?;
-    Ok(())
+        let intents = GatewayIntents::GUILDS
+            | GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT;
+
+        let handler = DiscordHandler::new(&self.cfg, bus, self.pending.clone());
+        let mut client = Client::builder(token, intents)
+            .event_handler(handler)
+            .await
+            .map_err(|err| anyhow!("discord client initialization failed: {err}"))?;
+        let _ = self.shard_manager.set(client.shard_manager.clone());
+
+        client
+            .start()
+            .await
+            .map_err(|err| anyhow!("discord runtime error: {err}"))?;
+        Ok(())
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        deliver_discord(&self.http, &self.pending, msg).await
+    }
+
+    async fn shutdown(&self) {
+        if let Some(shard_manager) = self.shard_manager.get() {
+            shard_manager.shutdown_all().await;
+        }
+    }
 }
 
 struct DiscordHandler {
     bus: MessageBus,
     allowed_channels: HashSet<u64>,
-    allow_from: Vec<String>,
+    allow_from: Allowlist,
+    transcriber: Option<Transcriber>,
+    threads: bool,
+    pending: PendingInteractions,
+}
+
+/// What we need to know about a thread the message was posted in.
+#[derive(Clone, Copy)]
+struct ThreadInfo {
+    parent_id: Option<ChannelId>,
+    owner_id: Option<UserId>,
 }
 
 impl DiscordHandler {
-    fn new(cfg: &AppConfig, bus: MessageBus) -> Self {
+    fn new(cfg: &AppConfig, bus: MessageBus, pending: PendingInteractions) -> Self {
         let allowed_channels = cfg
             .channels
             .discord
@@ -53,145 +130,600 @@ impl DiscordHandler {
             .iter()
             .filter_map(|raw| raw.trim().parse::<u64>().ok())
             .collect::<HashSet<_>>();
-        let allow_from = cfg
-            .channels
-            .discord
-            .allow_from
-            .iter()
-            .map(|entry| entry.trim().to_ascii_lowercase())
-            .filter(|entry| !entry.is_empty())
-            .collect::<Vec<_>>();
+        let allow_from = Allowlist::new(&cfg.channels.discord.allow_from);
         Self {
             bus,
             allowed_channels,
             allow_from,
+            transcriber: Transcriber::from_config(cfg),
+            threads: cfg.channels.discord.threads,
+            pending,
         }
     }
 
-    fn is_channel_allowed(&self, msg: &DiscordMessage) -> bool {
-        if self.allowed_channels.is_empty() || msg.guild_id.is_none() {
+    /// Guild channels must be allowlisted; a thread inherits its parent's
+    /// permission.
+    fn is_channel_allowed(&self, channel_id: ChannelId, parent_id: Option<ChannelId>) -> bool {
+        if self.allowed_channels.is_empty() {
             return true;
         }
-        self.allowed_channels.contains(&msg.channel_id.get())
+        self.allowed_channels.contains(&channel_id.get())
+            || parent_id.is_some_and(|parent| self.allowed_channels.contains(&parent.get()))
     }
 
-    fn is_sender_allowed(&self, msg: &DiscordMessage) -> bool {
-        if self.allow_from.is_empty() {
-            return true;
+    fn is_sender_allowed(&self, user: &User) -> bool {
+        self.allow_from
+            .allows(&user.id.get().to_string(), Some(&user.name))
+    }
+}
+
+impl DiscordHandler {
+    /// Download and transcribe an audio attachment, replying with the same
+    /// errors as the Telegram channel when that is not possible.
+    async fn transcribe_attachment(
+        &self,
+        ctx: &Context,
+        msg: &DiscordMessage,
+        attachment: &Attachment,
+    ) -> Option<String> {
+        let reply = |text: String| async move {
+            if let Err(err) = msg.channel_id.say(&ctx.http, text).await {
+                warn!("discord reply failed for channel {}: {err}", msg.channel_id);
+            }
+        };
+
+        let cache_key = format!("discord:{}", attachment.id);
+        let transcriber = self.transcriber.as_ref();
+        let cached = transcriber.and_then(|t| t.cached(&cache_key));
+        if let Some(problem) =
+            transcription_problem(transcriber, cached.is_some(), attachment.size as usize)
+        {
+            reply(problem).await;
+            return None;
+        }
+        let transcriber = transcriber?;
+
+        let transcript = match cached {
+            Some(transcript) => Ok(transcript),
+            None => match attachment.download().await {
+                Ok(data) => {
+                    transcriber
+                        .transcribe(Some(&cache_key), attachment.filename.clone(), data)
+                        .await
+                }
+                Err(err) => {
+                    warn!("audio download failed: {err}");
+                    reply("I couldn't download that audio message from Discord.".to_string()).await;
+                    return None;
+                }
+            },
+        };
+
+        match transcript {
+            Ok(transcript) if !transcript.is_empty() => {
+                if let Some(echo) = transcriber.echo_text(&transcript) {
+                    reply(echo).await;
+                }
+                Some(transcript)
+            }
+            Ok(_) => {
+                reply("I couldn't extract text from that audio message.".to_string()).await;
+                None
+            }
+            Err(err) => {
+                warn!("audio transcription failed: {err}");
+                reply(
+                    "I couldn't transcribe that audio message. Please retry or send text."
+                        .to_string(),
+                )
+                .await;
+                None
+            }
         }
-        let uid = msg.author.id.get().to_string();
-        let uname = msg.author.name.to_ascii_lowercase();
-        let mention = format!("<@{}>", msg.author.id.get());
-        self.allow_from.iter().any(|allowed| {
-            allowed == &uid
-                || allowed == &uname
-                || allowed == &format!("@{uname}")
-                || allowed == &mention
-        })
     }
 }
 
+/// The id and text of the message `msg` replies to, if any.
+fn reply_reference(msg: &DiscordMessage) -> (Option<String>, Option<String>) {
+    let Some(referenced) = msg.referenced_message.as_ref() else {
+        return (None, None);
+    };
+    let quoted = Some(referenced.content.clone()).filter(|content| !content.trim().is_empty());
+    (Some(referenced.id.get().to_string()), quoted)
+}
+
+/// Why an audio attachment cannot be transcribed, worded as a reply.
+fn transcription_problem(
+    transcriber: Option<&Transcriber>,
+    cached: bool,
+    size: usize,
+) -> Option<String> {
+    let Some(transcriber) = transcriber else {
+        return Some("Voice/audio transcription is not configured.".to_string());
+    };
+    if !cached && size > transcriber.max_input_bytes() {
+        return Some(format!(
+            "Audio file is too large ({} bytes). Max allowed is {} bytes.",
+            size,
+            transcriber.max_input_bytes()
+        ));
+    }
+    None
+}
+
+fn is_audio_attachment(attachment: &Attachment) -> bool {
+    if attachment.duration_secs.is_some() || attachment.waveform.is_some() {
+        return true;
+    }
+    if let Some(content_type) = &attachment.content_type {
+        return content_type.starts_with("audio/");
+    }
+    let ext = std::path::Path::new(&attachment.filename)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    matches!(
+        ext.as_str(),
+        "ogg" | "oga" | "opus" | "mp3" | "m4a" | "wav" | "flac"
+    )
+}
+
 #[async_trait]
 impl EventHandler for DiscordHandler {
     async fn message(&self, ctx: Context, msg: DiscordMessage) {
-        if msg.author.bot {
+        if msg.author.bot || !self.is_sender_allowed(&msg.author) {
             return;
         }
-        if !self.is_channel_allowed(&msg) || !self.is_sender_allowed(&msg) {
+
+        let in_guild = msg.guild_id.is_some();
+        let directly_allowed = self.allowed_channels.is_empty()
+            || self.allowed_channels.contains(&msg.channel_id.get());
+        let thread = if in_guild && (self.threads || !directly_allowed) {
+            thread_info(&ctx, msg.channel_id).await
+        } else {
+            None
+        };
+        if in_guild && !self.is_channel_allowed(msg.channel_id, thread.and_then(|t| t.parent_id)) {
             return;
         }
 
-        let text = msg.content.trim().to_string();
-        if text.is_empty() {
+        let mut text = msg.content.trim().to_string();
+        let audio = msg.attachments.iter().find(|a| is_audio_attachment(a));
+        if text.is_empty() && audio.is_none() {
             return;
         }
 
-        if msg.guild_id.is_some() {
+        if in_guild {
             let bot_id = ctx.cache.current_user().id;
             let mentioned = msg.mentions.iter().any(|user| user.id == bot_id);
-            if !mentioned {
+            // Voice messages cannot carry a mention, so a reply to the bot counts.
+            let replied_to_bot = msg
+                .referenced_message
+                .as_ref()
+                .is_some_and(|referenced| referenced.author.id == bot_id);
+            let in_bot_thread = thread.is_some_and(|t| t.owner_id == Some(bot_id));
+            if !mentioned && !replied_to_bot && !in_bot_thread {
                 return;
             }
         }
 
-        let _typing = msg.channel_id.start_typing(&ctx.http);
+        // Each new conversation in a guild channel gets its own thread, and
+        // with it its own session.
+        let mut chat_channel = msg.channel_id;
+        if self.threads && in_guild && thread.is_none() {
+            let name = thread_name(&text, msg.author.display_name());
+            match msg
+                .channel_id
+                .create_thread_from_message(&ctx.http, msg.id, CreateThread::new(name))
+                .await
+            {
+                Ok(created) => chat_channel = created.id,
+                Err(err) => warn!(
+                    "failed to start discord thread in channel {}: {err}",
+                    msg.channel_id
+                ),
+            }
+        }
+        let started_thread = chat_channel != msg.channel_id;
+
+        let _typing = chat_channel.start_typing(&ctx.http);
+
+        if let Some(attachment) = audio {
+            let Some(transcript) = self.transcribe_attachment(&ctx, &msg, attachment).await else {
+                return;
+            };
+            text = if text.is_empty() {
+                transcript
+            } else {
+                format!("{text}\n\n{transcript}")
+            };
+        }
 
+        let (reply_to, quoted_text) = reply_reference(&msg);
         self.bus
             .publish_inbound(InboundMessage {
                 channel: "discord".to_string(),
-                chat_id: msg.channel_id.get().to_string(),
+                chat_id: chat_channel.get().to_string(),
                 sender_id: msg.author.id.get().to_string(),
+                sender_name: Some(msg.author.display_name().to_string()),
                 content: text,
+                // The starter message lives in the parent channel, so a reply
+                // reference from inside the new thread would not resolve.
+                message_id: (!started_thread).then(|| msg.id.get().to_string()),
+                reply_to,
+                quoted_text,
+                ..Default::default()
             })
             .await;
     }
 
-    async fn ready(&self, _ctx: Context, ready: Ready) {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Command(command) = interaction else {
+            return;
+        };
+        if let Err(err) = self.handle_command(&ctx, &command).await {
+            warn!("discord /{} failed: {err}", command.data.name);
+        }
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
         info!("discord connected as {}", ready.user.name);
+        if let Err(err) = Command::set_global_commands(&ctx.http, slash_commands()).await {
+            warn!("failed to register discord slash commands: {err}");
+        }
     }
 }
 
-fn spawn_outbound_forwarder(
-    http: Arc<Http>,
-    mut rx: tokio::sync::broadcast::Receiver<OutboundMessage>,
-) {
-    tokio::spawn(async move {
-        loop {
-            let msg = match rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    info!("outbound channel closed, discord forwarder shutting down");
-                    break;
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    warn!("discord outbound lagged, skipped {skipped} message(s)");
-                    continue;
-                }
-            };
+impl DiscordHandler {
+    /// Handle `/ask`, `/reset` and `/status`. The interaction is deferred and
+    /// the agent's reply is delivered by editing it (see the forwarder).
+    async fn handle_command(
+        &self,
+        ctx: &Context,
+        command: &CommandInteraction,
+    ) -> serenity::Result<()> {
+        let respond = |text: &str| {
+            command.create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(text)
+                        .ephemeral(true),
+                ),
+            )
+        };
+
+        if !self.is_sender_allowed(&command.user) {
+            return respond("You are not allowed to use this bot.").await;
+        }
+        let thread_parent = command
+            .channel
+            .as_ref()
+            .filter(|channel| channel.thread_metadata.is_some())
+            .map(|channel| channel.parent_id);
+        if command.guild_id.is_some()
+            && !self.is_channel_allowed(command.channel_id, thread_parent.flatten())
+        {
+            return respond("This channel is not enabled for lightclaw.").await;
+        }
 
-            if msg.channel != "discord" {
-                continue;
+        let content = match command.data.name.as_str() {
+            "ask" => {
+                let prompt = command.data.options().into_iter().find_map(|option| {
+                    match (option.name, option.value) {
+                        ("prompt", ResolvedValue::String(prompt)) => {
+                            Some(prompt.trim().to_string())
+                        }
+                        _ => None,
+                    }
+                });
+                match prompt.filter(|prompt| !prompt.is_empty()) {
+                    Some(prompt) => prompt,
+                    None => return respond("Usage: /ask prompt:<question>").await,
+                }
             }
+            "reset" => "/reset".to_string(),
+            "status" => "/status".to_string(),
+            _ => return Ok(()),
+        };
 
-            let Ok(raw_channel_id) = msg.chat_id.parse::<u64>() else {
-                warn!("invalid discord chat_id: {}", msg.chat_id);
-                continue;
-            };
+        command.defer(&ctx.http).await?;
 
-            if let Err(err) =
-                send_discord_message(&http, ChannelId::new(raw_channel_id), &msg.content).await
-            {
-                warn!("discord send failed for channel {}: {err}", msg.chat_id);
-            }
+        let mut chat_channel = command.channel_id;
+        let start_thread = command.data.name == "ask"
+            && self.threads
+            && command.guild_id.is_some()
+            && thread_parent.is_none();
+        if start_thread {
+            let response = command.get_response(&ctx.http).await?;
+            let name = thread_name(&content, command.user.display_name());
+            let thread = command
+                .channel_id
+                .create_thread_from_message(&ctx.http, response.id, CreateThread::new(name))
+                .await?;
+            command
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().content(format!("> {}", quote_line(&content))),
+                )
+                .await?;
+            chat_channel = thread.id;
+        } else {
+            self.pending
+                .insert(command.id.get(), (Instant::now(), command.clone()));
         }
-    });
+
+        self.bus
+            .publish_inbound(InboundMessage {
+                channel: "discord".to_string(),
+                chat_id: chat_channel.get().to_string(),
+                sender_id: command.user.id.get().to_string(),
+                sender_name: Some(command.user.display_name().to_string()),
+                content,
+                // The reply carries this id back, so it answers this command.
+                message_id: (!start_thread).then(|| command.id.get().to_string()),
+                session_commands: true,
+                ..Default::default()
+            })
+            .await;
+        Ok(())
+    }
+}
+
+fn slash_commands() -> Vec<CreateCommand> {
+    vec![
+        CreateCommand::new("ask")
+            .description("Ask lightclaw a question")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "prompt", "Your question")
+                    .required(true),
+            ),
+        CreateCommand::new("reset").description("Clear the conversation history here"),
+        CreateCommand::new("status").description("Show model and session status"),
+    ]
+}
+
+/// Thread metadata for `channel_id`, or `None` when it is not a thread.
+async fn thread_info(ctx: &Context, channel_id: ChannelId) -> Option<ThreadInfo> {
+    match channel_id.to_channel(ctx).await {
+        Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some() => Some(ThreadInfo {
+            parent_id: channel.parent_id,
+            owner_id: channel.owner_id,
+        }),
+        Ok(_) => None,
+        Err(err) => {
+            warn!("failed to look up discord channel {channel_id}: {err}");
+            None
+        }
+    }
+}
+
+/// Thread title from the opening message, without user/role/channel mentions.
+fn thread_name(text: &str, author: &str) -> String {
+    let cleaned = text
+        .split_whitespace()
+        .filter(|word| !(word.starts_with("<@") || word.starts_with("<#")) || !word.ends_with('>'))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = if cleaned.is_empty() {
+        format!("Chat with {author}")
+    } else {
+        cleaned
+    };
+    if name.chars().count() <= THREAD_NAME_CHARS {
+        return name;
+    }
+    let mut truncated = name.chars().take(THREAD_NAME_CHARS - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+fn quote_line(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= QUOTE_LINE_CHARS {
+        return line;
+    }
+    let mut truncated = line.chars().take(QUOTE_LINE_CHARS).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Deliver one outbound message, answering the pending slash command it
+/// replies to when there is one. Errors are returned so the outbox can
+/// retry; a retry goes out as a regular message.
+async fn deliver_discord(
+    http: &Http,
+    pending: &PendingInteractions,
+    msg: OutboundMessage,
+) -> Result<()> {
+    let raw_channel_id = msg
+        .chat_id
+        .parse::<u64>()
+        .map_err(|_| anyhow!("invalid discord chat_id: {}", msg.chat_id))?;
+    let channel_id = ChannelId::new(raw_channel_id);
+    let reply_to = msg
+        .reply_to
+        .as_deref()
+        .and_then(|id| id.parse::<u64>().ok());
+    let interaction = if msg.content.trim().is_empty() {
+        None
+    } else {
+        reply_to
+            .and_then(|id| pending.remove(&id))
+            .map(|(_, pending)| pending)
+            .filter(|(created, _)| created.elapsed() < INTERACTION_TTL)
+            .map(|(_, command)| command)
+    };
+    let reply_to = reply_to.map(MessageId::new);
+    if let Some(command) = interaction {
+        send_interaction_reply(http, &command, channel_id, &msg.content).await?;
+    } else if !msg.content.trim().is_empty() {
+        send_discord_message(http, channel_id, &msg.content, reply_to).await?;
+    }
+    if !msg.attachments.is_empty() {
+        send_discord_attachments(http, channel_id, &msg.attachments).await?;
+    }
+    Ok(())
 }
 
+/// Send `text` in chunks under Discord's limit. The first chunk is posted as a
+/// reply to `reply_to` when set; it still sends if that message is gone.
 async fn send_discord_message(
     http: &Http,
     channel_id: ChannelId,
     text: &str,
+    mut reply_to: Option<MessageId>,
 ) -> serenity::Result<()> {
-    if text.len() <= DISCORD_MESSAGE_LIMIT {
-        channel_id.say(http, text).await?;
+    let mut send = |chunk: String| {
+        let mut builder = CreateMessage::new().content(chunk);
+        if let Some(message_id) = reply_to.take() {
+            let mut reference = MessageReference::from((channel_id, message_id));
+            reference.fail_if_not_exists = Some(false);
+            builder = builder.reference_message(reference);
+        }
+        channel_id.send_message(http, builder)
+    };
+
+    for chunk in CAPABILITIES.split_message(text) {
+        send(chunk.to_string()).await?;
+    }
+    Ok(())
+}
+
+/// Answer a deferred slash command: the first chunk replaces the "thinking"
+/// placeholder and any remainder follows as regular messages.
+async fn send_interaction_reply(
+    http: &Http,
+    command: &CommandInteraction,
+    channel_id: ChannelId,
+    text: &str,
+) -> serenity::Result<()> {
+    let chunks = CAPABILITIES.split_message(text);
+    let Some((first, rest)) = chunks.split_first() else {
         return Ok(());
+    };
+    command
+        .edit_response(http, EditInteractionResponse::new().content(*first))
+        .await?;
+    for chunk in rest {
+        channel_id.say(http, *chunk).await?;
     }
+    Ok(())
+}
 
-    let mut remaining = text;
-    while !remaining.is_empty() {
-        let chunk_len = if remaining.len() <= DISCORD_MESSAGE_LIMIT {
-            remaining.len()
-        } else {
-            remaining[..DISCORD_MESSAGE_LIMIT]
-                .rfind('\n')
-                .unwrap_or(DISCORD_MESSAGE_LIMIT)
-        };
-        let chunk = &remaining[..chunk_len];
-        channel_id.say(http, chunk).await?;
-        remaining = &remaining[chunk_len..];
-        if remaining.starts_with('\n') {
-            remaining = &remaining[1..];
-        }
+async fn send_discord_attachments(
+    http: &Http,
+    channel_id: ChannelId,
+    attachments: &[OutboundAttachment],
+) -> serenity::Result<()> {
+    let mut files = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        files.push(CreateAttachment::path(&attachment.path).await?);
     }
+    channel_id
+        .send_files(http, files, CreateMessage::new())
+        .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_audio_attachment, reply_reference, thread_name, transcription_problem, Attachment,
+        DiscordMessage, CAPABILITIES,
+    };
+
+    fn attachment(filename: &str, content_type: Option<&str>) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "size": 2048,
+            "url": "https://cdn.example.com/file",
+            "proxy_url": "https://media.example.com/file",
+            "content_type": content_type,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn thread_names_drop_mentions_and_fit_discord_limit() {
+        assert_eq!(
+            thread_name("<@123456> plan my trip to Lisbon", "ana"),
+            "plan my trip to Lisbon"
+        );
+        assert_eq!(thread_name("<@123456>", "ana"), "Chat with ana");
+        let long = "word ".repeat(40);
+        assert_eq!(thread_name(&long, "ana").chars().count(), 80);
+    }
+
+    #[test]
+    fn splits_long_discord_messages_on_line_breaks() {
+        let line = "x".repeat(1500);
+        let text = format!("{line}\n{line}\n{line}");
+        let chunks = CAPABILITIES.split_message(&text);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|chunk| CAPABILITIES.fits(chunk)));
+        assert_eq!(CAPABILITIES.split_message("short"), vec!["short"]);
+    }
+
+    #[test]
+    fn detects_audio_by_content_type_then_extension() {
+        assert!(is_audio_attachment(&attachment(
+            "voice.bin",
+            Some("audio/ogg")
+        )));
+        assert!(!is_audio_attachment(&attachment(
+            "song.mp3",
+            Some("image/png")
+        )));
+        assert!(is_audio_attachment(&attachment("memo.M4A", None)));
+        assert!(!is_audio_attachment(&attachment("notes.txt", None)));
+    }
+
+    #[test]
+    fn audio_without_transcriber_gets_not_configured_reply() {
+        assert_eq!(
+            transcription_problem(None, false, 2048).as_deref(),
+            Some("Voice/audio transcription is not configured.")
+        );
+    }
+
+    fn message(id: u64, content: &str, referenced: Option<serde_json::Value>) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "channel_id": "10",
+            "author": { "id": "20", "username": "ana", "discriminator": "0000", "avatar": null },
+            "content": content,
+            "timestamp": "2026-01-01T00:00:00Z",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": [],
+            "pinned": false,
+            "type": if referenced.is_some() { 19 } else { 0 },
+            "referenced_message": referenced,
+        })
+    }
+
+    #[test]
+    fn extracts_reply_target_and_quoted_text() {
+        let original = message(1, "Deploy is at 5pm", None);
+        let reply: DiscordMessage =
+            serde_json::from_value(message(2, "make it 6", Some(original))).unwrap();
+        assert_eq!(
+            reply_reference(&reply),
+            (Some("1".to_string()), Some("Deploy is at 5pm".to_string()))
+        );
+
+        let empty = message(3, "  ", None);
+        let reply: DiscordMessage =
+            serde_json::from_value(message(4, "what?", Some(empty))).unwrap();
+        assert_eq!(reply_reference(&reply), (Some("3".to_string()), None));
+
+        let plain: DiscordMessage = serde_json::from_value(message(5, "hi", None)).unwrap();
+        assert_eq!(reply_reference(&plain), (None, None));
+    }
+}
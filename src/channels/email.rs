@@ -0,0 +1,686 @@
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::{Allowlist, Channel, ChannelCapabilities};
+use crate::config::{AppConfig, EmailConfig, SmtpSecurity};
+use anyhow::{anyhow, Result};
+use async_imap::Client as ImapClient;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures_util::TryStreamExt;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message as Email, Tokio1Executor};
+use mail_parser::{MessageParser, MimeHeaders};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::{info, warn};
+
+/// Separates the correspondent from the thread root in chat addresses.
+const THREAD_SEPARATOR: char = '|';
+const DEFAULT_SUBJECT: &str = "lightclaw";
+
+/// Email transport: polls an IMAP mailbox for unseen mail and replies over
+/// SMTP. Each thread, identified by the first Message-ID of its References
+/// chain, is its own session.
+pub struct EmailChannel {
+    cfg: EmailConfig,
+    allow_from: Allowlist,
+    /// Inbound attachments are saved here so the agent can open them.
+    attachments_dir: PathBuf,
+    /// Subject and References of each thread, keyed by chat address, so
+    /// replies thread correctly.
+    threads: DashMap<String, ThreadHeaders>,
+    stop: watch::Sender<bool>,
+}
+
+#[derive(Clone, Default)]
+struct ThreadHeaders {
+    subject: String,
+    /// Message-IDs of the thread, oldest first, without angle brackets.
+    references: Vec<String>,
+}
+
+impl EmailChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            cfg: cfg.channels.email.clone(),
+            allow_from: Allowlist::new(&cfg.channels.email.allow_from),
+            attachments_dir: cfg.workspace_dir.join("inbox").join("email"),
+            threads: DashMap::new(),
+            stop: watch::channel(false).0,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for EmailChannel {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: true,
+        }
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        if self.cfg.authserv_id.trim().is_empty() && !self.cfg.trust_unauthenticated {
+            return Err(anyhow!(
+                "channels.email.authserv_id is required to check senders; set it to the id \
+                 your mail server puts in Authentication-Results headers"
+            ));
+        }
+        let mut stop = self.stop.subscribe();
+        // A first failure is most likely bad settings; report it instead of
+        // retrying forever.
+        self.poll(&bus)
+            .await
+            .map_err(|err| anyhow!("email mailbox check failed: {err:#}"))?;
+        info!(
+            "email channel polling {} every {}s",
+            self.cfg.imap_host, self.cfg.poll_interval_secs
+        );
+
+        let interval = Duration::from_secs(self.cfg.poll_interval_secs.max(1));
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = stop.changed() => return Ok(()),
+            }
+            if let Err(err) = self.poll(&bus).await {
+                warn!("email poll failed: {err:#}");
+            }
+        }
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let (to, root) = parse_chat_address(&msg.chat_id);
+        let thread = self
+            .threads
+            .get(&msg.chat_id)
+            .map(|thread| thread.clone())
+            .unwrap_or_else(|| ThreadHeaders {
+                subject: DEFAULT_SUBJECT.to_string(),
+                references: root.map(str::to_string).into_iter().collect(),
+            });
+        let parent = msg.reply_to.clone().or(thread.references.last().cloned());
+        let message_id = new_message_id(self.cfg.sender_address());
+
+        let mut builder = Email::builder()
+            .from(self.cfg.sender_address().parse::<Mailbox>()?)
+            .to(to.parse::<Mailbox>()?)
+            .subject(reply_subject(&thread.subject))
+            .message_id(Some(format!("<{message_id}>")));
+        if let Some(parent) = &parent {
+            builder = builder.in_reply_to(format!("<{parent}>"));
+        }
+        let mut references = thread.references.clone();
+        if let Some(parent) = parent.filter(|parent| !references.contains(parent)) {
+            references.push(parent);
+        }
+        if !references.is_empty() {
+            builder = builder.references(
+                references
+                    .iter()
+                    .map(|id| format!("<{id}>"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+
+        let text = msg.content.trim().to_string();
+        let email = if msg.attachments.is_empty() {
+            builder.header(ContentType::TEXT_PLAIN).body(text)?
+        } else {
+            let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(text));
+            for attachment in &msg.attachments {
+                let data = tokio::fs::read(&attachment.path).await?;
+                let content_type = ContentType::parse(attachment.mime_type())?;
+                parts = parts
+                    .singlepart(Attachment::new(attachment.file_name()).body(data, content_type));
+            }
+            builder.multipart(parts)?
+        };
+
+        self.smtp()?.send(email).await?;
+
+        references.push(message_id);
+        self.threads.insert(
+            msg.chat_id,
+            ThreadHeaders {
+                subject: thread.subject,
+                references,
+            },
+        );
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+impl EmailChannel {
+    fn smtp(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        let host = self.cfg.smtp_host.trim();
+        let builder = match self.cfg.smtp_security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        Ok(builder
+            .port(self.cfg.smtp_port)
+            .credentials(Credentials::new(
+                self.cfg.username.trim().to_string(),
+                self.cfg.password.clone(),
+            ))
+            .build())
+    }
+
+    /// Fetch unseen mail, publish it and mark it seen.
+    async fn poll(&self, bus: &MessageBus) -> Result<()> {
+        let host = self.cfg.imap_host.trim();
+        let tcp = TcpStream::connect((host, self.cfg.imap_port)).await?;
+        if self.cfg.imap_tls {
+            let server_name = ServerName::try_from(host.to_string())?;
+            let tls = tls_connector()?.connect(server_name, tcp).await?;
+            self.fetch_unseen(ImapClient::new(tls), bus).await
+        } else {
+            self.fetch_unseen(ImapClient::new(tcp), bus).await
+        }
+    }
+
+    async fn fetch_unseen<T>(&self, mut client: ImapClient<T>, bus: &MessageBus) -> Result<()>
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Debug + Send,
+    {
+        client
+            .read_response()
+            .await
+            .ok_or_else(|| anyhow!("IMAP server closed the connection"))??;
+        let mut session = client
+            .login(self.cfg.username.trim(), &self.cfg.password)
+            .await
+            .map_err(|(err, _)| anyhow!("IMAP login failed: {err}"))?;
+        session.select(&self.cfg.mailbox).await?;
+
+        let mut uids = session
+            .uid_search("UNSEEN")
+            .await?
+            .into_iter()
+            .collect::<Vec<_>>();
+        uids.sort_unstable();
+        for uid in uids {
+            let fetches = session
+                .uid_fetch(uid.to_string(), "BODY.PEEK[]")
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+            for raw in fetches.iter().filter_map(|fetch| fetch.body()) {
+                self.handle_mail(bus, uid, raw).await;
+            }
+            session
+                .uid_store(uid.to_string(), "+FLAGS (\\Seen)")
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+        }
+        session.logout().await?;
+        Ok(())
+    }
+
+    async fn handle_mail(&self, bus: &MessageBus, uid: u32, raw: &[u8]) {
+        let Some(mail) = MessageParser::default().parse(raw) else {
+            warn!("ignoring unparseable email (uid {uid})");
+            return;
+        };
+        let Some(sender) = mail.from().and_then(|from| from.first()) else {
+            return;
+        };
+        let Some(sender_address) = sender.address().map(str::to_ascii_lowercase) else {
+            return;
+        };
+        if sender_address == self.cfg.sender_address().to_ascii_lowercase() {
+            return;
+        }
+        // Never answer vacation replies, bounces or mailing lists.
+        if mail
+            .header_raw("Auto-Submitted")
+            .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no"))
+            || mail.header_raw("Precedence").is_some_and(|value| {
+                matches!(
+                    value.trim().to_ascii_lowercase().as_str(),
+                    "bulk" | "junk" | "list"
+                )
+            })
+        {
+            return;
+        }
+        let domain = sender_address.rsplit_once('@').map(|(_, domain)| domain);
+        if !self.allow_from.allows(&sender_address, domain) {
+            info!("ignoring email from {sender_address}: not in allow_from");
+            return;
+        }
+        // The allowlist is only as good as the `From:` header, so require the
+        // receiving server to have verified it. Replies always go back to
+        // that address, never to a `Reply-To:` the sender picked.
+        let authserv_id = self.cfg.authserv_id.trim();
+        let verified = domain.is_some_and(|domain| {
+            mail.headers_raw().any(|(name, results)| {
+                name.eq_ignore_ascii_case("Authentication-Results")
+                    && sender_authenticated(results, authserv_id, domain)
+            })
+        });
+        if !verified && !self.cfg.trust_unauthenticated {
+            info!("ignoring email from {sender_address}: no passing DKIM or DMARC result");
+            return;
+        }
+
+        let message_id = mail
+            .message_id()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("uid-{uid}@{}", self.cfg.imap_host.trim()));
+        let references = mail
+            .references()
+            .as_text_list()
+            .map(|ids| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let in_reply_to = mail.in_reply_to().as_text().map(str::to_string);
+        let root = references
+            .first()
+            .cloned()
+            .or(in_reply_to.clone())
+            .unwrap_or_else(|| message_id.clone());
+        let chat_id = format!("{sender_address}{THREAD_SEPARATOR}{root}");
+
+        let subject = mail.subject().unwrap_or(DEFAULT_SUBJECT).trim().to_string();
+        let mut thread_references = references;
+        if let Some(parent) = in_reply_to
+            .as_ref()
+            .filter(|parent| !thread_references.contains(parent))
+        {
+            thread_references.push(parent.clone());
+        }
+        thread_references.push(message_id.clone());
+        self.threads.insert(
+            chat_id.clone(),
+            ThreadHeaders {
+                subject: subject.clone(),
+                references: thread_references,
+            },
+        );
+
+        let body = mail.body_text(0).unwrap_or_default();
+        let (body, quoted_text) = strip_quoted_reply(&body);
+        let mut content = if in_reply_to.is_none() && !subject.is_empty() {
+            format!("Subject: {subject}\n\n{body}")
+        } else {
+            body
+        };
+        for (index, attachment) in mail.attachments().enumerate() {
+            let name = attachment
+                .attachment_name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("attachment-{}", index + 1));
+            match save_attachment(&self.attachments_dir, uid, &name, attachment.contents()).await {
+                Ok(path) => {
+                    content.push_str(&format!("\n[attachment saved to {}]", path.display()))
+                }
+                Err(err) => warn!("failed to save email attachment {name}: {err}"),
+            }
+        }
+        let content = content.trim().to_string();
+        if content.is_empty() {
+            return;
+        }
+
+        bus.publish_inbound(InboundMessage {
+            channel: "email".to_string(),
+            chat_id,
+            sender_id: sender_address,
+            sender_name: sender.name().map(str::to_string),
+            content,
+            message_id: Some(message_id),
+            reply_to: in_reply_to,
+            quoted_text,
+            ..Default::default()
+        })
+        .await;
+    }
+}
+
+fn tls_connector() -> Result<TlsConnector> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Correspondent address and thread root of a chat address.
+fn parse_chat_address(address: &str) -> (&str, Option<&str>) {
+    match address.split_once(THREAD_SEPARATOR) {
+        Some((to, root)) => (to, Some(root)),
+        None => (address, None),
+    }
+}
+
+/// Whether an `Authentication-Results` header stamped by `authserv_id` (our
+/// own mail server) reports a DKIM signature by `domain`, or a parent of it,
+/// or a DMARC pass for it.
+fn sender_authenticated(results: &str, authserv_id: &str, domain: &str) -> bool {
+    let mut results = results.split(';');
+    let stamped_by = results
+        .next()
+        .and_then(|id| id.split_whitespace().next())
+        .unwrap_or("");
+    if authserv_id.is_empty() || !stamped_by.eq_ignore_ascii_case(authserv_id) {
+        return false;
+    }
+    let aligned = |value: &str| {
+        let value = value.trim_matches('"').trim_start_matches('@');
+        let value = value.rsplit_once('@').map_or(value, |(_, domain)| domain);
+        domain.eq_ignore_ascii_case(value)
+            || domain
+                .to_ascii_lowercase()
+                .ends_with(&format!(".{}", value.to_ascii_lowercase()))
+    };
+    results.any(|result| {
+        let mut tokens = result.split_whitespace();
+        let method = tokens.next().unwrap_or("").to_ascii_lowercase();
+        let mut properties = tokens.filter_map(|token| token.split_once('='));
+        match method.as_str() {
+            "dkim=pass" => properties
+                .any(|(key, value)| key.eq_ignore_ascii_case("header.d") && aligned(value)),
+            "dmarc=pass" => properties.any(|(key, value)| {
+                key.eq_ignore_ascii_case("header.from") && domain.eq_ignore_ascii_case(value)
+            }),
+            _ => false,
+        }
+    })
+}
+
+fn reply_subject(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject
+        .get(..3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:"))
+    {
+        subject.to_string()
+    } else {
+        format!("Re: {subject}")
+    }
+}
+
+fn new_message_id(from_address: &str) -> String {
+    let domain = from_address
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .filter(|domain| !domain.is_empty())
+        .unwrap_or("lightclaw.local");
+    format!("{}@{domain}", uuid::Uuid::new_v4())
+}
+
+/// Split the new text of a reply from the quoted previous message: the
+/// trailing `>` block and the "On ... wrote:" line introducing it.
+fn strip_quoted_reply(body: &str) -> (String, Option<String>) {
+    let lines = body.lines().collect::<Vec<_>>();
+    let Some(first_quoted) = lines.iter().position(|line| line.starts_with('>')) else {
+        return (body.trim().to_string(), None);
+    };
+    if !lines[first_quoted..]
+        .iter()
+        .all(|line| line.starts_with('>') || line.trim().is_empty())
+    {
+        return (body.trim().to_string(), None);
+    }
+    let mut end = first_quoted;
+    while end > 0 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    if end > 0 && lines[end - 1].trim_end().ends_with("wrote:") {
+        end -= 1;
+    }
+    let quoted = lines[first_quoted..]
+        .iter()
+        .map(|line| line.trim_start_matches('>').trim_start())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (
+        lines[..end].join("\n").trim().to_string(),
+        Some(quoted.trim().to_string()).filter(|quoted| !quoted.is_empty()),
+    )
+}
+
+async fn save_attachment(dir: &Path, uid: u32, name: &str, data: &[u8]) -> Result<PathBuf> {
+    let file_name = Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "attachment".to_string());
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("{uid}-{file_name}"));
+    tokio::fs::write(&path, data).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reply_subject, sender_authenticated, strip_quoted_reply, EmailChannel};
+    use crate::bus::{MessageBus, OutboundMessage};
+    use crate::channels::Channel;
+    use crate::config::{AppConfig, SmtpSecurity};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn separates_new_text_from_quoted_reply() {
+        let body =
+            "Sounds good, ship it.\n\nOn Mon, Alice wrote:\n> Ready to deploy?\n> All green.";
+        let (text, quoted) = strip_quoted_reply(body);
+        assert_eq!(text, "Sounds good, ship it.");
+        assert_eq!(quoted.as_deref(), Some("Ready to deploy?\nAll green."));
+        assert_eq!(reply_subject("RE: plan"), "RE: plan");
+        assert_eq!(reply_subject("plan"), "Re: plan");
+        assert_eq!(reply_subject("Привет"), "Re: Привет");
+        assert_eq!(reply_subject("ré"), "Re: ré");
+    }
+
+    #[test]
+    fn senders_need_a_passing_dkim_or_dmarc_result() {
+        let mx = "mx.example.net";
+        let dkim = "mx.example.net; spf=fail smtp.mailfrom=example.com;\r\n             dkim=pass (2048-bit key) header.d=example.com header.s=s1";
+        assert!(sender_authenticated(dkim, mx, "example.com"));
+        assert!(sender_authenticated(dkim, mx, "mail.example.com"));
+        assert!(!sender_authenticated(dkim, mx, "example.org"));
+        assert!(!sender_authenticated(dkim, mx, "notexample.com"));
+
+        let dmarc =
+            "mx.example.net 1; dkim=fail header.d=example.com; dmarc=pass header.from=example.com";
+        assert!(sender_authenticated(dmarc, mx, "example.com"));
+        assert!(!sender_authenticated(dmarc, mx, "mail.example.com"));
+
+        let failed =
+            "mx.example.net; dkim=fail header.d=example.com; dmarc=fail header.from=example.com";
+        assert!(!sender_authenticated(failed, mx, "example.com"));
+        // The authserv-id is not a result, whatever it looks like.
+        assert!(!sender_authenticated(
+            "dkim=pass header.d=example.com",
+            mx,
+            "example.com"
+        ));
+        // Results stamped by another server may be forged by the sender.
+        let forged = "mx.attacker.test; dkim=pass header.d=example.com";
+        assert!(!sender_authenticated(forged, mx, "example.com"));
+        assert!(!sender_authenticated(dkim, "", "example.com"));
+    }
+
+    const MAIL: &str = "Authentication-Results: mx.example.com;\r\n \
+        dkim=pass header.d=example.com header.s=s1\r\n\
+        From: Alice <alice@example.com>\r\n\
+        Reply-To: mallory@example.net\r\n\
+        To: bot@example.com\r\n\
+        Subject: Weekly report\r\n\
+        Message-ID: <second@example.com>\r\n\
+        In-Reply-To: <first@example.com>\r\n\
+        References: <first@example.com>\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        Can you summarize it?\r\n";
+
+    /// Scripted IMAP server with one unseen message for the first session.
+    async fn imap_server(listener: TcpListener, seen: Arc<Mutex<bool>>) {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                return;
+            };
+            let seen = seen.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = stream.into_split();
+                let mut lines = BufReader::new(read).lines();
+                write.write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let (tag, command) = line.split_once(' ').unwrap();
+                    let command = command.to_ascii_uppercase();
+                    let reply = if command.starts_with("SELECT") {
+                        "* FLAGS (\\Seen)\r\n* 1 EXISTS\r\n* 0 RECENT\r\n\
+                         * OK [UIDVALIDITY 1] ok\r\n"
+                            .to_string()
+                    } else if command.starts_with("UID SEARCH") {
+                        let unseen = if *seen.lock().unwrap() { "" } else { " 7" };
+                        format!("* SEARCH{unseen}\r\n")
+                    } else if command.starts_with("UID FETCH") {
+                        format!("* 1 FETCH (UID 7 BODY[] {{{}}}\r\n{MAIL})\r\n", MAIL.len())
+                    } else if command.starts_with("UID STORE") {
+                        *seen.lock().unwrap() = true;
+                        "* 1 FETCH (UID 7 FLAGS (\\Seen))\r\n".to_string()
+                    } else if command.starts_with("LOGOUT") {
+                        "* BYE\r\n".to_string()
+                    } else {
+                        String::new()
+                    };
+                    let done = format!("{reply}{tag} OK done\r\n");
+                    write.write_all(done.as_bytes()).await.unwrap();
+                }
+            });
+        }
+    }
+
+    /// Minimal SMTP server that records the DATA of each message.
+    async fn smtp_server(listener: TcpListener, sent: Arc<Mutex<Vec<String>>>) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let command = line.to_ascii_uppercase();
+            let reply = if command.starts_with("EHLO") {
+                "250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+            } else if command.starts_with("AUTH") {
+                "235 ok\r\n"
+            } else if command == "DATA" {
+                write.write_all(b"354 go ahead\r\n").await.unwrap();
+                let mut data = Vec::new();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line == "." {
+                        break;
+                    }
+                    data.push(line);
+                }
+                sent.lock().unwrap().push(data.join("\n"));
+                "250 queued\r\n"
+            } else if command.starts_with("QUIT") {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                return;
+            } else {
+                "250 ok\r\n"
+            };
+            write.write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn threads_mail_from_imap_and_replies_over_smtp() {
+        let imap = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let smtp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.workspace_dir = workspace.path().to_path_buf();
+        let email = &mut cfg.channels.email;
+        email.imap_host = "127.0.0.1".to_string();
+        email.imap_port = imap.local_addr().unwrap().port();
+        email.imap_tls = false;
+        email.smtp_host = "127.0.0.1".to_string();
+        email.smtp_port = smtp.local_addr().unwrap().port();
+        email.smtp_security = SmtpSecurity::None;
+        email.username = "bot@example.com".to_string();
+        email.password = "secret".to_string();
+        email.allow_from = vec!["@example.com".to_string()];
+        email.authserv_id = "mx.example.com".to_string();
+
+        let seen = Arc::new(Mutex::new(false));
+        tokio::spawn(imap_server(imap, seen.clone()));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let smtp_task = tokio::spawn(smtp_server(smtp, sent.clone()));
+
+        let channel = Arc::new(EmailChannel::new(&cfg));
+        let bus = MessageBus::new();
+        let running = tokio::spawn({
+            let channel = channel.clone();
+            let bus = bus.clone();
+            async move { channel.start(bus).await }
+        });
+
+        let inbound = tokio::time::timeout(Duration::from_secs(5), bus.consume_inbound())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(inbound.chat_id, "alice@example.com|first@example.com");
+        assert_eq!(inbound.content, "Can you summarize it?");
+        assert_eq!(inbound.sender_name.as_deref(), Some("Alice"));
+
+        channel
+            .send(OutboundMessage {
+                channel: "email".to_string(),
+                chat_id: inbound.chat_id,
+                content: "Here is the summary.".to_string(),
+                attachments: Vec::new(),
+                reply_to: inbound.message_id,
+            })
+            .await
+            .unwrap();
+        channel.shutdown().await;
+        running.await.unwrap().unwrap();
+        assert!(*seen.lock().unwrap());
+
+        tokio::time::timeout(Duration::from_secs(5), smtp_task)
+            .await
+            .ok();
+        let data = sent.lock().unwrap().join("\n");
+        assert!(data.contains("To: alice@example.com"), "{data}");
+        assert!(!data.contains("mallory"), "{data}");
+        assert!(data.contains("Subject: Re: Weekly report"), "{data}");
+        assert!(data.contains("In-Reply-To: <second@example.com>"), "{data}");
+        assert!(
+            data.contains("References: <first@example.com> <second@example.com>"),
+            "{data}"
+        );
+        assert!(data.contains("Here is the summary."), "{data}");
+    }
+}
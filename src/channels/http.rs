@@ -0,0 +1,628 @@
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::{secrets_match, Channel, ChannelCapabilities};
+use crate::config::{AppConfig, HttpConfig};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Header callers set to keep a conversation across requests.
+const SESSION_HEADER: &str = "x-session-id";
+const MAX_SESSION_ID_LEN: usize = 128;
+const MODEL_ID: &str = "lightclaw";
+
+/// OpenAI-compatible HTTP API. Each request becomes an inbound message on
+/// the session named by `X-Session-Id` (or a one-off session), and the
+/// agent's replies to it are returned as a chat completion.
+pub struct HttpChannel {
+    cfg: HttpConfig,
+    pending: PendingRequests,
+    stop: watch::Sender<bool>,
+}
+
+/// Open requests keyed by session. A session serves one request at a time.
+type PendingRequests = Arc<DashMap<String, PendingRequest>>;
+
+struct PendingRequest {
+    /// Sent as the inbound `message_id`; the agent's final answer replies to it.
+    request_id: String,
+    tx: mpsc::UnboundedSender<OutboundMessage>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    bus: MessageBus,
+    api_keys: Arc<Vec<String>>,
+    timeout: Duration,
+    pending: PendingRequests,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionRequest {
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<RequestMessage>,
+    #[serde(default)]
+    stream: bool,
+    /// Caller-side end-user id, used as the sender.
+    #[serde(default)]
+    user: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RequestMessage {
+    role: String,
+    #[serde(default)]
+    content: Value,
+}
+
+impl HttpChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            cfg: cfg.channels.http.clone(),
+            pending: Arc::new(DashMap::new()),
+            stop: watch::channel(false).0,
+        }
+    }
+
+    fn router(&self, bus: MessageBus) -> Router {
+        let state = ApiState {
+            bus,
+            api_keys: Arc::new(
+                self.cfg
+                    .api_keys
+                    .iter()
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty())
+                    .collect(),
+            ),
+            timeout: Duration::from_secs(self.cfg.timeout_secs.max(1)),
+            pending: self.pending.clone(),
+        };
+        Router::new()
+            .route("/v1/models", get(list_models))
+            .route("/v1/chat/completions", post(chat_completions))
+            .with_state(state)
+    }
+}
+
+#[async_trait]
+impl Channel for HttpChannel {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: false,
+        }
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let mut stop = self.stop.subscribe();
+        let listener = tokio::net::TcpListener::bind(self.cfg.bind.trim())
+            .await
+            .map_err(|err| anyhow!("cannot listen on {}: {err}", self.cfg.bind))?;
+        info!("HTTP API listening on {}", listener.local_addr()?);
+        axum::serve(listener, self.router(bus))
+            .with_graceful_shutdown(async move {
+                let _ = stop.changed().await;
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Hand a reply to the request waiting on its session. Replies whose
+    /// caller has gone away are dropped rather than retried.
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let Some(pending) = self.pending.get(&msg.chat_id) else {
+            warn!(
+                "no open HTTP request for session {}; dropping reply",
+                msg.chat_id
+            );
+            return Ok(());
+        };
+        if msg
+            .reply_to
+            .as_ref()
+            .is_some_and(|id| id != &pending.request_id)
+        {
+            warn!(
+                "dropping late reply for session {}: its request already ended",
+                msg.chat_id
+            );
+            return Ok(());
+        }
+        let _ = pending.tx.send(msg);
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+async fn list_models(State(state): State<ApiState>, headers: HeaderMap) -> Response {
+    if caller(&state, &headers).is_none() {
+        return api_error(StatusCode::UNAUTHORIZED, "invalid API key");
+    }
+    Json(json!({
+        "object": "list",
+        "data": [{ "id": MODEL_ID, "object": "model", "owned_by": MODEL_ID }],
+    }))
+    .into_response()
+}
+
+async fn chat_completions(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Response {
+    let Some(caller) = caller(&state, &headers) else {
+        return api_error(StatusCode::UNAUTHORIZED, "invalid API key");
+    };
+
+    let session = match headers.get(SESSION_HEADER).map(|value| value.to_str()) {
+        Some(Ok(id)) if !id.trim().is_empty() && id.trim().len() <= MAX_SESSION_ID_LEN => {
+            Some(id.trim().to_string())
+        }
+        Some(_) => {
+            return api_error(
+                StatusCode::BAD_REQUEST,
+                &format!("X-Session-Id must be 1-{MAX_SESSION_ID_LEN} visible characters"),
+            )
+        }
+        None => None,
+    };
+    let Some(content) = prompt_from_messages(&request.messages, session.is_none()) else {
+        return api_error(
+            StatusCode::BAD_REQUEST,
+            "the last message must be a non-empty user message",
+        );
+    };
+    // Sessions are scoped to the API key, so callers cannot reach each
+    // other's history by guessing a session id.
+    let one_off = session.is_none();
+    let session = format!(
+        "{caller}/{}",
+        session.unwrap_or_else(|| format!("oneshot-{}", uuid::Uuid::new_v4()))
+    );
+
+    let request_id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let (tx, rx) = mpsc::unbounded_channel();
+    match state.pending.entry(session.clone()) {
+        Entry::Occupied(_) => {
+            return api_error(
+                StatusCode::CONFLICT,
+                "this session is already answering another request",
+            )
+        }
+        Entry::Vacant(slot) => {
+            slot.insert(PendingRequest {
+                request_id: request_id.clone(),
+                tx,
+            });
+        }
+    }
+    let guard = PendingGuard {
+        pending: state.pending.clone(),
+        session: session.clone(),
+        request_id: request_id.clone(),
+    };
+
+    state
+        .bus
+        .publish_inbound(InboundMessage {
+            channel: "http".to_string(),
+            chat_id: session,
+            sender_id: request
+                .user
+                .filter(|user| !user.trim().is_empty())
+                .unwrap_or_else(|| "api".to_string()),
+            content,
+            message_id: Some(request_id.clone()),
+            ephemeral: one_off,
+            ..Default::default()
+        })
+        .await;
+
+    let replies = Replies {
+        rx,
+        request_id: request_id.clone(),
+        deadline: Instant::now() + state.timeout,
+        _guard: guard,
+    };
+    let completion = Completion {
+        id: request_id,
+        model: request.model.unwrap_or_else(|| MODEL_ID.to_string()),
+        created: chrono::Utc::now().timestamp(),
+    };
+    if request.stream {
+        stream_completion(completion, replies).into_response()
+    } else {
+        complete(completion, replies).await
+    }
+}
+
+/// A stable label for the caller's API key, or `None` when the request
+/// carries no valid key.
+fn caller(state: &ApiState, headers: &HeaderMap) -> Option<String> {
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)?;
+    // Check every key, so the time taken does not tell which one came close.
+    state
+        .api_keys
+        .iter()
+        .fold(false, |found, key| secrets_match(token, key) | found)
+        .then(|| key_label(token))
+}
+
+/// First 12 hex digits of the key's SHA-256, so the key itself never shows
+/// up in session names, logs or memory.
+fn key_label(key: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, key.as_bytes());
+    format!("key-{}", &hex::encode(digest.as_ref())[..12])
+}
+
+fn api_error(status: StatusCode, message: &str) -> Response {
+    let kind = if status == StatusCode::UNAUTHORIZED {
+        "authentication_error"
+    } else if status.is_server_error() {
+        "server_error"
+    } else {
+        "invalid_request_error"
+    };
+    (
+        status,
+        Json(json!({ "error": { "message": message, "type": kind } })),
+    )
+        .into_response()
+}
+
+/// The text sent to the agent: the last user message. Sessions keep their own
+/// history, so earlier messages are only folded in for one-off requests.
+fn prompt_from_messages(messages: &[RequestMessage], include_earlier: bool) -> Option<String> {
+    let (last, earlier) = messages.split_last()?;
+    let text = message_text(&last.content);
+    if last.role != "user" || text.trim().is_empty() {
+        return None;
+    }
+    if !include_earlier || earlier.is_empty() {
+        return Some(text);
+    }
+    let transcript = earlier
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message_text(&message.content)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!("[Earlier conversation]\n{transcript}\n\n{text}"))
+}
+
+/// Message content is either a string or a list of typed parts; only text
+/// parts are kept.
+fn message_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|part| part["type"] == "text")
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Removes the session's pending entry when the request ends, including when
+/// the caller disconnects mid-stream.
+struct PendingGuard {
+    pending: PendingRequests,
+    session: String,
+    request_id: String,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.pending.remove_if(&self.session, |_, pending| {
+            pending.request_id == self.request_id
+        });
+    }
+}
+
+struct Completion {
+    id: String,
+    model: String,
+    created: i64,
+}
+
+/// Messages the agent sends to one request's session. Messages sent with
+/// `send_message` arrive first; the answer, which replies to the request,
+/// ends the request.
+struct Replies {
+    rx: mpsc::UnboundedReceiver<OutboundMessage>,
+    request_id: String,
+    deadline: Instant,
+    _guard: PendingGuard,
+}
+
+enum Reply {
+    Partial(String),
+    Final(String),
+    TimedOut,
+}
+
+impl Replies {
+    async fn next(&mut self) -> Reply {
+        match tokio::time::timeout_at(self.deadline, self.rx.recv()).await {
+            Ok(Some(msg)) if msg.reply_to.as_deref() == Some(self.request_id.as_str()) => {
+                Reply::Final(msg.content)
+            }
+            Ok(Some(msg)) => Reply::Partial(msg.content),
+            // The channel's sender lives in the pending map, which this
+            // request's guard keeps alive.
+            Ok(None) | Err(_) => Reply::TimedOut,
+        }
+    }
+}
+
+async fn complete(completion: Completion, mut replies: Replies) -> Response {
+    let mut parts = Vec::new();
+    loop {
+        match replies.next().await {
+            Reply::Partial(text) => parts.push(text),
+            Reply::Final(text) => {
+                parts.push(text);
+                break;
+            }
+            Reply::TimedOut => {
+                return api_error(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    "the agent did not answer in time",
+                )
+            }
+        }
+    }
+    Json(json!({
+        "id": completion.id,
+        "object": "chat.completion",
+        "created": completion.created,
+        "model": completion.model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": parts.join("\n\n") },
+            "finish_reason": "stop",
+        }],
+    }))
+    .into_response()
+}
+
+/// Stream the replies as `chat.completion.chunk` events. The agent answers in
+/// whole messages, so each message is one content delta.
+fn stream_completion(
+    completion: Completion,
+    mut replies: Replies,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, Infallible>>> {
+    let (events_tx, events_rx) = mpsc::channel::<Event>(16);
+    tokio::spawn(async move {
+        let chunk = |delta: Value, finish_reason: Option<&str>| {
+            Event::default().data(
+                json!({
+                    "id": completion.id,
+                    "object": "chat.completion.chunk",
+                    "created": completion.created,
+                    "model": completion.model,
+                    "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+                })
+                .to_string(),
+            )
+        };
+        if events_tx
+            .send(chunk(json!({ "role": "assistant" }), None))
+            .await
+            .is_err()
+        {
+            return;
+        }
+        let mut first = true;
+        loop {
+            let (text, done) = match replies.next().await {
+                Reply::Partial(text) => (text, false),
+                Reply::Final(text) => (text, true),
+                Reply::TimedOut => {
+                    let error = json!({ "error": {
+                        "message": "the agent did not answer in time",
+                        "type": "server_error",
+                    }});
+                    let _ = events_tx
+                        .send(Event::default().data(error.to_string()))
+                        .await;
+                    return;
+                }
+            };
+            let text = if first { text } else { format!("\n\n{text}") };
+            first = false;
+            if events_tx
+                .send(chunk(json!({ "content": text }), None))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            if done {
+                break;
+            }
+        }
+        let _ = events_tx.send(chunk(json!({}), Some("stop"))).await;
+        let _ = events_tx.send(Event::default().data("[DONE]")).await;
+    });
+    let events = futures_util::stream::unfold(events_rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Stand-in for the agent: answers every inbound message on the channel,
+    /// sending a `send_message`-style note first when asked to. Returns the
+    /// sessions it saw.
+    fn spawn_agent(bus: MessageBus, channel: Arc<HttpChannel>) -> Arc<Mutex<Vec<String>>> {
+        let sessions = Arc::new(Mutex::new(Vec::new()));
+        let seen = sessions.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = bus.consume_inbound().await {
+                seen.lock().unwrap().push(msg.chat_id.clone());
+                if msg.content.contains("note") {
+                    channel
+                        .send(OutboundMessage {
+                            channel: "http".to_string(),
+                            chat_id: msg.chat_id.clone(),
+                            content: "working on it".to_string(),
+                            attachments: Vec::new(),
+                            reply_to: None,
+                        })
+                        .await
+                        .unwrap();
+                }
+                channel
+                    .send(OutboundMessage {
+                        channel: "http".to_string(),
+                        chat_id: msg.chat_id.clone(),
+                        content: format!("{} said: {}", msg.sender_id, msg.content),
+                        attachments: Vec::new(),
+                        reply_to: msg.message_id,
+                    })
+                    .await
+                    .unwrap();
+            }
+        });
+        sessions
+    }
+
+    #[tokio::test]
+    async fn answers_completions_plain_and_streaming() {
+        let mut cfg = AppConfig::defaults();
+        cfg.channels.http.api_keys = vec!["secret".to_string()];
+        let channel = Arc::new(HttpChannel::new(&cfg));
+        let bus = MessageBus::new();
+        let sessions = spawn_agent(bus.clone(), channel.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let router = channel.router(bus);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let unauthorized = client
+            .post(format!("{base}/chat/completions"))
+            .bearer_auth("wrong")
+            .json(&json!({ "messages": [{ "role": "user", "content": "hi" }] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), 401);
+
+        let body: Value = client
+            .post(format!("{base}/chat/completions"))
+            .bearer_auth("secret")
+            .header("X-Session-Id", "tool-a")
+            .json(&json!({
+                "model": "gpt-4o",
+                "user": "ci",
+                "messages": [
+                    { "role": "system", "content": "be brief" },
+                    { "role": "user", "content": [{ "type": "text", "text": "hello" }] },
+                ],
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["object"], "chat.completion");
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["choices"][0]["message"]["content"], "ci said: hello");
+
+        let body: Value = client
+            .post(format!("{base}/chat/completions"))
+            .bearer_auth("secret")
+            .json(&json!({ "messages": [
+                { "role": "user", "content": "first" },
+                { "role": "assistant", "content": "ok" },
+                { "role": "user", "content": "note this" },
+            ]}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            body["choices"][0]["message"]["content"],
+            "working on it\n\napi said: [Earlier conversation]\nuser: first\nassistant: ok\n\nnote this"
+        );
+
+        let stream = client
+            .post(format!("{base}/chat/completions"))
+            .bearer_auth("secret")
+            .header("X-Session-Id", "tool-a")
+            .json(&json!({ "stream": true, "messages": [{ "role": "user", "content": "note" }] }))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let events: Vec<&str> = stream
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect();
+        assert_eq!(events.last(), Some(&"[DONE]"));
+        let chunks: Vec<Value> = events[..events.len() - 1]
+            .iter()
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        assert_eq!(text, "working on it\n\napi said: note");
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "stop"
+        );
+        assert!(channel.pending.is_empty());
+
+        // Sessions are namespaced by key; one-off requests get their own.
+        let label = key_label("secret");
+        assert_eq!(label.len(), "key-".len() + 12);
+        assert_ne!(label, key_label("other"));
+        let sessions = sessions.lock().unwrap();
+        assert_eq!(sessions[0], format!("{label}/tool-a"));
+        assert!(sessions[1].starts_with(&format!("{label}/oneshot-")));
+        assert_eq!(sessions[2], sessions[0]);
+    }
+}
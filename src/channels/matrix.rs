@@ -0,0 +1,924 @@
+use crate::bus::{InboundMessage, MessageBus, OutboundAttachment, OutboundMessage};
+use crate::channels::{Allowlist, Channel, ChannelCapabilities};
+use crate::config::{AppConfig, MatrixConfig};
+use anyhow::{anyhow, Context as _, Result};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use pulldown_cmark::{html, Options, Parser};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+use url::Url;
+
+/// Events are capped at 64 KiB; leave room for the HTML copy of the body.
+const CAPABILITIES: ChannelCapabilities = ChannelCapabilities {
+    max_message_chars: Some(16_000),
+    attachments: true,
+};
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+/// Separates the room id from the thread root in chat addresses. Room ids
+/// already contain `:`.
+const THREAD_SEPARATOR: char = '|';
+/// Threads remembered for mention-free follow-ups; the least recently
+/// active one is forgotten first.
+const MAX_ACTIVE_THREADS: usize = 1024;
+
+/// Matrix transport over the client-server API: long-polls `/sync` and sends
+/// `m.room.message` events. Encrypted rooms are not supported.
+pub struct MatrixChannel {
+    api: MatrixApi,
+    cfg: MatrixConfig,
+    allow_from: Allowlist,
+    allowed_rooms: HashSet<String>,
+    /// Joined member count per room, from the sync summaries; rooms with two
+    /// members are treated as DMs.
+    member_counts: DashMap<String, u64>,
+    /// Threads the bot was mentioned in, as chat addresses, with their last
+    /// activity; follow-ups there need no further mention.
+    active_threads: DashMap<String, Instant>,
+    display_names: DashMap<String, String>,
+    stop: watch::Sender<bool>,
+}
+
+#[derive(Clone)]
+struct Identity {
+    user_id: String,
+    display_name: Option<String>,
+}
+
+impl MatrixChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        let matrix = cfg.channels.matrix.clone();
+        Self {
+            api: MatrixApi {
+                http: reqwest::Client::new(),
+                homeserver: matrix.homeserver.trim().to_string(),
+                access_token: RwLock::new(matrix.access_token.trim().to_string()),
+            },
+            allow_from: Allowlist::new(&matrix.allow_from),
+            allowed_rooms: matrix
+                .allowed_rooms
+                .iter()
+                .map(|room| room.trim().to_string())
+                .filter(|room| !room.is_empty())
+                .collect(),
+            cfg: matrix,
+            member_counts: DashMap::new(),
+            active_threads: DashMap::new(),
+            display_names: DashMap::new(),
+            stop: watch::channel(false).0,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for MatrixChannel {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        CAPABILITIES
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let identity = self
+            .authenticate()
+            .await
+            .map_err(|err| anyhow!("matrix authentication failed: {err}"))?;
+        info!("matrix connected as {}", identity.user_id);
+
+        // Only answer what arrives after startup: the initial sync just
+        // records room state and pending invites.
+        let initial = self.api.sync(None, 0).await?;
+        let mut since = initial.next_batch.clone();
+        self.handle_sync(&bus, &identity, initial, false).await;
+
+        let mut stop = self.stop.subscribe();
+        let mut backoff = RETRY_MIN;
+        loop {
+            let response = tokio::select! {
+                response = self.api.sync(Some(&since), SYNC_TIMEOUT_MS) => response,
+                _ = stop.changed() => return Ok(()),
+            };
+            match response {
+                Ok(sync) => {
+                    backoff = RETRY_MIN;
+                    since = sync.next_batch.clone();
+                    self.handle_sync(&bus, &identity, sync, true).await;
+                }
+                Err(err) => {
+                    if err
+                        .downcast_ref::<MatrixError>()
+                        .is_some_and(|err| err.status == 401)
+                    {
+                        return Err(err);
+                    }
+                    warn!("matrix sync failed, retrying in {backoff:?}: {err:#}");
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = stop.changed() => return Ok(()),
+                    }
+                    backoff = (backoff * 2).min(RETRY_MAX);
+                }
+            }
+        }
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let (room_id, thread_root) = parse_chat_address(&msg.chat_id);
+        let relation = relation(thread_root, msg.reply_to.as_deref());
+        for chunk in CAPABILITIES.split_message(msg.content.trim()) {
+            let mut content = json!({
+                "msgtype": "m.text",
+                "body": chunk,
+                "format": "org.matrix.custom.html",
+                "formatted_body": markdown_to_html(chunk),
+            });
+            if let Some(relation) = &relation {
+                content["m.relates_to"] = relation.clone();
+            }
+            self.api.send_message(room_id, content).await?;
+        }
+        for attachment in &msg.attachments {
+            let mut content = self
+                .api
+                .upload(attachment)
+                .await
+                .with_context(|| format!("failed to upload {}", attachment.file_name()))?;
+            if let Some(relation) = &relation {
+                content["m.relates_to"] = relation.clone();
+            }
+            self.api.send_message(room_id, content).await?;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+impl MatrixChannel {
+    async fn authenticate(&self) -> Result<Identity> {
+        let user_id = if self.api.token().is_empty() {
+            let login = self
+                .api
+                .request(
+                    reqwest::Method::POST,
+                    &["login"],
+                    Some(json!({
+                        "type": "m.login.password",
+                        "identifier": { "type": "m.id.user", "user": self.cfg.user_id.trim() },
+                        "password": self.cfg.password,
+                        "initial_device_display_name": "lightclaw",
+                    })),
+                )
+                .await?;
+            let token = login["access_token"]
+                .as_str()
+                .ok_or_else(|| anyhow!("login returned no access_token"))?;
+            *self
+                .api
+                .access_token
+                .write()
+                .unwrap_or_else(|e| e.into_inner()) = token.to_string();
+            login["user_id"].as_str().unwrap_or_default().to_string()
+        } else {
+            let whoami = self
+                .api
+                .request(reqwest::Method::GET, &["account", "whoami"], None)
+                .await?;
+            whoami["user_id"].as_str().unwrap_or_default().to_string()
+        };
+        if user_id.is_empty() {
+            return Err(anyhow!("homeserver did not report the bot's user id"));
+        }
+        let display_name = self.api.display_name(&user_id).await;
+        Ok(Identity {
+            user_id,
+            display_name,
+        })
+    }
+
+    async fn handle_sync(
+        &self,
+        bus: &MessageBus,
+        identity: &Identity,
+        sync: SyncResponse,
+        deliver: bool,
+    ) {
+        for (room_id, invited) in sync.rooms.invite {
+            self.handle_invite(&room_id, &invited, identity).await;
+        }
+        for (room_id, joined) in sync.rooms.join {
+            if let Some(count) = joined.summary.joined_member_count {
+                self.member_counts.insert(room_id.clone(), count);
+            }
+            if !deliver {
+                continue;
+            }
+            for event in &joined.timeline.events {
+                self.handle_event(bus, identity, &room_id, event).await;
+            }
+        }
+    }
+
+    /// Join rooms allowed users invite us to. DM invites are accepted from
+    /// allowed users regardless of `allowed_rooms`.
+    async fn handle_invite(&self, room_id: &str, invited: &InvitedRoom, identity: &Identity) {
+        let Some(invite) = invited.invite_state.events.iter().find(|event| {
+            event.kind == "m.room.member" && event.state_key.as_deref() == Some(&identity.user_id)
+        }) else {
+            return;
+        };
+        let is_direct = invite.content["is_direct"].as_bool().unwrap_or(false);
+        let room_allowed = self.allowed_rooms.is_empty() || self.allowed_rooms.contains(room_id);
+        if !self.is_sender_allowed(&invite.sender) || !(is_direct || room_allowed) {
+            info!("ignoring matrix invite to {room_id} from {}", invite.sender);
+            return;
+        }
+        match self
+            .api
+            .request(reqwest::Method::POST, &["join", room_id], Some(json!({})))
+            .await
+        {
+            Ok(_) => info!("joined matrix room {room_id}"),
+            Err(err) => warn!("failed to join matrix room {room_id}: {err}"),
+        }
+    }
+
+    /// A room with just the bot and one other member.
+    fn is_direct(&self, room_id: &str) -> bool {
+        self.member_counts
+            .get(room_id)
+            .is_some_and(|count| *count <= 2)
+    }
+
+    async fn handle_event(
+        &self,
+        bus: &MessageBus,
+        identity: &Identity,
+        room_id: &str,
+        event: &RoomEvent,
+    ) {
+        if event.kind != "m.room.message" {
+            return;
+        }
+        let Ok(content) = serde_json::from_value::<MessageContent>(event.content.clone()) else {
+            return;
+        };
+        let Some(chat_id) = self.route_message(room_id, event, &content, identity) else {
+            return;
+        };
+        if !self.is_sender_allowed(&event.sender) {
+            return;
+        }
+
+        let (quoted_text, body) = split_reply_fallback(&content.body);
+        let text = strip_mention(&body, identity);
+        if text.is_empty() {
+            return;
+        }
+        let sender_name = match self.display_names.get(&event.sender) {
+            Some(name) => Some(name.clone()),
+            None => {
+                let name = self.api.display_name(&event.sender).await;
+                if let Some(name) = &name {
+                    self.display_names
+                        .insert(event.sender.clone(), name.clone());
+                }
+                name
+            }
+        };
+
+        bus.publish_inbound(InboundMessage {
+            channel: "matrix".to_string(),
+            chat_id,
+            sender_id: event.sender.clone(),
+            sender_name,
+            content: text,
+            message_id: event.event_id.clone(),
+            reply_to: content
+                .relates_to
+                .as_ref()
+                .and_then(|relation| relation.in_reply_to.as_ref())
+                .map(|reply| reply.event_id.clone()),
+            quoted_text,
+            session_commands: self.is_direct(room_id),
+            ..Default::default()
+        })
+        .await;
+    }
+
+    /// Chat address to publish a message under, or `None` when the bot should
+    /// not answer it. Group rooms need a mention (or an ongoing thread the bot
+    /// was mentioned in), and every conversation there is answered in a
+    /// thread.
+    fn route_message(
+        &self,
+        room_id: &str,
+        event: &RoomEvent,
+        content: &MessageContent,
+        identity: &Identity,
+    ) -> Option<String> {
+        if event.sender == identity.user_id
+            || !matches!(content.msgtype.as_str(), "m.text" | "m.emote")
+        {
+            return None;
+        }
+        let relation = content.relates_to.as_ref();
+        if relation.is_some_and(|relation| relation.rel_type.as_deref() == Some("m.replace")) {
+            return None;
+        }
+        let thread_root = relation
+            .filter(|relation| relation.rel_type.as_deref() == Some("m.thread"))
+            .and_then(|relation| relation.event_id.as_deref());
+
+        if self.is_direct(room_id) {
+            return Some(match thread_root {
+                Some(root) => format!("{room_id}{THREAD_SEPARATOR}{root}"),
+                None => room_id.to_string(),
+            });
+        }
+        if !self.allowed_rooms.is_empty() && !self.allowed_rooms.contains(room_id) {
+            return None;
+        }
+        let root = thread_root.or(event.event_id.as_deref())?;
+        let address = format!("{room_id}{THREAD_SEPARATOR}{root}");
+        if mentions(content, identity)
+            || (thread_root.is_some() && self.active_threads.contains_key(&address))
+        {
+            self.touch_thread(&address);
+            Some(address)
+        } else {
+            None
+        }
+    }
+
+    /// Mark a thread as active, forgetting the least recently active one
+    /// once [`MAX_ACTIVE_THREADS`] are remembered.
+    fn touch_thread(&self, address: &str) {
+        self.active_threads
+            .insert(address.to_string(), Instant::now());
+        if self.active_threads.len() > MAX_ACTIVE_THREADS {
+            let oldest = self
+                .active_threads
+                .iter()
+                .min_by_key(|entry| *entry.value())
+                .map(|entry| entry.key().clone());
+            if let Some(oldest) = oldest {
+                self.active_threads.remove(&oldest);
+            }
+        }
+    }
+
+    fn is_sender_allowed(&self, user_id: &str) -> bool {
+        let localpart = user_id
+            .strip_prefix('@')
+            .and_then(|rest| rest.split(':').next());
+        self.allow_from.allows(user_id, localpart)
+    }
+}
+
+/// An error response from the homeserver.
+#[derive(Debug)]
+struct MatrixError {
+    status: u16,
+    errcode: String,
+    message: String,
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.status, self.errcode, self.message)
+    }
+}
+
+impl std::error::Error for MatrixError {}
+
+/// Minimal client-server API client.
+struct MatrixApi {
+    http: reqwest::Client,
+    homeserver: String,
+    /// Set from config or by the password login.
+    access_token: RwLock<String>,
+}
+
+impl MatrixApi {
+    fn token(&self) -> String {
+        self.access_token
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// `/_matrix/<api>/v3/<segments>`, with each segment percent-encoded.
+    fn endpoint(&self, api: &str, segments: &[&str]) -> Result<Url> {
+        let mut url: Url = self
+            .homeserver
+            .parse()
+            .map_err(|err| anyhow!("invalid matrix homeserver '{}': {err}", self.homeserver))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("invalid matrix homeserver '{}'", self.homeserver))?
+            .pop_if_empty()
+            .extend(["_matrix", api, "v3"])
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        segments: &[&str],
+        body: Option<Value>,
+    ) -> Result<Value> {
+        let url = self.endpoint("client", segments)?;
+        let mut request = self.http.request(method, url);
+        let token = self.token();
+        if !token.is_empty() {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        parse_response(request.send().await?).await
+    }
+
+    async fn sync(&self, since: Option<&str>, timeout_ms: u64) -> Result<SyncResponse> {
+        let mut url = self.endpoint("client", &["sync"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("timeout", &timeout_ms.to_string());
+            query.append_pair(
+                "filter",
+                &json!({
+                    "presence": { "types": [] },
+                    "account_data": { "types": [] },
+                    "room": {
+                        "ephemeral": { "types": [] },
+                        "state": { "lazy_load_members": true },
+                        "timeline": { "limit": 50 },
+                    },
+                })
+                .to_string(),
+            );
+            if let Some(since) = since {
+                query.append_pair("since", since);
+            }
+        }
+        let response = self.http.get(url).bearer_auth(self.token()).send().await?;
+        Ok(serde_json::from_value(parse_response(response).await?)?)
+    }
+
+    async fn display_name(&self, user_id: &str) -> Option<String> {
+        self.request(
+            reqwest::Method::GET,
+            &["profile", user_id, "displayname"],
+            None,
+        )
+        .await
+        .ok()
+        .and_then(|profile| profile["displayname"].as_str().map(str::to_string))
+        .filter(|name| !name.trim().is_empty())
+    }
+
+    async fn send_message(&self, room_id: &str, content: Value) -> Result<()> {
+        let txn_id = uuid::Uuid::new_v4().to_string();
+        self.request(
+            reqwest::Method::PUT,
+            &["rooms", room_id, "send", "m.room.message", &txn_id],
+            Some(content),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Upload `attachment` to the media repository and return the content of
+    /// the `m.image` / `m.file` message that shares it.
+    async fn upload(&self, attachment: &OutboundAttachment) -> Result<Value> {
+        let data = tokio::fs::read(&attachment.path).await?;
+        let file_name = attachment.file_name();
+        let mimetype = attachment.mime_type();
+        let size = data.len();
+        let mut url = self.endpoint("media", &["upload"])?;
+        url.query_pairs_mut().append_pair("filename", &file_name);
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(self.token())
+            .header(reqwest::header::CONTENT_TYPE, mimetype)
+            .body(data)
+            .send()
+            .await?;
+        let uploaded = parse_response(response).await?;
+        let content_uri = uploaded["content_uri"]
+            .as_str()
+            .ok_or_else(|| anyhow!("media upload returned no content_uri"))?;
+        Ok(json!({
+            "msgtype": if attachment.is_image() { "m.image" } else { "m.file" },
+            "body": file_name,
+            "url": content_uri,
+            "info": { "mimetype": mimetype, "size": size },
+        }))
+    }
+}
+
+async fn parse_response(response: reqwest::Response) -> Result<Value> {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        return Err(MatrixError {
+            status: status.as_u16(),
+            errcode: body["errcode"].as_str().unwrap_or("M_UNKNOWN").to_string(),
+            message: body["error"].as_str().unwrap_or_default().to_string(),
+        }
+        .into());
+    }
+    Ok(body)
+}
+
+#[derive(Deserialize, Default)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: SyncRooms,
+}
+
+#[derive(Deserialize, Default)]
+struct SyncRooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+    #[serde(default)]
+    invite: HashMap<String, InvitedRoom>,
+}
+
+#[derive(Deserialize, Default)]
+struct JoinedRoom {
+    #[serde(default)]
+    summary: RoomSummary,
+    #[serde(default)]
+    timeline: Timeline,
+}
+
+#[derive(Deserialize, Default)]
+struct RoomSummary {
+    #[serde(rename = "m.joined_member_count")]
+    joined_member_count: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Deserialize, Default)]
+struct InvitedRoom {
+    #[serde(default)]
+    invite_state: Timeline,
+}
+
+#[derive(Deserialize, Default)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    event_id: Option<String>,
+    state_key: Option<String>,
+    #[serde(default)]
+    content: Value,
+}
+
+#[derive(Deserialize, Default)]
+struct MessageContent {
+    #[serde(default)]
+    msgtype: String,
+    #[serde(default)]
+    body: String,
+    formatted_body: Option<String>,
+    #[serde(rename = "m.relates_to")]
+    relates_to: Option<Relation>,
+    #[serde(rename = "m.mentions")]
+    mentions: Option<Mentions>,
+}
+
+#[derive(Deserialize, Default)]
+struct Relation {
+    rel_type: Option<String>,
+    event_id: Option<String>,
+    #[serde(rename = "m.in_reply_to")]
+    in_reply_to: Option<InReplyTo>,
+}
+
+#[derive(Deserialize)]
+struct InReplyTo {
+    event_id: String,
+}
+
+#[derive(Deserialize, Default)]
+struct Mentions {
+    #[serde(default)]
+    user_ids: Vec<String>,
+}
+
+/// Inverse of the addresses built in `route_message`: the room id, plus the
+/// thread root for threaded conversations.
+fn parse_chat_address(address: &str) -> (&str, Option<&str>) {
+    match address.split_once(THREAD_SEPARATOR) {
+        Some((room_id, root)) => (room_id, Some(root)),
+        None => (address, None),
+    }
+}
+
+/// `m.relates_to` for a reply: threaded replies stay in their thread, and
+/// `reply_to` makes it a rich reply to the triggering message.
+fn relation(thread_root: Option<&str>, reply_to: Option<&str>) -> Option<Value> {
+    match (thread_root, reply_to) {
+        (Some(root), reply_to) => Some(json!({
+            "rel_type": "m.thread",
+            "event_id": root,
+            "is_falling_back": reply_to.is_none(),
+            "m.in_reply_to": { "event_id": reply_to.unwrap_or(root) },
+        })),
+        (None, Some(reply_to)) => Some(json!({ "m.in_reply_to": { "event_id": reply_to } })),
+        (None, None) => None,
+    }
+}
+
+/// Intentional mentions when the client sends them, otherwise the legacy
+/// rule: the body names the bot's user id or display name.
+fn mentions(content: &MessageContent, identity: &Identity) -> bool {
+    if let Some(mentions) = &content.mentions {
+        return mentions.user_ids.contains(&identity.user_id);
+    }
+    let named = |text: &str| {
+        text.contains(&identity.user_id)
+            || identity
+                .display_name
+                .as_deref()
+                .is_some_and(|name| text.to_lowercase().contains(&name.to_lowercase()))
+    };
+    named(&content.body) || content.formatted_body.as_deref().is_some_and(named)
+}
+
+/// Split the `> <@user:server> quoted` fallback older clients prepend to
+/// replies from the actual message.
+fn split_reply_fallback(body: &str) -> (Option<String>, String) {
+    if !body.starts_with("> ") {
+        return (None, body.to_string());
+    }
+    let mut quoted = Vec::new();
+    let mut lines = body.lines();
+    for line in lines.by_ref() {
+        match line.strip_prefix("> ") {
+            Some(rest) => quoted.push(rest),
+            None => break,
+        }
+    }
+    let rest = lines.collect::<Vec<_>>().join("\n");
+    let quoted = quoted.join("\n");
+    // The first quoted line starts with the quoted sender.
+    let quoted = match quoted.strip_prefix('<') {
+        Some(rest) => rest
+            .split_once("> ")
+            .map_or(quoted.clone(), |(_, text)| text.to_string()),
+        None => quoted,
+    };
+    (
+        (!quoted.trim().is_empty()).then_some(quoted),
+        rest.trim().to_string(),
+    )
+}
+
+/// Drop a leading `Name:` / `@bot:server` mention pill from the body.
+fn strip_mention(body: &str, identity: &Identity) -> String {
+    let text = body.replace(&identity.user_id, "");
+    let rest = identity
+        .display_name
+        .as_deref()
+        .and_then(|name| strip_name(&text, name))
+        .unwrap_or(&text);
+    rest.trim_start_matches([':', ',', ' ']).trim().to_string()
+}
+
+/// `text` after a leading, case-insensitive `name`, when the name is followed
+/// by a delimiter (so "Claw" does not match the start of "Clawdia").
+fn strip_name<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    if name.is_empty() {
+        return None;
+    }
+    let mut chars = text.char_indices();
+    for expected in name.chars() {
+        let (_, actual) = chars.next()?;
+        if !actual.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    let rest = chars.as_str();
+    rest.chars()
+        .next()
+        .is_none_or(|next| next.is_whitespace() || next == ':' || next == ',')
+        .then_some(rest)
+}
+
+fn markdown_to_html(markdown: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(markdown, options));
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        markdown_to_html, split_reply_fallback, strip_mention, Identity, MatrixChannel,
+        MAX_ACTIVE_THREADS,
+    };
+    use crate::bus::{MessageBus, OutboundMessage};
+    use crate::channels::Channel;
+    use crate::config::AppConfig;
+    use axum::extract::{Path, Query, State};
+    use axum::routing::{get, post, put};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn formats_replies_and_cleans_inbound_bodies() {
+        assert_eq!(
+            markdown_to_html("**hi** `x`"),
+            "<p><strong>hi</strong> <code>x</code></p>\n"
+        );
+
+        let identity = Identity {
+            user_id: "@claw:example.org".to_string(),
+            display_name: Some("Claw".to_string()),
+        };
+        assert_eq!(strip_mention("claw: what's new?", &identity), "what's new?");
+        assert_eq!(strip_mention("@claw:example.org hi", &identity), "hi");
+        assert_eq!(
+            strip_mention("Clawdia says hi", &identity),
+            "Clawdia says hi"
+        );
+
+        let identity = Identity {
+            user_id: "@bot:example.org".to_string(),
+            display_name: Some("İris".to_string()),
+        };
+        assert_eq!(strip_mention("i̇ris, hi", &identity), "i̇ris, hi");
+        assert_eq!(strip_mention("İRIS: hi", &identity), "hi");
+
+        let (quoted, body) =
+            split_reply_fallback("> <@alice:example.org> the plan\n> step two\n\nsounds good");
+        assert_eq!(quoted.as_deref(), Some("the plan\nstep two"));
+        assert_eq!(body, "sounds good");
+    }
+
+    type Sent = Arc<Mutex<Vec<(String, Value)>>>;
+
+    async fn sync(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+        let room = |events: Value| json!({ "rooms": { "join": { "!room:localhost": events } } });
+        let mention = |id: &str, body: &str| {
+            json!({
+                "type": "m.room.message",
+                "sender": "@alice:localhost",
+                "event_id": id,
+                "content": {
+                    "msgtype": "m.text",
+                    "body": body,
+                    "m.mentions": { "user_ids": ["@claw:localhost"] },
+                },
+            })
+        };
+        let mut response = match query.get("since").map(String::as_str) {
+            // The backlog from before startup must not be answered.
+            None => room(json!({
+                "summary": { "m.joined_member_count": 3 },
+                "timeline": { "events": [mention("$old", "Claw: stale question")] },
+            })),
+            Some("s1") => room(json!({
+                "timeline": { "events": [mention("$ev1", "Claw: hello there")] },
+            })),
+            _ => {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                json!({})
+            }
+        };
+        let next = match query.get("since").map(String::as_str) {
+            None => "s1",
+            _ => "s2",
+        };
+        response["next_batch"] = json!(next);
+        Json(response)
+    }
+
+    #[test]
+    fn active_threads_forget_the_least_recently_active() {
+        let matrix = MatrixChannel::new(&AppConfig::defaults());
+        let now = Instant::now();
+        for index in 0..MAX_ACTIVE_THREADS {
+            let age = Duration::from_millis((MAX_ACTIVE_THREADS - index) as u64);
+            matrix
+                .active_threads
+                .insert(format!("!room:x|$e{index}"), now - age);
+        }
+        matrix.touch_thread("!room:x|$e0");
+        matrix.touch_thread("!room:x|$new");
+
+        assert_eq!(matrix.active_threads.len(), MAX_ACTIVE_THREADS);
+        assert!(matrix.active_threads.contains_key("!room:x|$e0"));
+        assert!(matrix.active_threads.contains_key("!room:x|$new"));
+        assert!(!matrix.active_threads.contains_key("!room:x|$e1"));
+    }
+
+    #[tokio::test]
+    async fn syncs_and_replies_against_a_stub_homeserver() {
+        let sent: Sent = Arc::default();
+        let api = Router::new()
+            .route(
+                "/_matrix/client/v3/login",
+                post(|| async {
+                    Json(json!({ "access_token": "tok", "user_id": "@claw:localhost" }))
+                }),
+            )
+            .route(
+                "/_matrix/client/v3/profile/{user}/displayname",
+                get(|Path(user): Path<String>| async move {
+                    let name = if user.starts_with("@claw") {
+                        "Claw"
+                    } else {
+                        "Alice"
+                    };
+                    Json(json!({ "displayname": name }))
+                }),
+            )
+            .route("/_matrix/client/v3/sync", get(sync))
+            .route(
+                "/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}",
+                put(
+                    |State(sent): State<Sent>,
+                     Path((room, _txn)): Path<(String, String)>,
+                     Json(content): Json<Value>| async move {
+                        sent.lock().unwrap().push((room, content));
+                        Json(json!({ "event_id": "$reply" }))
+                    },
+                ),
+            )
+            .with_state(sent.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let homeserver = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, api).await });
+
+        let mut cfg = AppConfig::defaults();
+        cfg.channels.matrix.homeserver = homeserver;
+        cfg.channels.matrix.user_id = "@claw:localhost".to_string();
+        cfg.channels.matrix.password = "secret".to_string();
+        let matrix = Arc::new(MatrixChannel::new(&cfg));
+        let bus = MessageBus::new();
+        let running = tokio::spawn({
+            let matrix = matrix.clone();
+            let bus = bus.clone();
+            async move { matrix.start(bus).await }
+        });
+
+        let inbound = tokio::time::timeout(Duration::from_secs(5), bus.consume_inbound())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(inbound.chat_id, "!room:localhost|$ev1");
+        assert_eq!(inbound.content, "hello there");
+        assert_eq!(inbound.sender_name.as_deref(), Some("Alice"));
+
+        matrix
+            .send(OutboundMessage {
+                channel: "matrix".to_string(),
+                chat_id: inbound.chat_id,
+                content: "**done**".to_string(),
+                attachments: Vec::new(),
+                reply_to: inbound.message_id,
+            })
+            .await
+            .unwrap();
+        let (room, content) = sent.lock().unwrap()[0].clone();
+        assert_eq!(room, "!room:localhost");
+        assert_eq!(content["formatted_body"], "<p><strong>done</strong></p>\n");
+        assert_eq!(content["m.relates_to"]["rel_type"], "m.thread");
+        assert_eq!(content["m.relates_to"]["event_id"], "$ev1");
+
+        matrix.shutdown().await;
+        running.await.unwrap().unwrap();
+    }
+}
@@ -1,2 +1,254 @@
 pub mod discord;
+pub mod email;
+pub mod http;
+pub mod matrix;
+pub mod slack;
 pub mod telegram;
+pub mod tui;
+pub mod webchat;
+
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::config::AppConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// What a transport can render, so callers can adapt replies to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelCapabilities {
+    /// Longest single message the platform accepts; longer replies are split.
+    pub max_message_chars: Option<usize>,
+    /// Whether files can be delivered.
+    pub attachments: bool,
+}
+
+impl ChannelCapabilities {
+    /// Split `text` into messages the platform accepts.
+    pub fn split_message<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        split_chunks(text, self.max_message_chars.unwrap_or(usize::MAX))
+    }
+
+    /// Whether `text` fits in a single message.
+    pub fn fits(&self, text: &str) -> bool {
+        self.max_message_chars
+            .is_none_or(|limit| text.chars().count() <= limit)
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = vec![match self.max_message_chars {
+            Some(limit) => format!("max {limit} chars"),
+            None => "unlimited length".to_string(),
+        }];
+        if self.attachments {
+            parts.push("attachments".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// A chat transport. `start` runs the inbound side and publishes to the bus
+/// until the channel stops; outbound messages addressed to `name()` are
+/// handed to `send` by the bus's delivery worker.
+#[async_trait]
+pub trait Channel: Send + Sync {
+    /// Name used as `channel` on bus messages.
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> ChannelCapabilities;
+
+    async fn start(&self, bus: MessageBus) -> Result<()>;
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()>;
+
+    /// Stop receiving and release platform resources.
+    async fn shutdown(&self) {}
+}
+
+/// The channels enabled in `cfg`. New transports are added here.
+pub fn from_config(cfg: &AppConfig) -> Vec<Arc<dyn Channel>> {
+    let mut channels: Vec<Arc<dyn Channel>> = Vec::new();
+
+    if cfg.telegram_enabled() {
+        channels.push(Arc::new(telegram::TelegramChannel::new(cfg)));
+    } else {
+        info!("Telegram token not configured; running without Telegram input/output");
+        info!("Set TELOXIDE_TOKEN or channels.telegram.token to enable Telegram");
+    }
+
+    if cfg.discord_enabled() {
+        channels.push(Arc::new(discord::DiscordChannel::new(cfg)));
+    } else {
+        info!("Discord token not configured; running without Discord input/output");
+        info!("Set DISCORD_BOT_TOKEN or channels.discord.token to enable Discord");
+    }
+
+    if cfg.slack_enabled() {
+        channels.push(Arc::new(slack::SlackChannel::new(cfg)));
+    } else {
+        info!("Slack tokens not configured; running without Slack input/output");
+        info!("Set SLACK_APP_TOKEN and SLACK_BOT_TOKEN (or channels.slack.*) to enable Slack");
+    }
+
+    if cfg.matrix_enabled() {
+        channels.push(Arc::new(matrix::MatrixChannel::new(cfg)));
+    } else {
+        info!("Matrix not configured; running without Matrix input/output");
+        info!("Set channels.matrix.homeserver and an access token or password to enable Matrix");
+    }
+
+    if cfg.email_enabled() {
+        channels.push(Arc::new(email::EmailChannel::new(cfg)));
+    } else {
+        info!("Email not configured; running without email input/output");
+        info!("Set channels.email.imap_host, smtp_host and username to enable email");
+    }
+
+    if cfg.http_enabled() {
+        channels.push(Arc::new(http::HttpChannel::new(cfg)));
+    } else {
+        info!("HTTP API not configured; set channels.http.bind and api_keys to enable it");
+    }
+
+    if cfg.webchat_enabled() {
+        channels.push(Arc::new(webchat::WebChatChannel::new(cfg)));
+    } else {
+        info!("Web chat not configured; set channels.webchat.bind and users to enable it");
+    }
+
+    channels
+}
+
+/// Register `channel` on the bus so replies addressed to it reach `send`.
+pub fn attach(channel: &Arc<dyn Channel>, bus: &MessageBus) {
+    let capabilities = channel.capabilities();
+    info!(
+        "{} channel ready ({})",
+        channel.name(),
+        capabilities.describe()
+    );
+    let sender = channel.clone();
+    bus.spawn_delivery_worker(channel.name(), capabilities, move |msg| {
+        let sender = sender.clone();
+        async move { sender.send(msg).await }
+    });
+}
+
+/// Attach every channel and run its inbound side in the background. A
+/// channel that fails to start is unregistered so replies to it fail fast.
+pub fn start_all(channels: &[Arc<dyn Channel>], bus: &MessageBus) {
+    for channel in channels {
+        attach(channel, bus);
+        let channel = channel.clone();
+        let bus = bus.clone();
+        tokio::spawn(async move {
+            if let Err(err) = channel.start(bus.clone()).await {
+                warn!("{} disabled: {err}", channel.name());
+                bus.unregister_channel(channel.name());
+            }
+        });
+    }
+}
+
+pub async fn shutdown_all(channels: &[Arc<dyn Channel>]) {
+    for channel in channels {
+        channel.shutdown().await;
+    }
+}
+
+/// Split `text` into chunks of at most `max_bytes`, preferring line breaks
+/// and never cutting inside a UTF-8 character.
+fn split_chunks(text: &str, max_bytes: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+    while !remaining.is_empty() {
+        let chunk_len = if remaining.len() <= max_bytes {
+            remaining.len()
+        } else {
+            let mut limit = max_bytes;
+            while !remaining.is_char_boundary(limit) {
+                limit -= 1;
+            }
+            remaining[..limit].rfind('\n').unwrap_or(limit)
+        };
+        chunks.push(&remaining[..chunk_len]);
+        remaining = &remaining[chunk_len..];
+        if remaining.starts_with('\n') {
+            remaining = &remaining[1..];
+        }
+    }
+    chunks
+}
+
+/// Sender allowlist shared by the chat channels. Entries match a user id, a
+/// username with or without `@` (case-insensitive) or a `<@id>` mention. An
+/// empty list allows everyone.
+#[derive(Clone, Debug, Default)]
+pub struct Allowlist(Vec<String>);
+
+impl Allowlist {
+    pub fn new(entries: &[String]) -> Self {
+        Self(
+            entries
+                .iter()
+                .map(|entry| entry.trim().to_ascii_lowercase())
+                .filter(|entry| !entry.is_empty())
+                .collect(),
+        )
+    }
+
+    pub fn allows(&self, user_id: &str, username: Option<&str>) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        let username = username.map(|name| name.to_ascii_lowercase());
+        let mention = format!("<@{user_id}>");
+        self.0.iter().any(|allowed| {
+            allowed == user_id
+                || allowed == &mention
+                || username
+                    .as_deref()
+                    .is_some_and(|name| allowed == name || allowed.strip_prefix('@') == Some(name))
+        })
+    }
+}
+
+/// Compare a presented secret with the expected one in constant time. Both
+/// are hashed first, so neither their contents nor their lengths leak.
+pub(crate) fn secrets_match(given: &str, expected: &str) -> bool {
+    let digest = |text: &str| ring::digest::digest(&ring::digest::SHA256, text.as_bytes());
+    let (given, expected) = (digest(given), digest(expected));
+    let difference = given
+        .as_ref()
+        .iter()
+        .zip(expected.as_ref())
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    difference == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{secrets_match, Allowlist};
+
+    #[test]
+    fn allowlist_matches_ids_usernames_and_mentions() {
+        let entries = ["123", "@Alice", "bob", "<@456>", " "].map(String::from);
+        let allowlist = Allowlist::new(&entries);
+
+        assert!(allowlist.allows("123", None));
+        assert!(allowlist.allows("9", Some("alice")));
+        assert!(allowlist.allows("9", Some("BOB")));
+        assert!(allowlist.allows("456", Some("carol")));
+        assert!(!allowlist.allows("9", Some("carol")));
+        assert!(!allowlist.allows("9", None));
+        assert!(Allowlist::new(&[]).allows("9", None));
+    }
+
+    #[test]
+    fn secrets_match_only_identical_strings() {
+        assert!(secrets_match("hunter2", "hunter2"));
+        assert!(!secrets_match("hunter2", "hunter3"));
+        assert!(!secrets_match("hunter", "hunter2"));
+        assert!(!secrets_match("", "hunter2"));
+    }
+}
@@ -0,0 +1,856 @@
+use crate::bus::{InboundMessage, MessageBus, OutboundAttachment, OutboundMessage};
+use crate::channels::{Allowlist, Channel, ChannelCapabilities};
+use crate::config::AppConfig;
+use crate::transcription::Transcriber;
+use anyhow::{anyhow, Context as _, Result};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures_util::stream::FuturesUnordered;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tracing::{info, warn};
+
+const SLACK_API_BASE: &str = "https://slack.com/api";
+/// `chat.postMessage` accepts at most 12,000 characters of `markdown_text`.
+const CAPABILITIES: ChannelCapabilities = ChannelCapabilities {
+    max_message_chars: Some(12_000),
+    attachments: true,
+};
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// Threads remembered for mention-free follow-ups; the least recently
+/// active one is forgotten first.
+const MAX_ACTIVE_THREADS: usize = 1024;
+/// Larger inbound files are mentioned by name but not downloaded.
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Slack transport over Socket Mode: events arrive on a WebSocket opened with
+/// the app-level token, replies go out through the Web API with the bot token.
+pub struct SlackChannel {
+    api: SlackApi,
+    app_token: String,
+    allow_from: Allowlist,
+    allowed_channels: HashSet<String>,
+    transcriber: Option<Transcriber>,
+    attachments_dir: PathBuf,
+    /// Channel threads the bot was mentioned in, as chat addresses, with
+    /// their last activity; follow-ups there need no further mention.
+    active_threads: DashMap<String, Instant>,
+    users: DashMap<String, SlackUser>,
+    stop: watch::Sender<bool>,
+}
+
+#[derive(Clone)]
+struct SlackUser {
+    handle: String,
+    display_name: String,
+}
+
+impl SlackChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self::with_api_base(cfg, SLACK_API_BASE)
+    }
+
+    fn with_api_base(cfg: &AppConfig, api_base: &str) -> Self {
+        let slack = &cfg.channels.slack;
+        Self {
+            api: SlackApi {
+                http: reqwest::Client::new(),
+                base: api_base.trim_end_matches('/').to_string(),
+                bot_token: slack.bot_token.trim().to_string(),
+            },
+            app_token: slack.app_token.trim().to_string(),
+            allow_from: Allowlist::new(&slack.allow_from),
+            allowed_channels: slack
+                .allowed_channels
+                .iter()
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect(),
+            transcriber: Transcriber::from_config(cfg),
+            attachments_dir: cfg.workspace_dir.join("inbox").join("slack"),
+            active_threads: DashMap::new(),
+            users: DashMap::new(),
+            stop: watch::channel(false).0,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for SlackChannel {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        CAPABILITIES
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let auth = self
+            .api
+            .call("auth.test", &[])
+            .await
+            .map_err(|err| anyhow!("slack authentication failed: {err}"))?;
+        let bot_user_id = auth["user_id"].as_str().unwrap_or_default().to_string();
+
+        // Events are handled off the socket loop so slow work (user lookups,
+        // downloads, transcription) never delays acks and pings.
+        let (events_tx, events_rx) = mpsc::channel(64);
+        let mut stop = self.stop.subscribe();
+        let socket = async {
+            let mut backoff = RECONNECT_MIN;
+            loop {
+                match self.run_socket(&events_tx, &mut stop).await {
+                    Ok(SocketEnd::Stopped) => return,
+                    Ok(SocketEnd::Reconnect) => backoff = RECONNECT_MIN,
+                    Err(err) => {
+                        warn!("slack socket error, reconnecting in {backoff:?}: {err:#}");
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => {}
+                            _ = stop.changed() => return,
+                        }
+                        backoff = (backoff * 2).min(RECONNECT_MAX);
+                    }
+                }
+            }
+        };
+        tokio::select! {
+            _ = socket => {}
+            _ = self.handle_events(&bus, &bot_user_id, events_rx) => {}
+        }
+        Ok(())
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let (channel, thread_ts) = parse_chat_address(&msg.chat_id);
+        let text = msg.content.trim();
+        for chunk in CAPABILITIES.split_message(text) {
+            self.api.post_message(channel, thread_ts, chunk).await?;
+        }
+        for attachment in &msg.attachments {
+            self.api
+                .upload_file(channel, thread_ts, attachment)
+                .await
+                .with_context(|| format!("failed to upload {}", attachment.file_name()))?;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+enum SocketEnd {
+    /// Slack asked us to reconnect (or closed the socket).
+    Reconnect,
+    Stopped,
+}
+
+impl SlackChannel {
+    /// Run one Socket Mode connection until it closes or we are stopped.
+    async fn run_socket(
+        &self,
+        events: &mpsc::Sender<SlackEvent>,
+        stop: &mut watch::Receiver<bool>,
+    ) -> Result<SocketEnd> {
+        let opened = self
+            .api
+            .call_with_token("apps.connections.open", &self.app_token, &[])
+            .await?;
+        let url = opened["url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("apps.connections.open returned no url"))?;
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let (mut write, mut read) = socket.split();
+
+        loop {
+            let frame = tokio::select! {
+                frame = read.next() => frame,
+                _ = stop.changed() => {
+                    let _ = write.close().await;
+                    return Ok(SocketEnd::Stopped);
+                }
+            };
+            let text = match frame {
+                None | Some(Ok(WsMessage::Close(_))) => return Ok(SocketEnd::Reconnect),
+                Some(Ok(WsMessage::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => return Err(err.into()),
+            };
+            let envelope: Envelope = match serde_json::from_str(&text) {
+                Ok(envelope) => envelope,
+                Err(err) => {
+                    warn!("ignoring malformed slack envelope: {err}");
+                    continue;
+                }
+            };
+            // Acknowledge before handling, or Slack redelivers the event.
+            if let Some(envelope_id) = &envelope.envelope_id {
+                let ack = json!({ "envelope_id": envelope_id }).to_string();
+                write.send(WsMessage::Text(ack)).await?;
+            }
+            match envelope.kind.as_str() {
+                "hello" => info!("slack socket connected"),
+                "disconnect" => return Ok(SocketEnd::Reconnect),
+                "events_api" => {
+                    if let Some(event) = envelope.payload.and_then(|payload| payload.event) {
+                        events.send(event).await?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Handle queued events concurrently, so one long transcription does not
+    /// hold up the next message.
+    async fn handle_events(
+        &self,
+        bus: &MessageBus,
+        bot_user_id: &str,
+        mut events: mpsc::Receiver<SlackEvent>,
+    ) {
+        let mut running = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => running.push(self.handle_event(bus, bot_user_id, event)),
+                    None => return,
+                },
+                Some(()) = running.next(), if !running.is_empty() => {}
+            }
+        }
+    }
+
+    async fn handle_event(&self, bus: &MessageBus, bot_user_id: &str, event: SlackEvent) {
+        let Some(chat_id) = self.route_event(&event, bot_user_id) else {
+            return;
+        };
+        let (Some(user_id), Some(ts)) = (event.user.as_deref(), event.ts.as_deref()) else {
+            return;
+        };
+        let user = self.user(user_id).await;
+        if !self
+            .allow_from
+            .allows(user_id, user.as_ref().map(|u| u.handle.as_str()))
+        {
+            return;
+        }
+
+        let mut text = clean_text(event.text.as_deref().unwrap_or(""), bot_user_id);
+        let (channel, thread_ts) = parse_chat_address(&chat_id);
+        if let Some(file) = event.files.iter().find(|file| file.is_audio()) {
+            let Some(transcript) = self.transcribe_file(channel, thread_ts, file).await else {
+                return;
+            };
+            text = if text.is_empty() {
+                transcript
+            } else {
+                format!("{text}\n\n{transcript}")
+            };
+        }
+        for file in event.files.iter().filter(|file| !file.is_audio()) {
+            match self.save_file(file).await {
+                Ok(path) => text.push_str(&format!("\n[attachment saved to {}]", path.display())),
+                Err(err) => {
+                    warn!("failed to save slack file {}: {err:#}", file.id);
+                    text.push_str(&format!("\n[attached file: {}]", file.display_name()));
+                }
+            }
+        }
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+
+        bus.publish_inbound(InboundMessage {
+            channel: "slack".to_string(),
+            chat_id,
+            sender_id: user_id.to_string(),
+            sender_name: user.map(|u| u.display_name),
+            content: text,
+            message_id: Some(ts.to_string()),
+            reply_to: event.thread_ts.clone().filter(|thread| thread != ts),
+            session_commands: event.channel_type.as_deref() == Some("im"),
+            ..Default::default()
+        })
+        .await;
+    }
+
+    /// Chat address to publish `event` under, or `None` when the bot should
+    /// not answer it. Channels need a mention (or an ongoing thread the bot
+    /// was mentioned in); every conversation there is answered in a thread.
+    fn route_event(&self, event: &SlackEvent, bot_user_id: &str) -> Option<String> {
+        if event.bot_id.is_some()
+            || event
+                .subtype
+                .as_deref()
+                .is_some_and(|subtype| subtype != "file_share")
+        {
+            return None;
+        }
+        let user = event.user.as_deref()?;
+        if user == bot_user_id {
+            return None;
+        }
+        let channel = event.channel.as_deref()?;
+        let ts = event.ts.as_deref()?;
+
+        if event.kind == "message" && event.channel_type.as_deref() == Some("im") {
+            return Some(match &event.thread_ts {
+                Some(thread) => format!("{channel}:{thread}"),
+                None => channel.to_string(),
+            });
+        }
+        if !self.allowed_channels.is_empty() && !self.allowed_channels.contains(channel) {
+            return None;
+        }
+        let address = format!("{channel}:{}", event.thread_ts.as_deref().unwrap_or(ts));
+        match event.kind.as_str() {
+            "app_mention" => {
+                self.touch_thread(&address);
+                Some(address)
+            }
+            // Mentions also arrive as `app_mention`; answer those only once.
+            "message"
+                if event.thread_ts.is_some()
+                    && self.active_threads.contains_key(&address)
+                    && !mentions_user(event.text.as_deref().unwrap_or(""), bot_user_id) =>
+            {
+                self.touch_thread(&address);
+                Some(address)
+            }
+            _ => None,
+        }
+    }
+
+    /// Mark a thread as active, forgetting the least recently active one
+    /// once [`MAX_ACTIVE_THREADS`] are remembered.
+    fn touch_thread(&self, address: &str) {
+        self.active_threads
+            .insert(address.to_string(), Instant::now());
+        if self.active_threads.len() > MAX_ACTIVE_THREADS {
+            let oldest = self
+                .active_threads
+                .iter()
+                .min_by_key(|entry| *entry.value())
+                .map(|entry| entry.key().clone());
+            if let Some(oldest) = oldest {
+                self.active_threads.remove(&oldest);
+            }
+        }
+    }
+
+    /// Download a non-audio file into the workspace inbox.
+    async fn save_file(&self, file: &SlackFile) -> Result<PathBuf> {
+        let size = file.size.unwrap_or(0);
+        if size > MAX_FILE_BYTES {
+            return Err(anyhow!("file is too large ({size} bytes)"));
+        }
+        let data = self.api.download(file).await?;
+        save_file(&self.attachments_dir, &file.id, &file.display_name(), &data).await
+    }
+
+    async fn user(&self, user_id: &str) -> Option<SlackUser> {
+        if let Some(user) = self.users.get(user_id) {
+            return Some(user.clone());
+        }
+        let info = match self
+            .api
+            .call("users.info", &[("user", user_id.to_string())])
+            .await
+        {
+            Ok(info) => info,
+            Err(err) => {
+                warn!("slack users.info failed for {user_id}: {err}");
+                return None;
+            }
+        };
+        let handle = info["user"]["name"].as_str().unwrap_or(user_id).to_string();
+        let display_name = ["display_name", "real_name"]
+            .iter()
+            .filter_map(|key| info["user"]["profile"][key].as_str())
+            .find(|name| !name.trim().is_empty())
+            .unwrap_or(&handle)
+            .to_string();
+        let user = SlackUser {
+            handle,
+            display_name,
+        };
+        self.users.insert(user_id.to_string(), user.clone());
+        Some(user)
+    }
+
+    /// Download and transcribe an audio file, replying with the same errors
+    /// as the other channels when that is not possible.
+    async fn transcribe_file(
+        &self,
+        channel: &str,
+        thread_ts: Option<&str>,
+        file: &SlackFile,
+    ) -> Option<String> {
+        let reply = |text: String| async move {
+            if let Err(err) = self.api.post_message(channel, thread_ts, &text).await {
+                warn!("slack reply failed for channel {channel}: {err}");
+            }
+        };
+
+        let Some(transcriber) = &self.transcriber else {
+            reply("Voice/audio transcription is not configured.".to_string()).await;
+            return None;
+        };
+        let cache_key = format!("slack:{}", file.id);
+        let cached = transcriber.cached(&cache_key);
+        let size = file.size.unwrap_or(0) as usize;
+        if cached.is_none() && size > transcriber.max_input_bytes() {
+            reply(format!(
+                "Audio file is too large ({} bytes). Max allowed is {} bytes.",
+                size,
+                transcriber.max_input_bytes()
+            ))
+            .await;
+            return None;
+        }
+
+        let transcript = match cached {
+            Some(transcript) => Ok(transcript),
+            None => match self.api.download(file).await {
+                Ok(data) => {
+                    transcriber
+                        .transcribe(Some(&cache_key), file.display_name(), data)
+                        .await
+                }
+                Err(err) => {
+                    warn!("audio download failed: {err}");
+                    reply("I couldn't download that audio message from Slack.".to_string()).await;
+                    return None;
+                }
+            },
+        };
+
+        match transcript {
+            Ok(transcript) if !transcript.is_empty() => {
+                if let Some(echo) = transcriber.echo_text(&transcript) {
+                    reply(echo).await;
+                }
+                Some(transcript)
+            }
+            Ok(_) => {
+                reply("I couldn't extract text from that audio message.".to_string()).await;
+                None
+            }
+            Err(err) => {
+                warn!("audio transcription failed: {err}");
+                reply(
+                    "I couldn't transcribe that audio message. Please retry or send text."
+                        .to_string(),
+                )
+                .await;
+                None
+            }
+        }
+    }
+}
+
+/// Minimal Slack Web API client.
+struct SlackApi {
+    http: reqwest::Client,
+    base: String,
+    bot_token: String,
+}
+
+impl SlackApi {
+    async fn call(&self, method: &str, params: &[(&str, String)]) -> Result<Value> {
+        self.call_with_token(method, &self.bot_token, params).await
+    }
+
+    async fn call_with_token(
+        &self,
+        method: &str,
+        token: &str,
+        params: &[(&str, String)],
+    ) -> Result<Value> {
+        let response: Value = self
+            .http
+            .post(format!("{}/{method}", self.base))
+            .bearer_auth(token)
+            .form(params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if response["ok"].as_bool() != Some(true) {
+            let error = response["error"].as_str().unwrap_or("unknown error");
+            return Err(anyhow!("slack {method} failed: {error}"));
+        }
+        Ok(response)
+    }
+
+    async fn post_message(&self, channel: &str, thread_ts: Option<&str>, text: &str) -> Result<()> {
+        let mut params = vec![
+            ("channel", channel.to_string()),
+            ("markdown_text", text.to_string()),
+        ];
+        if let Some(thread_ts) = thread_ts {
+            params.push(("thread_ts", thread_ts.to_string()));
+        }
+        self.call("chat.postMessage", &params).await?;
+        Ok(())
+    }
+
+    /// Upload through `files.getUploadURLExternal` and share the file into
+    /// the conversation with `files.completeUploadExternal`.
+    async fn upload_file(
+        &self,
+        channel: &str,
+        thread_ts: Option<&str>,
+        attachment: &OutboundAttachment,
+    ) -> Result<()> {
+        let data = tokio::fs::read(&attachment.path).await?;
+        let file_name = attachment.file_name();
+        let ticket = self
+            .call(
+                "files.getUploadURLExternal",
+                &[
+                    ("filename", file_name.clone()),
+                    ("length", data.len().to_string()),
+                ],
+            )
+            .await?;
+        let upload_url = ticket["upload_url"]
+            .as_str()
+            .ok_or_else(|| anyhow!("files.getUploadURLExternal returned no upload_url"))?;
+        let file_id = ticket["file_id"]
+            .as_str()
+            .ok_or_else(|| anyhow!("files.getUploadURLExternal returned no file_id"))?;
+
+        self.http
+            .post(upload_url)
+            .body(data)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut params = vec![
+            (
+                "files",
+                json!([{ "id": file_id, "title": file_name }]).to_string(),
+            ),
+            ("channel_id", channel.to_string()),
+        ];
+        if let Some(thread_ts) = thread_ts {
+            params.push(("thread_ts", thread_ts.to_string()));
+        }
+        self.call("files.completeUploadExternal", &params).await?;
+        Ok(())
+    }
+
+    async fn download(&self, file: &SlackFile) -> Result<Vec<u8>> {
+        let url = file
+            .url_private_download
+            .as_deref()
+            .ok_or_else(|| anyhow!("slack file {} has no download url", file.id))?;
+        let bytes = self
+            .http
+            .get(url)
+            .bearer_auth(&self.bot_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    kind: String,
+    envelope_id: Option<String>,
+    payload: Option<EventsPayload>,
+}
+
+#[derive(Deserialize)]
+struct EventsPayload {
+    event: Option<SlackEvent>,
+}
+
+#[derive(Deserialize, Default)]
+struct SlackEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    subtype: Option<String>,
+    user: Option<String>,
+    bot_id: Option<String>,
+    text: Option<String>,
+    channel: Option<String>,
+    channel_type: Option<String>,
+    ts: Option<String>,
+    thread_ts: Option<String>,
+    #[serde(default)]
+    files: Vec<SlackFile>,
+}
+
+#[derive(Deserialize)]
+struct SlackFile {
+    id: String,
+    name: Option<String>,
+    mimetype: Option<String>,
+    size: Option<u64>,
+    url_private_download: Option<String>,
+}
+
+impl SlackFile {
+    fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.id.clone())
+    }
+
+    fn is_audio(&self) -> bool {
+        self.mimetype
+            .as_deref()
+            .is_some_and(|mime| mime.starts_with("audio/"))
+    }
+}
+
+/// Chat address used on the bus: the conversation id, plus `:<thread ts>`
+/// for threads so each thread gets its own session.
+fn parse_chat_address(address: &str) -> (&str, Option<&str>) {
+    match address.split_once(':') {
+        Some((channel, thread_ts)) => (channel, Some(thread_ts)),
+        None => (address, None),
+    }
+}
+
+async fn save_file(dir: &Path, id: &str, name: &str, data: &[u8]) -> Result<PathBuf> {
+    let file_name = Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "attachment".to_string());
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("{id}-{file_name}"));
+    tokio::fs::write(&path, data).await?;
+    Ok(path)
+}
+
+fn mentions_user(text: &str, user_id: &str) -> bool {
+    !user_id.is_empty() && text.contains(&format!("<@{user_id}>"))
+}
+
+/// Drop the bot mention and undo Slack's HTML-style escaping.
+fn clean_text(text: &str, bot_user_id: &str) -> String {
+    let text = if bot_user_id.is_empty() {
+        text.to_string()
+    } else {
+        text.replace(&format!("<@{bot_user_id}>"), "")
+    };
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SlackChannel, SlackEvent, MAX_ACTIVE_THREADS};
+    use crate::bus::{MessageBus, OutboundMessage};
+    use crate::channels::Channel;
+    use crate::config::AppConfig;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Form, Json, Router};
+    use futures_util::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    fn test_config() -> AppConfig {
+        let mut cfg = AppConfig::defaults();
+        cfg.channels.slack.app_token = "xapp-test".to_string();
+        cfg.channels.slack.bot_token = "xoxb-test".to_string();
+        cfg
+    }
+
+    fn event(kind: &str, channel: &str, text: &str) -> SlackEvent {
+        SlackEvent {
+            kind: kind.to_string(),
+            user: Some("U1".to_string()),
+            text: Some(text.to_string()),
+            channel: Some(channel.to_string()),
+            ts: Some("100.1".to_string()),
+            ..SlackEvent::default()
+        }
+    }
+
+    #[test]
+    fn channel_messages_need_a_mention_or_an_active_thread() {
+        let slack = SlackChannel::new(&test_config());
+
+        let mut dm = event("message", "D1", "hi");
+        dm.channel_type = Some("im".to_string());
+        assert_eq!(slack.route_event(&dm, "UBOT").as_deref(), Some("D1"));
+
+        let mut chatter = event("message", "C1", "hi all");
+        chatter.channel_type = Some("channel".to_string());
+        assert_eq!(slack.route_event(&chatter, "UBOT"), None);
+
+        let mention = event("app_mention", "C1", "<@UBOT> hi");
+        assert_eq!(
+            slack.route_event(&mention, "UBOT").as_deref(),
+            Some("C1:100.1")
+        );
+
+        let mut follow_up = event("message", "C1", "and then?");
+        follow_up.channel_type = Some("channel".to_string());
+        follow_up.ts = Some("100.2".to_string());
+        follow_up.thread_ts = Some("100.1".to_string());
+        assert_eq!(
+            slack.route_event(&follow_up, "UBOT").as_deref(),
+            Some("C1:100.1")
+        );
+
+        let mut own = event("message", "D1", "echo");
+        own.bot_id = Some("B1".to_string());
+        assert_eq!(slack.route_event(&own, "UBOT"), None);
+    }
+
+    #[test]
+    fn active_threads_forget_the_least_recently_active() {
+        let slack = SlackChannel::new(&test_config());
+        let now = Instant::now();
+        for index in 0..MAX_ACTIVE_THREADS {
+            let age = Duration::from_millis((MAX_ACTIVE_THREADS - index) as u64);
+            slack
+                .active_threads
+                .insert(format!("C1:{index}.0"), now - age);
+        }
+        slack.touch_thread("C1:0.0");
+        slack.touch_thread("C1:new.0");
+
+        assert_eq!(slack.active_threads.len(), MAX_ACTIVE_THREADS);
+        assert!(slack.active_threads.contains_key("C1:0.0"));
+        assert!(slack.active_threads.contains_key("C1:new.0"));
+        assert!(!slack.active_threads.contains_key("C1:1.0"));
+    }
+
+    type Calls = Arc<Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+    #[tokio::test]
+    async fn socket_mode_round_trip_against_mock_servers() {
+        // Socket Mode endpoint: greet, deliver one mention, expect its ack.
+        let ws_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", ws_listener.local_addr().unwrap());
+        let socket = tokio::spawn(async move {
+            let (stream, _) = ws_listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            ws.send(WsMessage::Text(json!({"type": "hello"}).to_string()))
+                .await
+                .unwrap();
+            let envelope = json!({
+                "type": "events_api",
+                "envelope_id": "env-1",
+                "payload": {"event": {
+                    "type": "app_mention",
+                    "user": "U1",
+                    "text": "<@UBOT> what's up &amp; how?",
+                    "channel": "C1",
+                    "ts": "111.1"
+                }}
+            });
+            ws.send(WsMessage::Text(envelope.to_string()))
+                .await
+                .unwrap();
+            let ack = ws.next().await.unwrap().unwrap().into_text().unwrap();
+            // Keep the socket open until the client shuts down.
+            while let Some(Ok(frame)) = ws.next().await {
+                if frame.is_close() {
+                    break;
+                }
+            }
+            ack
+        });
+
+        // Web API endpoint: record every call by method name.
+        let calls: Calls = Arc::default();
+        let api = Router::new()
+            .route(
+                "/{method}",
+                post(
+                    |State((calls, ws_url)): State<(Calls, String)>,
+                     axum::extract::Path(method): axum::extract::Path<String>,
+                     Form(params): Form<HashMap<String, String>>| async move {
+                        calls.lock().unwrap().push((method.clone(), params));
+                        Json(match method.as_str() {
+                            "auth.test" => json!({"ok": true, "user_id": "UBOT"}),
+                            "apps.connections.open" => json!({"ok": true, "url": ws_url}),
+                            "users.info" => json!({"ok": true, "user": {
+                                "name": "alice",
+                                "profile": {"display_name": "Alice"}
+                            }}),
+                            _ => json!({"ok": true}),
+                        })
+                    },
+                ),
+            )
+            .with_state((calls.clone(), ws_url));
+        let api_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_base = format!("http://{}", api_listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(api_listener, api).await });
+
+        let slack = Arc::new(SlackChannel::with_api_base(&test_config(), &api_base));
+        let bus = MessageBus::new();
+        let running = tokio::spawn({
+            let slack = slack.clone();
+            let bus = bus.clone();
+            async move { slack.start(bus).await }
+        });
+
+        let inbound = tokio::time::timeout(Duration::from_secs(5), bus.consume_inbound())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(inbound.chat_id, "C1:111.1");
+        assert_eq!(inbound.content, "what's up & how?");
+        assert_eq!(inbound.sender_name.as_deref(), Some("Alice"));
+
+        slack
+            .send(OutboundMessage {
+                channel: "slack".to_string(),
+                chat_id: inbound.chat_id,
+                content: "all good".to_string(),
+                attachments: Vec::new(),
+                reply_to: inbound.message_id,
+            })
+            .await
+            .unwrap();
+        let post = calls
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(method, _)| method == "chat.postMessage")
+            .map(|(_, params)| params.clone())
+            .unwrap();
+        assert_eq!(post["channel"], "C1");
+        assert_eq!(post["thread_ts"], "111.1");
+        assert_eq!(post["markdown_text"], "all good");
+
+        slack.shutdown().await;
+        running.await.unwrap().unwrap();
+        let ack: Value = serde_json::from_str(&socket.await.unwrap()).unwrap();
+        assert_eq!(ack["envelope_id"], "env-1");
+    }
+}
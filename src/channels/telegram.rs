@@ -1,55 +1,248 @@
-use crate::bus::{InboundMessage, MessageBus};
-use crate::config::AppConfig;
+use crate::bus::{InboundMessage, MessageBus, OutboundAttachment, OutboundMessage};
+use crate::channels::{Allowlist, Channel, ChannelCapabilities};
+use crate::config::{AppConfig, GroupMode, TelegramWebhookConfig, VoiceReplyMode};
 use crate::transcription::Transcriber;
+use crate::tts::Synthesizer;
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use dashmap::DashMap;
 use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
-use teloxide::dispatching::UpdateHandler;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use teloxide::dispatching::{ShutdownToken, UpdateHandler};
 use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::{ChatAction, FileId, ParseMode};
+use teloxide::types::{
+    ChatAction, FileId, InputFile, MessageId, ParseMode, ReplyParameters, ThreadId, UserId,
+};
+use teloxide::update_listeners::webhooks;
+use teloxide::{ApiError, RequestError};
 use tracing::{info, warn};
 
-pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
-    let bot = Bot::new(cfg.channels.telegram.bot_token.clone());
-    bot.get_me()
+const CAPABILITIES: ChannelCapabilities = ChannelCapabilities {
+    max_message_chars: Some(4096),
+    attachments: true,
+};
+/// Source budget per chunk; MarkdownV2 escaping makes the rendered text longer.
+const TELEGRAM_CHUNK_CHARS: usize = 3500;
+
+/// Per-chat voice reply state. The default mode comes from config, `/voice`
+/// overrides it per chat, and `auto` mirrors whether the user last spoke.
+#[derive(Clone)]
+struct VoiceReplies {
+    synthesizer: Synthesizer,
+    overrides: Arc<DashMap<i64, VoiceReplyMode>>,
+    last_inbound_voice: Arc<DashMap<i64, bool>>,
+}
+
+impl VoiceReplies {
+    fn new(synthesizer: Synthesizer) -> Self {
+        Self {
+            synthesizer,
+            overrides: Arc::new(DashMap::new()),
+            last_inbound_voice: Arc::new(DashMap::new()),
+        }
+    }
+
+    fn mode_for(&self, chat_id: i64) -> VoiceReplyMode {
+        self.overrides
+            .get(&chat_id)
+            .map(|mode| mode.clone())
+            .unwrap_or_else(|| self.synthesizer.reply_mode().clone())
+    }
+
+    fn record_inbound(&self, chat_id: i64, was_voice: bool) {
+        self.last_inbound_voice.insert(chat_id, was_voice);
+    }
+
+    fn should_speak(&self, chat_id: i64) -> bool {
+        match self.mode_for(chat_id) {
+            VoiceReplyMode::Off => false,
+            VoiceReplyMode::Always => true,
+            VoiceReplyMode::Auto => self
+                .last_inbound_voice
+                .get(&chat_id)
+                .map(|v| *v)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Telegram transport, using long polling or a webhook.
+pub struct TelegramChannel {
+    cfg: AppConfig,
+    bot: Bot,
+    voice_replies: Option<VoiceReplies>,
+    /// Stops the running dispatcher; set once `start` has built it.
+    shutdown: Mutex<Option<ShutdownToken>>,
+}
+
+impl TelegramChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            bot: Bot::new(cfg.channels.telegram.bot_token.clone()),
+            voice_replies: Synthesizer::from_config(cfg).map(VoiceReplies::new),
+            shutdown: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for TelegramChannel {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        CAPABILITIES
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        run_dispatcher(self, bus).await
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let threshold = self.cfg.channels.telegram.long_reply_document_chars;
+        deliver_telegram(&self.bot, self.voice_replies.as_ref(), threshold, msg).await
+    }
+
+    async fn shutdown(&self) {
+        let token = self
+            .shutdown
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(token) = token {
+            if let Ok(stopped) = token.shutdown() {
+                stopped.await;
+            }
+        }
+        if self.cfg.channels.telegram.webhook.is_enabled() {
+            if let Err(err) = self.bot.delete_webhook().await {
+                warn!("failed to delete telegram webhook on shutdown: {err}");
+            }
+        }
+    }
+}
+
+async fn run_dispatcher(channel: &TelegramChannel, bus: MessageBus) -> Result<()> {
+    let cfg = &channel.cfg;
+    let bot = channel.bot.clone();
+    let me = bot
+        .get_me()
         .await
         .map_err(|err| anyhow!("telegram authentication failed: {err}"))?;
+    let bot_id = me.user.id;
+    let bot_username = me.username().to_string();
 
-    spawn_outbound_forwarder(bot.clone(), bus.subscribe_outbound());
-
-    let allowlist = cfg.channels.telegram.allow_from.clone();
-    let transcriber = Transcriber::from_config(&cfg);
+    let voice_replies = channel.voice_replies.clone();
+    let allowlist = Allowlist::new(&cfg.channels.telegram.allow_from);
+    let telegram_cfg = cfg.channels.telegram.clone();
+    let transcriber = Transcriber::from_config(cfg);
     let handler: UpdateHandler<anyhow::Error> =
         Update::filter_message().endpoint(move |bot: Bot, msg: Message, bus: MessageBus| {
             let allowlist = allowlist.clone();
+            let telegram_cfg = telegram_cfg.clone();
+            let bot_username = bot_username.clone();
             let transcriber = transcriber.clone();
+            let voice_replies = voice_replies.clone();
             async move {
                 if !is_allowed(&msg, &allowlist) {
                     return Ok(());
                 }
 
-                let chat_id = msg.chat.id.0.to_string();
+                let chat_id = chat_address(&msg);
                 let sender_id = msg
                     .from
                     .as_ref()
                     .map(|u| u.id.0.to_string())
                     .unwrap_or_else(|| "unknown".to_string());
+                let sender_name = msg.from.as_ref().map(display_name);
+                let message_id = Some(msg.id.0.to_string());
+                let reply_to = replied_message(&msg).map(|reply| reply.id.0.to_string());
+                let quoted_text = quoted_text(&msg);
+
+                let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+                if is_group {
+                    let mode = telegram_cfg.group_mode_for(&msg.chat.id.0.to_string());
+                    let addressed = is_addressed(&msg, bot_id, &bot_username);
+                    match mode {
+                        GroupMode::Off => return Ok(()),
+                        GroupMode::All => {}
+                        GroupMode::Mention if !addressed => return Ok(()),
+                        GroupMode::Listen if !addressed => {
+                            if let Some(text) = msg.text().or_else(|| msg.caption()) {
+                                bus.publish_inbound(InboundMessage {
+                                    channel: "telegram".to_string(),
+                                    chat_id,
+                                    sender_id,
+                                    sender_name,
+                                    content: text.to_string(),
+                                    message_id,
+                                    reply_to,
+                                    quoted_text,
+                                    observe_only: true,
+                                    ..Default::default()
+                                })
+                                .await;
+                            }
+                            return Ok(());
+                        }
+                        GroupMode::Mention | GroupMode::Listen => {}
+                    }
+                }
 
                 if let Some(text) = msg.text() {
+                    if let Some(arg) = parse_voice_command(text) {
+                        let reply = match (&voice_replies, arg) {
+                            (None, _) => "Voice replies are not configured.".to_string(),
+                            (Some(voice), "") => format!(
+                                "Voice replies: {}. Use /voice on, off or auto.",
+                                voice.mode_for(msg.chat.id.0).as_str()
+                            ),
+                            (Some(voice), arg) => match VoiceReplyMode::parse(arg) {
+                                Some(mode) => {
+                                    let reply = format!("Voice replies: {}.", mode.as_str());
+                                    voice.overrides.insert(msg.chat.id.0, mode);
+                                    reply
+                                }
+                                None => "Usage: /voice on, off or auto.".to_string(),
+                            },
+                        };
+                        reply_in_topic(&bot, &msg, reply).await?;
+                        return Ok(());
+                    }
+                    if let Some(voice) = &voice_replies {
+                        voice.record_inbound(msg.chat.id.0, false);
+                    }
+                    let content = if is_group {
+                        strip_bot_mention(text, &bot_username)
+                    } else {
+                        text.to_string()
+                    };
+                    let session_commands = !is_group || is_command_for_bot(text, &bot_username);
                     let inbound = InboundMessage {
                         channel: "telegram".to_string(),
                         chat_id,
                         sender_id,
-                        content: text.to_string(),
+                        sender_name,
+                        content,
+                        message_id,
+                        reply_to,
+                        quoted_text,
+                        session_commands,
+                        ..Default::default()
                     };
                     bus.publish_inbound(inbound).await;
-                    bot.send_chat_action(msg.chat.id, ChatAction::Typing).await?;
+                    typing_in_topic(&bot, &msg).await?;
                     return Ok(());
                 }
 
                 let media = if let Some(voice) = msg.voice() {
                     Some((
                         voice.file.id.clone(),
+                        voice.file.unique_id.0.clone(),
                         format!("voice_{}.ogg", voice.file.unique_id.0),
                         voice.file.size as usize,
                     ))
@@ -58,68 +251,80 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
                         .file_name
                         .clone()
                         .unwrap_or_else(|| format!("audio_{}.mp3", audio.file.unique_id.0));
-                    Some((audio.file.id.clone(), filename, audio.file.size as usize))
+                    Some((
+                        audio.file.id.clone(),
+                        audio.file.unique_id.0.clone(),
+                        filename,
+                        audio.file.size as usize,
+                    ))
                 } else {
                     None
                 };
 
-                if let Some((file_id, filename, file_size)) = media {
+                if let Some((file_id, unique_id, filename, file_size)) = media {
                     let Some(transcriber) = transcriber.clone() else {
-                        bot.send_message(
-                            msg.chat.id,
-                            "Voice/audio transcription is not configured.",
-                        )
-                        .await?;
+                        let text = "Voice/audio transcription is not configured.";
+                        reply_in_topic(&bot, &msg, text).await?;
                         return Ok(());
                     };
-                    if file_size > transcriber.max_bytes() {
-                        bot.send_message(
-                            msg.chat.id,
-                            format!(
-                                "Audio file is too large ({} bytes). Max allowed is {} bytes.",
-                                file_size,
-                                transcriber.max_bytes()
-                            ),
-                        )
-                        .await?;
+                    let cached = transcriber.cached(&unique_id);
+                    if cached.is_none() && file_size > transcriber.max_input_bytes() {
+                        let text = format!(
+                            "Audio file is too large ({} bytes). Max allowed is {} bytes.",
+                            file_size,
+                            transcriber.max_input_bytes()
+                        );
+                        reply_in_topic(&bot, &msg, text).await?;
                         return Ok(());
                     }
 
-                    bot.send_chat_action(msg.chat.id, ChatAction::Typing).await?;
-                    match download_telegram_file(&bot, file_id).await {
-                        Ok(data) => match transcriber.transcribe_bytes(filename, data).await {
-                            Ok(transcript) if !transcript.is_empty() => {
-                                let inbound = InboundMessage {
-                                    channel: "telegram".to_string(),
-                                    chat_id,
-                                    sender_id,
-                                    content: transcript,
-                                };
-                                bus.publish_inbound(inbound).await;
-                            }
-                            Ok(_) => {
-                                bot.send_message(
-                                    msg.chat.id,
-                                    "I couldn't extract text from that audio message.",
-                                )
-                                .await?;
+                    typing_in_topic(&bot, &msg).await?;
+                    let transcript = match cached {
+                        Some(transcript) => Ok(transcript),
+                        None => match download_telegram_file(&bot, file_id).await {
+                            Ok(data) => {
+                                transcriber
+                                    .transcribe(Some(&unique_id), filename, data)
+                                    .await
                             }
                             Err(err) => {
-                                warn!("audio transcription failed: {err}");
-                                bot.send_message(
-                                    msg.chat.id,
-                                    "I couldn't transcribe that audio message. Please retry or send text.",
-                                )
-                                .await?;
+                                warn!("audio download failed: {err}");
+                                let text = "I couldn't download that audio message from Telegram.";
+                                reply_in_topic(&bot, &msg, text).await?;
+                                return Ok(());
                             }
                         },
+                    };
+                    match transcript {
+                        Ok(transcript) if !transcript.is_empty() => {
+                            if let Some(echo) = transcriber.echo_text(&transcript) {
+                                reply_in_topic(&bot, &msg, echo).await?;
+                            }
+                            if let Some(voice) = &voice_replies {
+                                voice.record_inbound(msg.chat.id.0, true);
+                            }
+                            let inbound = InboundMessage {
+                                channel: "telegram".to_string(),
+                                chat_id,
+                                sender_id,
+                                sender_name,
+                                content: transcript,
+                                message_id,
+                                reply_to,
+                                quoted_text,
+                                ..Default::default()
+                            };
+                            bus.publish_inbound(inbound).await;
+                        }
+                        Ok(_) => {
+                            let text = "I couldn't extract text from that audio message.";
+                            reply_in_topic(&bot, &msg, text).await?;
+                        }
                         Err(err) => {
-                            warn!("audio download failed: {err}");
-                            bot.send_message(
-                                msg.chat.id,
-                                "I couldn't download that audio message from Telegram.",
-                            )
-                            .await?;
+                            warn!("audio transcription failed: {err}");
+                            let text = "I couldn't transcribe that audio message. \
+                                        Please retry or send text.";
+                            reply_in_topic(&bot, &msg, text).await?;
                         }
                     }
                 }
@@ -128,72 +333,477 @@ pub async fn start(cfg: AppConfig, bus: MessageBus) -> Result<()> {
             }
         });
 
-    Dispatcher::builder(bot, handler)
+    let webhook = &cfg.channels.telegram.webhook;
+    let options = if webhook.is_enabled() {
+        Some(webhook_options(webhook)?)
+    } else {
+        None
+    };
+
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
         .dependencies(dptree::deps![bus])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+    *channel.shutdown.lock().unwrap_or_else(|e| e.into_inner()) = Some(dispatcher.shutdown_token());
+
+    match options {
+        Some(options) => {
+            info!(
+                "telegram webhook mode: listening on {} for {}",
+                options.address, options.url
+            );
+            let listener = webhooks::axum(bot.clone(), options)
+                .await
+                .map_err(|err| anyhow!("failed to register telegram webhook: {err}"))?;
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("telegram webhook listener error"),
+                )
+                .await;
+        }
+        None => dispatcher.dispatch().await,
+    }
 
     Ok(())
 }
 
-fn is_allowed(msg: &Message, allowlist: &[String]) -> bool {
-    if allowlist.is_empty() {
-        return true;
+fn webhook_options(cfg: &TelegramWebhookConfig) -> Result<webhooks::Options> {
+    let url: url::Url = cfg
+        .url
+        .trim()
+        .parse()
+        .map_err(|err| anyhow!("invalid telegram webhook url '{}': {err}", cfg.url))?;
+    let address: SocketAddr = cfg.listen.trim().parse().map_err(|err| {
+        anyhow!(
+            "invalid telegram webhook listen address '{}': {err}",
+            cfg.listen
+        )
+    })?;
+
+    let mut options = webhooks::Options::new(address, url);
+    let path = cfg.path.trim();
+    if !path.is_empty() {
+        let path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{path}")
+        };
+        options = options.path(path);
+    }
+    let secret = cfg.secret_token.trim();
+    if !secret.is_empty() {
+        if !is_valid_secret_token(secret) {
+            return Err(anyhow!(
+                "telegram webhook secret_token must be 1-256 characters of A-Z, a-z, 0-9, _ or -"
+            ));
+        }
+        options = options.secret_token(secret.to_string());
+    }
+    Ok(options)
+}
+
+fn is_valid_secret_token(secret: &str) -> bool {
+    (1..=256).contains(&secret.len())
+        && secret
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+fn is_allowed(msg: &Message, allowlist: &Allowlist) -> bool {
+    match msg.from.as_ref() {
+        Some(user) => allowlist.allows(&user.id.0.to_string(), user.username.as_deref()),
+        None => allowlist.allows("", None),
     }
-    let user = match msg.from.as_ref() {
-        Some(u) => u,
-        None => return false,
+}
+
+/// Chat address used on the bus: the chat id, plus `:<thread id>` for
+/// messages in a forum topic so each topic gets its own session.
+fn chat_address(msg: &Message) -> String {
+    match topic_thread_id(msg) {
+        Some(thread_id) => format!("{}:{}", msg.chat.id.0, thread_id.0 .0),
+        None => msg.chat.id.0.to_string(),
+    }
+}
+
+/// Inverse of [`chat_address`].
+fn parse_chat_address(address: &str) -> Option<(i64, Option<ThreadId>)> {
+    match address.split_once(':') {
+        Some((chat, thread)) => Some((
+            chat.parse().ok()?,
+            Some(ThreadId(MessageId(thread.parse().ok()?))),
+        )),
+        None => Some((address.parse().ok()?, None)),
+    }
+}
+
+fn topic_thread_id(msg: &Message) -> Option<ThreadId> {
+    msg.thread_id.filter(|_| msg.is_topic_message)
+}
+
+/// The message being replied to. In forum topics Telegram reports the topic's
+/// root message as the reply target of every post, which is not a real reply.
+fn replied_message(msg: &Message) -> Option<&Message> {
+    msg.reply_to_message()
+        .filter(|reply| topic_thread_id(msg).is_none_or(|thread_id| reply.id != thread_id.0))
+}
+
+/// Send a text reply to the chat and forum topic `msg` came from.
+fn reply_in_topic(
+    bot: &Bot,
+    msg: &Message,
+    text: impl Into<String>,
+) -> <Bot as Requester>::SendMessage {
+    let request = bot.send_message(msg.chat.id, text);
+    match topic_thread_id(msg) {
+        Some(thread_id) => request.message_thread_id(thread_id),
+        None => request,
+    }
+}
+
+fn typing_in_topic(bot: &Bot, msg: &Message) -> <Bot as Requester>::SendChatAction {
+    let request = bot.send_chat_action(msg.chat.id, ChatAction::Typing);
+    match topic_thread_id(msg) {
+        Some(thread_id) => request.message_thread_id(thread_id),
+        None => request,
+    }
+}
+
+/// Thread a reply under `message_id`, still sending if it was deleted.
+fn reply_parameters(message_id: MessageId) -> ReplyParameters {
+    ReplyParameters::new(message_id).allow_sending_without_reply()
+}
+
+/// The selected quote of a reply, or else the full text of the message being
+/// replied to.
+fn quoted_text(msg: &Message) -> Option<String> {
+    if let Some(quote) = msg.quote() {
+        return Some(quote.text.clone());
+    }
+    replied_message(msg)
+        .and_then(|reply| reply.text().or_else(|| reply.caption()))
+        .map(str::to_string)
+}
+
+fn display_name(user: &teloxide::types::User) -> String {
+    match &user.username {
+        Some(username) => format!("{} (@{username})", user.full_name()),
+        None => user.full_name(),
+    }
+}
+
+/// Whether a group message is addressed to the bot: an @mention, a reply to
+/// one of its messages, or a command not aimed at another bot.
+fn is_addressed(msg: &Message, bot_id: UserId, bot_username: &str) -> bool {
+    let replied_to_bot = replied_message(msg)
+        .and_then(|reply| reply.from.as_ref())
+        .is_some_and(|user| user.id == bot_id);
+    let text = msg.text().or_else(|| msg.caption()).unwrap_or("");
+    replied_to_bot || mentions_bot(text, bot_username) || is_command_for_bot(text, bot_username)
+}
+
+fn find_bot_mention(text: &str, bot_username: &str) -> Option<(usize, usize)> {
+    if bot_username.is_empty() {
+        return None;
+    }
+    let needle = format!("@{}", bot_username.to_ascii_lowercase());
+    let haystack = text.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(pos) = haystack[from..].find(&needle) {
+        let start = from + pos;
+        let end = start + needle.len();
+        let boundary = haystack[end..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_'));
+        if boundary {
+            return Some((start, end));
+        }
+        from = end;
+    }
+    None
+}
+
+fn mentions_bot(text: &str, bot_username: &str) -> bool {
+    find_bot_mention(text, bot_username).is_some()
+}
+
+fn is_command_for_bot(text: &str, bot_username: &str) -> bool {
+    let Some(command) = text.split_whitespace().next() else {
+        return false;
     };
-    let uid = user.id.0.to_string();
-    let uname = user.username.as_ref().map(|u| u.to_string());
-    allowlist.iter().any(|allowed| {
-        if allowed.starts_with('@') {
-            uname
-                .as_ref()
-                .map(|u| format!("@{u}"))
-                .as_ref()
-                .map(|u| u == allowed)
-                .unwrap_or(false)
+    if !command.starts_with('/') || command.len() < 2 {
+        return false;
+    }
+    match command.split_once('@') {
+        Some((_, target)) => target.eq_ignore_ascii_case(bot_username),
+        None => true,
+    }
+}
+
+fn strip_bot_mention(text: &str, bot_username: &str) -> String {
+    let mut out = text.to_string();
+    while let Some((start, end)) = find_bot_mention(&out, bot_username) {
+        out.replace_range(start..end, "");
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_voice_command(text: &str) -> Option<&str> {
+    let mut parts = text.trim().splitn(2, char::is_whitespace);
+    let command = parts.next()?;
+    let name = command.split('@').next()?;
+    if name != "/voice" {
+        return None;
+    }
+    Some(parts.next().unwrap_or("").trim())
+}
+
+/// Deliver one outbound message. Errors are returned so the outbox can retry;
+/// a failed voice reply falls back to text instead.
+async fn deliver_telegram(
+    bot: &Bot,
+    voice_replies: Option<&VoiceReplies>,
+    document_threshold: usize,
+    msg: OutboundMessage,
+) -> Result<()> {
+    let (chat_id, thread_id) = parse_chat_address(&msg.chat_id)
+        .ok_or_else(|| anyhow!("invalid telegram chat_id: {}", msg.chat_id))?;
+    let reply_to = msg
+        .reply_to
+        .as_deref()
+        .and_then(|id| id.parse::<i32>().ok())
+        .map(MessageId);
+    let mut spoke = false;
+    if let Some(voice) = voice_replies
+        .filter(|voice| voice.should_speak(chat_id))
+        .filter(|_| !msg.content.trim().is_empty())
+    {
+        let mut action = bot.send_chat_action(ChatId(chat_id), ChatAction::RecordVoice);
+        if let Some(thread_id) = thread_id {
+            action = action.message_thread_id(thread_id);
+        }
+        let _ = action.await;
+        match voice.synthesizer.synthesize(&msg.content).await {
+            Ok(audio) => {
+                let file = InputFile::memory(audio).file_name("reply.ogg");
+                let mut request = bot.send_voice(ChatId(chat_id), file);
+                if let Some(thread_id) = thread_id {
+                    request = request.message_thread_id(thread_id);
+                }
+                if let Some(reply_to) = reply_to {
+                    request = request.reply_parameters(reply_parameters(reply_to));
+                }
+                match request.await {
+                    Ok(_) => spoke = true,
+                    Err(e) => {
+                        warn!("Failed to send Telegram voice reply to chat {chat_id}: {e}")
+                    }
+                }
+            }
+            Err(e) => warn!("voice reply synthesis failed, sending text: {e}"),
+        }
+    }
+    if !spoke && !msg.content.trim().is_empty() {
+        send_telegram_text(
+            bot,
+            ChatId(chat_id),
+            &msg.content,
+            document_threshold,
+            thread_id,
+            reply_to,
+        )
+        .await?;
+    }
+    for attachment in &msg.attachments {
+        send_telegram_attachment(bot, ChatId(chat_id), thread_id, attachment)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "failed to send attachment {}: {e}",
+                    attachment.path.display()
+                )
+            })?;
+    }
+    Ok(())
+}
+
+/// Send a markdown reply, split into chunks that fit Telegram's message limit.
+/// A chunk Telegram refuses to parse is resent as plain text, and replies
+/// longer than `document_threshold` (when non-zero) go out as a `.md` file.
+/// Messages go to the forum topic `thread_id` when set, and the first one is
+/// threaded under `reply_to`.
+async fn send_telegram_text(
+    bot: &Bot,
+    chat_id: ChatId,
+    text: &str,
+    document_threshold: usize,
+    thread_id: Option<ThreadId>,
+    mut reply_to: Option<MessageId>,
+) -> Result<()> {
+    if document_threshold > 0 && text.chars().count() > document_threshold {
+        let file = InputFile::memory(text.as_bytes().to_vec()).file_name("reply.md");
+        let mut request = bot.send_document(chat_id, file);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        if let Some(reply_to) = reply_to {
+            request = request.reply_parameters(reply_parameters(reply_to));
+        }
+        match request.await {
+            Ok(_) => return Ok(()),
+            Err(e) => warn!("Failed to send Telegram reply document to chat {chat_id}: {e}"),
+        }
+    }
+
+    let mut chunks = Vec::new();
+    render_telegram_chunks(text, TELEGRAM_CHUNK_CHARS, &mut chunks);
+    for (source, rendered) in chunks {
+        let reply = reply_to.take().map(reply_parameters);
+        let mut request = bot
+            .send_message(chat_id, rendered)
+            .parse_mode(ParseMode::MarkdownV2);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        if let Some(reply) = reply.clone() {
+            request = request.reply_parameters(reply);
+        }
+        match request.await {
+            Ok(_) => {}
+            Err(RequestError::Api(ApiError::CantParseEntities(err))) => {
+                warn!(
+                    "Telegram rejected MarkdownV2 for chat {chat_id} ({err}); resending as plain text"
+                );
+                let mut request = bot.send_message(chat_id, source);
+                if let Some(thread_id) = thread_id {
+                    request = request.message_thread_id(thread_id);
+                }
+                if let Some(reply) = reply {
+                    request = request.reply_parameters(reply);
+                }
+                request.await?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Split `text` into `(source, rendered)` pairs whose MarkdownV2 rendering
+/// fits in one message, halving the source budget when escaping overflows it.
+fn render_telegram_chunks(text: &str, limit: usize, out: &mut Vec<(String, String)>) {
+    for chunk in split_markdown_chunks(text, limit) {
+        let rendered = markdown_to_telegram_markdown_v2(&chunk);
+        if rendered.is_empty() {
+            continue;
+        }
+        if !CAPABILITIES.fits(&rendered) && limit > 256 {
+            render_telegram_chunks(&chunk, limit / 2, out);
         } else {
-            allowed == &uid || uname.as_ref().map(|u| u == allowed).unwrap_or(false)
+            out.push((chunk, rendered));
         }
-    })
+    }
 }
 
-fn spawn_outbound_forwarder(
-    bot: Bot,
-    mut outbound_rx: tokio::sync::broadcast::Receiver<crate::bus::OutboundMessage>,
-) {
-    tokio::spawn(async move {
-        loop {
-            let msg = match outbound_rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    info!("outbound channel closed, telegram forwarder shutting down");
-                    break;
+/// Split markdown on line boundaries into chunks of at most `limit` chars.
+/// A fenced code block cut by a split is closed at the end of one chunk and
+/// reopened (with its info string) at the start of the next.
+fn split_markdown_chunks(text: &str, limit: usize) -> Vec<String> {
+    fn fence_marker(line: &str) -> Option<&str> {
+        let trimmed = line.trim_start();
+        let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = trimmed.chars().take_while(|c| *c == ch).count();
+        (len >= 3).then(|| &trimmed[..len])
+    }
+
+    let limit = limit.max(16);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0usize;
+    // Opening fence line and its marker while inside a fenced block.
+    let mut open_fence: Option<(String, String)> = None;
+
+    for line in text.lines() {
+        let marker = fence_marker(line);
+        let reserve = open_fence
+            .as_ref()
+            .map(|(_, marker)| marker.len() + 1)
+            .unwrap_or(0)
+            .max(marker.map(|m| m.len() + 1).unwrap_or(0));
+        let reopen_len = open_fence
+            .as_ref()
+            .map(|(fence, _)| fence.chars().count() + 1)
+            .unwrap_or(0);
+        let budget = limit.saturating_sub(reserve + reopen_len).max(8);
+
+        let chars = line.chars().collect::<Vec<_>>();
+        let pieces = if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars
+                .chunks(budget)
+                .map(|piece| piece.iter().collect::<String>())
+                .collect()
+        };
+
+        for piece in pieces {
+            let piece_len = piece.chars().count() + 1;
+            if current_len > 0 && current_len + piece_len + reserve > limit {
+                if let Some((_, marker)) = &open_fence {
+                    current.push_str(marker);
                 }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    warn!("telegram outbound lagged, skipped {skipped} message(s)");
-                    continue;
+                chunks.push(current.trim_end().to_string());
+                current = String::new();
+                current_len = 0;
+                if let Some((fence, _)) = &open_fence {
+                    current.push_str(fence);
+                    current.push('\n');
+                    current_len = fence.chars().count() + 1;
                 }
-            };
-            if msg.channel != "telegram" {
-                continue;
             }
-            if let Ok(chat_id) = msg.chat_id.parse::<i64>() {
-                let rendered = markdown_to_telegram_markdown_v2(&msg.content);
-                if let Err(e) = bot
-                    .send_message(ChatId(chat_id), rendered)
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .await
-                {
-                    warn!("Failed to send Telegram message to chat {chat_id}: {e}");
+            current.push_str(&piece);
+            current.push('\n');
+            current_len += piece_len;
+        }
+
+        if let Some(marker) = marker {
+            match &open_fence {
+                Some((_, open_marker)) if marker.starts_with(open_marker.as_str()) => {
+                    open_fence = None;
                 }
+                Some(_) => {}
+                None => open_fence = Some((line.trim_start().to_string(), marker.to_string())),
             }
         }
-    });
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current.trim_end().to_string());
+    }
+    chunks
+}
+
+async fn send_telegram_attachment(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread_id: Option<ThreadId>,
+    attachment: &OutboundAttachment,
+) -> Result<()> {
+    let file = InputFile::file(attachment.path.clone()).file_name(attachment.file_name());
+    if attachment.is_image() {
+        let mut request = bot.send_photo(chat_id, file);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        request.await?;
+    } else {
+        let mut request = bot.send_document(chat_id, file);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        request.await?;
+    }
+    Ok(())
 }
 
 fn markdown_to_telegram_markdown_v2(input: &str) -> String {
@@ -436,7 +1046,138 @@ async fn download_telegram_file(bot: &Bot, file_id: FileId) -> Result<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use super::markdown_to_telegram_markdown_v2;
+    use super::{
+        is_command_for_bot, markdown_to_telegram_markdown_v2, mentions_bot, parse_chat_address,
+        parse_voice_command, quoted_text, render_telegram_chunks, replied_message,
+        split_markdown_chunks, strip_bot_mention, webhook_options, Message, CAPABILITIES,
+    };
+    use crate::config::TelegramWebhookConfig;
+
+    #[test]
+    fn webhook_options_default_path_to_url_and_validate_secret() {
+        let mut cfg = TelegramWebhookConfig {
+            url: "https://bot.example.com/tg/hook".to_string(),
+            listen: "127.0.0.1:8443".to_string(),
+            path: String::new(),
+            secret_token: "s3cret_token-1".to_string(),
+        };
+        let options = webhook_options(&cfg).unwrap();
+        assert_eq!(options.path, "/tg/hook");
+        assert_eq!(options.address.port(), 8443);
+        assert_eq!(options.secret_token.as_deref(), Some("s3cret_token-1"));
+
+        cfg.path = "internal".to_string();
+        assert_eq!(webhook_options(&cfg).unwrap().path, "/internal");
+
+        cfg.secret_token = "not valid!".to_string();
+        assert!(webhook_options(&cfg).is_err());
+    }
+
+    #[test]
+    fn parses_chat_addresses_with_forum_topics() {
+        assert_eq!(parse_chat_address("-1001234"), Some((-1001234, None)));
+        let (chat, thread) = parse_chat_address("-1001234:42").unwrap();
+        assert_eq!(chat, -1001234);
+        assert_eq!(thread.map(|t| t.0 .0), Some(42));
+        assert_eq!(parse_chat_address("local"), None);
+        assert_eq!(parse_chat_address("-1001234:general"), None);
+    }
+
+    #[test]
+    fn detects_bot_mentions_on_word_boundary() {
+        assert!(mentions_bot(
+            "hey @LightClaw_bot what's up",
+            "lightclaw_bot"
+        ));
+        assert!(mentions_bot("@lightclaw_bot", "lightclaw_bot"));
+        assert!(!mentions_bot("hey @lightclaw_bot2", "lightclaw_bot"));
+        assert!(!mentions_bot("no mention here", "lightclaw_bot"));
+    }
+
+    #[test]
+    fn commands_for_other_bots_are_not_addressed() {
+        assert!(is_command_for_bot("/status", "lightclaw_bot"));
+        assert!(is_command_for_bot(
+            "/status@lightclaw_bot now",
+            "lightclaw_bot"
+        ));
+        assert!(!is_command_for_bot("/status@other_bot", "lightclaw_bot"));
+        assert!(!is_command_for_bot("status", "lightclaw_bot"));
+    }
+
+    #[test]
+    fn strips_bot_mention_from_text() {
+        assert_eq!(
+            strip_bot_mention("@lightclaw_bot summarize   this", "lightclaw_bot"),
+            "summarize this"
+        );
+    }
+
+    #[test]
+    fn short_text_is_a_single_chunk() {
+        assert_eq!(
+            split_markdown_chunks("hello\nworld", 100),
+            vec!["hello\nworld".to_string()]
+        );
+    }
+
+    #[test]
+    fn splits_on_line_boundaries_within_limit() {
+        let text = (0..50)
+            .map(|i| format!("line number {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let chunks = split_markdown_chunks(&text, 100);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 100));
+        assert_eq!(chunks.join("\n"), text);
+    }
+
+    #[test]
+    fn split_code_block_is_closed_and_reopened() {
+        let body = (0..30)
+            .map(|i| format!("let x{i} = {i};"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = format!("Intro\n```rust\n{body}\n```\nOutro");
+        let chunks = split_markdown_chunks(&text, 120);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 120);
+            assert_eq!(chunk.matches("```").count() % 2, 0, "unbalanced: {chunk}");
+        }
+        assert!(chunks[1].starts_with("```rust\n"));
+    }
+
+    #[test]
+    fn hard_splits_overlong_lines() {
+        let text = "x".repeat(250);
+        let chunks = split_markdown_chunks(&text, 100);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 100));
+    }
+
+    #[test]
+    fn rendered_chunks_fit_telegram_limit_despite_escaping() {
+        let text = "a.b-c!".repeat(2000);
+        let mut chunks = Vec::new();
+        render_telegram_chunks(&text, 3500, &mut chunks);
+        assert!(chunks
+            .iter()
+            .all(|(_, rendered)| CAPABILITIES.fits(rendered)));
+    }
+
+    #[test]
+    fn parses_voice_command_with_bot_suffix() {
+        assert_eq!(parse_voice_command("/voice on"), Some("on"));
+        assert_eq!(
+            parse_voice_command("/voice@lightclaw_bot auto"),
+            Some("auto")
+        );
+        assert_eq!(parse_voice_command("/voice"), Some(""));
+        assert_eq!(parse_voice_command("/voiceover"), None);
+        assert_eq!(parse_voice_command("voice on"), None);
+    }
 
     #[test]
     fn renders_multiline_blockquote_lines() {
@@ -444,4 +1185,62 @@ mod tests {
         let rendered = markdown_to_telegram_markdown_v2(input);
         assert_eq!(rendered, "\\> first line\n\\> second line");
     }
+
+    fn message(extra: serde_json::Value) -> Message {
+        let mut value = serde_json::json!({
+            "message_id": 7,
+            "date": 1_700_000_000,
+            "chat": { "id": -1001, "type": "supergroup", "title": "team" },
+            "from": { "id": 42, "is_bot": false, "first_name": "Ana" },
+            "text": "sounds good",
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn extracts_reply_target_and_quote() {
+        let original = serde_json::json!({
+            "message_id": 3,
+            "date": 1_700_000_000,
+            "chat": { "id": -1001, "type": "supergroup", "title": "team" },
+            "text": "Standup moves to 10:30 tomorrow",
+        });
+        let reply = message(serde_json::json!({ "reply_to_message": original }));
+        assert_eq!(replied_message(&reply).map(|m| m.id.0), Some(3));
+        assert_eq!(
+            quoted_text(&reply).as_deref(),
+            Some("Standup moves to 10:30 tomorrow")
+        );
+
+        let quoted = message(serde_json::json!({
+            "reply_to_message": original,
+            "quote": { "text": "10:30", "position": 20 },
+        }));
+        assert_eq!(quoted_text(&quoted).as_deref(), Some("10:30"));
+
+        let plain = message(serde_json::json!({}));
+        assert!(replied_message(&plain).is_none());
+        assert!(quoted_text(&plain).is_none());
+    }
+
+    #[test]
+    fn topic_root_is_not_a_reply() {
+        let root = serde_json::json!({
+            "message_id": 100,
+            "date": 1_700_000_000,
+            "chat": { "id": -1001, "type": "supergroup", "title": "team", "is_forum": true },
+            "forum_topic_created": { "name": "Ops", "icon_color": 7322096 },
+        });
+        let msg = message(serde_json::json!({
+            "message_thread_id": 100,
+            "is_topic_message": true,
+            "reply_to_message": root,
+        }));
+        assert!(replied_message(&msg).is_none());
+        assert!(quoted_text(&msg).is_none());
+    }
 }
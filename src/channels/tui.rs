@@ -0,0 +1,808 @@
+use crate::bus::{AgentEvent, AgentEventKind, InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::{Channel, ChannelCapabilities};
+use crate::config::AppConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures_util::StreamExt;
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::Mutex;
+use tokio::sync::{broadcast, mpsc};
+
+const DEFAULT_SESSION: &str = "local";
+/// Input rows shown before the editor starts scrolling.
+const MAX_INPUT_ROWS: usize = 6;
+const HELP: &str = "Enter sends, Alt+Enter or Ctrl+J adds a line. PgUp/PgDn scroll, Tab and \
+Shift+Tab switch sessions, Ctrl+N opens one, Ctrl+T expands tool calls, Ctrl+C quits.\n\
+Commands: /new [name], /switch <name>, /sessions, /exit; /reset, /status and /model go to the agent.";
+
+/// Full-screen terminal chat. Each session is its own conversation (the
+/// session name is the chat id); replies to sessions in the background are
+/// kept and flagged as unread.
+pub struct TuiChannel {
+    /// First configured route, shown until a turn reports the one it used.
+    model: String,
+    updates: mpsc::UnboundedSender<Update>,
+    inbox: Mutex<Option<mpsc::UnboundedReceiver<Update>>>,
+}
+
+enum Update {
+    Outbound(OutboundMessage),
+    Agent(AgentEvent),
+}
+
+impl TuiChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        let (updates, inbox) = mpsc::unbounded_channel();
+        Self {
+            model: cfg
+                .model_routes()
+                .first()
+                .map(|route| format!("{} / {}", route.provider.as_str(), route.model))
+                .unwrap_or_else(|| "none".to_string()),
+            updates,
+            inbox: Mutex::new(Some(inbox)),
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for TuiChannel {
+    fn name(&self) -> &'static str {
+        "tui"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: true,
+        }
+    }
+
+    /// Runs until the user quits with `/exit` or Ctrl+C.
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let inbox = self
+            .inbox
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| anyhow!("the TUI is already running"))?;
+        tokio::spawn(forward_agent_events(
+            bus.subscribe_events(),
+            self.updates.clone(),
+        ));
+
+        let mut terminal = ratatui::init();
+        let result = run_ui(&mut terminal, App::new(&self.model), bus, inbox).await;
+        ratatui::restore();
+        result
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        self.updates
+            .send(Update::Outbound(msg))
+            .map_err(|_| anyhow!("the TUI has exited"))
+    }
+}
+
+async fn forward_agent_events(
+    mut events: broadcast::Receiver<AgentEvent>,
+    updates: mpsc::UnboundedSender<Update>,
+) {
+    loop {
+        match events.recv().await {
+            Ok(event) if event.channel == "tui" => {
+                if updates.send(Update::Agent(event)).is_err() {
+                    break;
+                }
+            }
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+async fn run_ui(
+    terminal: &mut DefaultTerminal,
+    mut app: App,
+    bus: MessageBus,
+    mut inbox: mpsc::UnboundedReceiver<Update>,
+) -> Result<()> {
+    let mut keys = EventStream::new();
+    loop {
+        terminal.draw(|frame| app.render(frame))?;
+        let action = tokio::select! {
+            event = keys.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => app.on_key(key),
+                Some(Ok(_)) => Action::None,
+                Some(Err(err)) => return Err(err.into()),
+                None => Action::Quit,
+            },
+            Some(update) = inbox.recv() => {
+                app.apply(update);
+                Action::None
+            }
+        };
+        match action {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Send { session, text } => {
+                bus.publish_inbound(InboundMessage {
+                    channel: "tui".to_string(),
+                    chat_id: session,
+                    sender_id: "local".to_string(),
+                    content: text,
+                    message_id: Some(uuid::Uuid::new_v4().to_string()),
+                    session_commands: true,
+                    ..Default::default()
+                })
+                .await;
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Quit,
+    Send { session: String, text: String },
+}
+
+enum Entry {
+    User(String),
+    Assistant(String),
+    Notice(String),
+    Tool {
+        name: String,
+        args: String,
+        result: Option<String>,
+    },
+}
+
+struct Session {
+    name: String,
+    entries: Vec<Entry>,
+    unread: bool,
+    /// Waiting for the answer to a message.
+    busy: bool,
+    /// Transcript lines scrolled up from the bottom.
+    scroll: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl Session {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            entries: Vec::new(),
+            unread: false,
+            busy: false,
+            scroll: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+        }
+    }
+}
+
+struct App {
+    sessions: Vec<Session>,
+    current: usize,
+    input: Editor,
+    /// Route that answered the last turn.
+    model: String,
+    fallback: bool,
+    tools_expanded: bool,
+    /// Transcript height at the last render, for page-wise scrolling.
+    page: usize,
+}
+
+impl App {
+    fn new(model: &str) -> Self {
+        let mut session = Session::new(DEFAULT_SESSION);
+        session.entries.push(Entry::Notice(HELP.to_string()));
+        Self {
+            sessions: vec![session],
+            current: 0,
+            input: Editor::default(),
+            model: model.to_string(),
+            fallback: false,
+            tools_expanded: false,
+            page: 10,
+        }
+    }
+
+    fn session_index(&mut self, name: &str) -> usize {
+        match self.sessions.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                self.sessions.push(Session::new(name));
+                self.sessions.len() - 1
+            }
+        }
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        self.current = index;
+        self.sessions[index].unread = false;
+    }
+
+    fn notice(&mut self, text: impl Into<String>) {
+        let current = self.current;
+        self.sessions[current]
+            .entries
+            .push(Entry::Notice(text.into()));
+        self.sessions[current].scroll = 0;
+    }
+
+    fn apply(&mut self, update: Update) {
+        let (chat_id, entry) = match update {
+            Update::Outbound(msg) => {
+                let index = self.session_index(&msg.chat_id);
+                let session = &mut self.sessions[index];
+                if !msg.content.trim().is_empty() {
+                    session
+                        .entries
+                        .push(Entry::Assistant(msg.content.trim().to_string()));
+                }
+                for attachment in &msg.attachments {
+                    session.entries.push(Entry::Notice(format!(
+                        "[file] {}",
+                        attachment.path.display()
+                    )));
+                }
+                // The answer to a message replies to it; `send_message`
+                // output arrives while the turn is still running.
+                if msg.reply_to.is_some() {
+                    session.busy = false;
+                }
+                (msg.chat_id, None)
+            }
+            Update::Agent(event) => match event.kind {
+                AgentEventKind::ToolCall { name, args } => (
+                    event.chat_id,
+                    Some(Entry::Tool {
+                        name,
+                        args,
+                        result: None,
+                    }),
+                ),
+                AgentEventKind::ToolResult { name, result } => {
+                    let index = self.session_index(&event.chat_id);
+                    let pending =
+                        self.sessions[index].entries.iter_mut().rev().find_map(
+                            |entry| match entry {
+                                Entry::Tool {
+                                    name: called,
+                                    result: slot @ None,
+                                    ..
+                                } if *called == name => Some(slot),
+                                _ => None,
+                            },
+                        );
+                    if let Some(slot) = pending {
+                        *slot = Some(result);
+                    }
+                    return;
+                }
+                AgentEventKind::TurnCompleted {
+                    route,
+                    fallback,
+                    input_tokens,
+                    output_tokens,
+                } => {
+                    let index = self.session_index(&event.chat_id);
+                    let session = &mut self.sessions[index];
+                    session.input_tokens += input_tokens;
+                    session.output_tokens += output_tokens;
+                    self.model = route;
+                    self.fallback = fallback;
+                    return;
+                }
+            },
+        };
+        let index = self.session_index(&chat_id);
+        if let Some(entry) = entry {
+            self.sessions[index].entries.push(entry);
+        }
+        if index == self.current {
+            self.sessions[index].scroll = 0;
+        } else {
+            self.sessions[index].unread = true;
+        }
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Char('d') if ctrl && self.input.is_empty() => return Action::Quit,
+            KeyCode::Char('n') if ctrl => self.new_session(None),
+            KeyCode::Char('t') if ctrl => self.tools_expanded = !self.tools_expanded,
+            KeyCode::Char('j') if ctrl => self.input.newline(),
+            KeyCode::Enter if alt || key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.input.newline()
+            }
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Tab => self.switch_to((self.current + 1) % self.sessions.len()),
+            KeyCode::BackTab => {
+                let count = self.sessions.len();
+                self.switch_to((self.current + count - 1) % count);
+            }
+            KeyCode::PageUp => self.sessions[self.current].scroll += self.page.max(2) / 2,
+            KeyCode::PageDown => {
+                let session = &mut self.sessions[self.current];
+                session.scroll = session.scroll.saturating_sub(self.page.max(2) / 2);
+            }
+            KeyCode::Char(c) if !ctrl => self.input.insert(c),
+            KeyCode::Backspace => self.input.backspace(),
+            KeyCode::Delete => self.input.delete(),
+            KeyCode::Left => self.input.left(),
+            KeyCode::Right => self.input.right(),
+            KeyCode::Up => self.input.up(),
+            KeyCode::Down => self.input.down(),
+            KeyCode::Home => self.input.col = 0,
+            KeyCode::End => self.input.col = self.input.line_len(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn new_session(&mut self, name: Option<&str>) {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => (self.sessions.len() + 1..)
+                .map(|n| format!("chat-{n}"))
+                .find(|name| self.sessions.iter().all(|s| &s.name != name))
+                .unwrap_or_default(),
+        };
+        let index = self.session_index(&name);
+        self.switch_to(index);
+    }
+
+    /// Send the input to the agent, or run it when it is a TUI command.
+    fn submit(&mut self) -> Action {
+        let text = self.input.take();
+        let text = text.trim();
+        if text.is_empty() {
+            return Action::None;
+        }
+        let (command, arg) = text.split_once(' ').unwrap_or((text, ""));
+        let arg = arg.trim();
+        match command {
+            "/exit" | "/quit" => return Action::Quit,
+            "/help" => self.notice(HELP),
+            "/new" => self.new_session((!arg.is_empty()).then_some(arg)),
+            "/switch" if !arg.is_empty() => {
+                let index = self.session_index(arg);
+                self.switch_to(index);
+            }
+            "/switch" => self.notice("Usage: /switch <session>"),
+            "/sessions" => {
+                let names = self
+                    .sessions
+                    .iter()
+                    .map(|s| s.name.as_str())
+                    .collect::<Vec<_>>();
+                self.notice(format!("Sessions: {}", names.join(", ")));
+            }
+            _ => {
+                let session = &mut self.sessions[self.current];
+                session.entries.push(Entry::User(text.to_string()));
+                session.busy = true;
+                session.scroll = 0;
+                return Action::Send {
+                    session: session.name.clone(),
+                    text: text.to_string(),
+                };
+            }
+        }
+        Action::None
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let input_rows = self.input.lines.len().clamp(1, MAX_INPUT_ROWS) as u16;
+        let [tabs_area, transcript_area, input_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(input_rows + 2),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let titles = self.sessions.iter().map(|session| {
+            let marker = if session.busy {
+                " …"
+            } else if session.unread {
+                " •"
+            } else {
+                ""
+            };
+            format!(" {}{marker} ", session.name)
+        });
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.current)
+                .highlight_style(Style::new().fg(Color::Black).bg(Color::Cyan))
+                .divider(""),
+            tabs_area,
+        );
+
+        self.render_transcript(frame, transcript_area);
+        self.render_input(frame, input_area);
+
+        let session = &self.sessions[self.current];
+        let route = if self.fallback {
+            format!("{} (fallback)", self.model)
+        } else {
+            self.model.clone()
+        };
+        let status = Line::from(vec![
+            Span::raw(format!(" {route} ")).bold(),
+            Span::raw(format!(
+                "│ tokens {} in / {} out ",
+                session.input_tokens, session.output_tokens
+            )),
+            Span::raw(format!(
+                "│ {} ({}/{}) ",
+                session.name,
+                self.current + 1,
+                self.sessions.len()
+            )),
+            Span::raw(if session.busy { "│ working… " } else { "" }),
+        ]);
+        frame.render_widget(
+            Paragraph::new(status).style(Style::new().bg(Color::DarkGray).fg(Color::White)),
+            status_area,
+        );
+    }
+
+    fn render_transcript(&mut self, frame: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        self.page = height;
+        let lines = transcript_lines(&self.sessions[self.current], width, self.tools_expanded);
+
+        let session = &mut self.sessions[self.current];
+        session.scroll = session.scroll.min(lines.len().saturating_sub(height));
+        let end = lines.len() - session.scroll;
+        let start = end.saturating_sub(height);
+        let title = if session.scroll > 0 {
+            format!(" {} (scrolled, PgDn for newer) ", session.name)
+        } else {
+            format!(" {} ", session.name)
+        };
+        frame.render_widget(
+            Paragraph::new(lines[start..end].to_vec()).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    fn render_input(&self, frame: &mut Frame, area: Rect) {
+        let rows = area.height.saturating_sub(2) as usize;
+        let first = (self.input.row + 1).saturating_sub(rows);
+        let text = self.input.lines[first..]
+            .iter()
+            .take(rows)
+            .map(|line| Line::raw(line.clone()))
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(text).block(
+                Block::bordered().title(" Message (Enter to send, Alt+Enter for a new line) "),
+            ),
+            area,
+        );
+        let before_cursor = self.input.lines[self.input.row]
+            .chars()
+            .take(self.input.col)
+            .count() as u16;
+        frame.set_cursor_position(Position::new(
+            (area.x + 1 + before_cursor).min(area.right().saturating_sub(2)),
+            area.y + 1 + (self.input.row - first) as u16,
+        ));
+    }
+}
+
+/// The session's transcript, wrapped to `width`.
+fn transcript_lines(session: &Session, width: usize, tools_expanded: bool) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let dim = Style::new().fg(Color::DarkGray);
+    for entry in &session.entries {
+        match entry {
+            Entry::User(text) => {
+                lines.push(Line::styled("you", Style::new().fg(Color::Cyan).bold()));
+                lines.extend(wrap(text, width).into_iter().map(Line::raw));
+            }
+            Entry::Assistant(text) => {
+                lines.push(Line::styled(
+                    "lightclaw",
+                    Style::new().fg(Color::Green).bold(),
+                ));
+                lines.extend(wrap(text, width).into_iter().map(Line::raw));
+            }
+            Entry::Notice(text) => {
+                lines.extend(
+                    wrap(text, width)
+                        .into_iter()
+                        .map(|line| Line::styled(line, dim.add_modifier(Modifier::ITALIC))),
+                );
+            }
+            Entry::Tool { name, args, result } => {
+                let state = match result {
+                    Some(_) => "done",
+                    None => "running",
+                };
+                let arrow = if tools_expanded { "▾" } else { "▸" };
+                lines.push(Line::styled(
+                    format!("{arrow} tool {name} ({state})"),
+                    Style::new().fg(Color::Yellow),
+                ));
+                if tools_expanded {
+                    let detail_width = width.saturating_sub(4).max(1);
+                    for (label, text) in [("args", Some(args)), ("result", result.as_ref())] {
+                        let Some(text) = text else { continue };
+                        for line in wrap(&format!("{label}: {text}"), detail_width) {
+                            lines.push(Line::styled(format!("    {line}"), dim));
+                        }
+                    }
+                }
+            }
+        }
+        lines.push(Line::default());
+    }
+    lines
+}
+
+/// Word-wrap `text` to `width` characters, splitting words that are longer
+/// than a line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    for raw in text.lines() {
+        let mut line = String::new();
+        let mut len = 0;
+        for word in raw.split(' ') {
+            let mut word: Vec<char> = word.chars().collect();
+            if len > 0 && len + 1 + word.len() > width {
+                out.push(std::mem::take(&mut line));
+                len = 0;
+            }
+            if len > 0 {
+                line.push(' ');
+                len += 1;
+            }
+            while len + word.len() > width {
+                let rest = word.split_off(width - len);
+                line.extend(word);
+                out.push(std::mem::take(&mut line));
+                len = 0;
+                word = rest;
+            }
+            len += word.len();
+            line.extend(word);
+        }
+        out.push(line);
+    }
+    out
+}
+
+/// Multi-line input with a cursor. `col` counts characters.
+struct Editor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+        }
+    }
+}
+
+impl Editor {
+    fn is_empty(&self) -> bool {
+        self.lines.iter().all(String::is_empty)
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+    }
+
+    fn insert(&mut self, c: char) {
+        let at = self.byte_index();
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    fn newline(&mut self) {
+        let at = self.byte_index();
+        let rest = self.lines[self.row].split_off(at);
+        self.row += 1;
+        self.lines.insert(self.row, rest);
+        self.col = 0;
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_index();
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len() {
+            let at = self.byte_index();
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    fn right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    fn up(&mut self) {
+        if self.row > 0 {
+            self.row -= 1;
+            self.col = self.col.min(self.line_len());
+        }
+    }
+
+    fn down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = self.col.min(self.line_len());
+        }
+    }
+
+    /// The whole text; the editor is left empty.
+    fn take(&mut self) -> String {
+        std::mem::take(self).lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            app.on_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    #[test]
+    fn editor_handles_multiline_input_and_commands() {
+        let mut app = App::new("openrouter / test-model");
+        type_text(&mut app, "héllo");
+        app.on_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT));
+        type_text(&mut app, "wrld");
+        for _ in 0..3 {
+            app.on_key(key(KeyCode::Left));
+        }
+        type_text(&mut app, "o");
+        app.on_key(key(KeyCode::Up));
+        app.on_key(key(KeyCode::End));
+        app.on_key(key(KeyCode::Delete));
+        app.on_key(key(KeyCode::Backspace));
+        assert_eq!(
+            app.on_key(key(KeyCode::Enter)),
+            Action::Send {
+                session: "local".to_string(),
+                text: "héllworld".to_string(),
+            }
+        );
+        assert!(app.input.is_empty());
+
+        type_text(&mut app, "/new ops");
+        assert_eq!(app.on_key(key(KeyCode::Enter)), Action::None);
+        assert_eq!(app.sessions[app.current].name, "ops");
+        type_text(&mut app, "/exit");
+        assert_eq!(app.on_key(key(KeyCode::Enter)), Action::Quit);
+    }
+
+    #[test]
+    fn routes_updates_to_their_sessions_and_renders_status() {
+        let mut app = App::new("openrouter / test-model");
+        type_text(&mut app, "check the build");
+        app.on_key(key(KeyCode::Enter));
+        app.new_session(Some("ops"));
+
+        let event = |kind| {
+            Update::Agent(AgentEvent {
+                channel: "tui".to_string(),
+                chat_id: "local".to_string(),
+                kind,
+            })
+        };
+        app.apply(event(AgentEventKind::ToolCall {
+            name: "exec".to_string(),
+            args: "{\"command\":\"cargo build\"}".to_string(),
+        }));
+        app.apply(event(AgentEventKind::ToolResult {
+            name: "exec".to_string(),
+            result: "Finished".to_string(),
+        }));
+        app.apply(event(AgentEventKind::TurnCompleted {
+            route: "openai / backup-model".to_string(),
+            fallback: true,
+            input_tokens: 1200,
+            output_tokens: 80,
+        }));
+        app.apply(Update::Outbound(OutboundMessage {
+            channel: "tui".to_string(),
+            chat_id: "local".to_string(),
+            content: "The build passes.".to_string(),
+            attachments: Vec::new(),
+            reply_to: Some("m1".to_string()),
+        }));
+        assert!(app.sessions[0].unread);
+        assert!(!app.sessions[0].busy);
+
+        app.on_key(key(KeyCode::BackTab));
+        assert!(!app.sessions[0].unread);
+        app.on_key(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL));
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(80)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(screen.contains("▾ tool exec (done)"));
+        assert!(screen.contains("result: Finished"));
+        assert!(screen.contains("The build passes."));
+        assert!(screen.contains("openai / backup-model (fallback)"));
+        assert!(screen.contains("tokens 1200 in / 80 out"));
+        assert!(screen.contains("│ local (1/2)"));
+    }
+}
@@ -0,0 +1,669 @@
+use crate::bus::{AgentEvent, AgentEventKind, InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::{secrets_match, Channel, ChannelCapabilities};
+use crate::config::{AppConfig, WebChatConfig};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{info, warn};
+
+const INDEX_HTML: &str = include_str!("webchat.html");
+const MAX_USER_NAME_LEN: usize = 32;
+const MAX_TITLE_CHARS: usize = 40;
+/// How long a login token stays valid.
+const LOGIN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Browser chat served from the binary: a static page, a login endpoint, a
+/// WebSocket per tab and file uploads into the workspace. Each user has any
+/// number of conversations, each its own session (`<user>/<session id>`).
+/// Replies arrive as whole messages; the agent does not stream tokens.
+pub struct WebChatChannel {
+    cfg: WebChatConfig,
+    state: Arc<ChatState>,
+    stop: watch::Sender<bool>,
+}
+
+struct ChatState {
+    /// Login passwords by user name.
+    users: BTreeMap<String, String>,
+    /// Uploads are saved under `<uploads_dir>/<user>/`.
+    uploads_dir: PathBuf,
+    /// Login tokens, with the user each one belongs to and its expiry.
+    logins: DashMap<String, (String, Instant)>,
+    /// Conversations per user, oldest first.
+    sessions: DashMap<String, Vec<Conversation>>,
+    /// Open sockets per user; every tab of a user sees the same updates.
+    sockets: DashMap<String, Vec<mpsc::UnboundedSender<Value>>>,
+}
+
+struct Conversation {
+    id: String,
+    title: String,
+    /// Transcript entries as sent to the browser.
+    entries: Vec<Value>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    chat: Arc<ChatState>,
+    bus: MessageBus,
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    user: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    name: String,
+}
+
+/// Messages a browser sends over its socket.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Start an empty conversation.
+    New,
+    /// Show the transcript of a conversation.
+    Open { session: String },
+    /// Ask the agent; an unknown or empty session starts a new conversation.
+    Send {
+        #[serde(default)]
+        session: String,
+        text: String,
+    },
+}
+
+impl WebChatChannel {
+    pub fn new(cfg: &AppConfig) -> Self {
+        Self {
+            cfg: cfg.channels.webchat.clone(),
+            state: Arc::new(ChatState {
+                users: cfg.channels.webchat.users.clone(),
+                uploads_dir: cfg.workspace_dir.join("uploads").join("webchat"),
+                logins: DashMap::new(),
+                sessions: DashMap::new(),
+                sockets: DashMap::new(),
+            }),
+            stop: watch::channel(false).0,
+        }
+    }
+
+    fn router(&self, bus: MessageBus) -> Router {
+        let upload_limit = usize::try_from(self.cfg.max_upload_bytes).unwrap_or(usize::MAX);
+        Router::new()
+            .route("/", get(|| async { Html(INDEX_HTML) }))
+            .route("/api/login", post(login))
+            .route(
+                "/api/upload",
+                post(upload).layer(DefaultBodyLimit::max(upload_limit)),
+            )
+            .route("/ws", get(connect))
+            .with_state(AppState {
+                chat: self.state.clone(),
+                bus,
+            })
+    }
+}
+
+#[async_trait]
+impl Channel for WebChatChannel {
+    fn name(&self) -> &'static str {
+        "webchat"
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: false,
+        }
+    }
+
+    async fn start(&self, bus: MessageBus) -> Result<()> {
+        let mut stop = self.stop.subscribe();
+        let listener = tokio::net::TcpListener::bind(self.cfg.bind.trim())
+            .await
+            .map_err(|err| anyhow!("cannot listen on {}: {err}", self.cfg.bind))?;
+        info!("web chat listening on http://{}", listener.local_addr()?);
+        tokio::spawn(forward_tool_events(
+            self.state.clone(),
+            bus.subscribe_events(),
+        ));
+        axum::serve(listener, self.router(bus))
+            .with_graceful_shutdown(async move {
+                let _ = stop.changed().await;
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn send(&self, msg: OutboundMessage) -> Result<()> {
+        let (user, session) = msg
+            .chat_id
+            .split_once('/')
+            .ok_or_else(|| anyhow!("invalid web chat address '{}'", msg.chat_id))?;
+        self.state.record(
+            user,
+            session,
+            json!({
+                "type": "message",
+                "session": session,
+                "role": "assistant",
+                "text": msg.content,
+                // The answer to a turn replies to the user's message; other
+                // messages (e.g. from `send_message`) arrive while it runs.
+                "done": msg.reply_to.is_some(),
+            }),
+        );
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+}
+
+impl ChatState {
+    /// Append `entry` to a conversation's transcript and push it to the
+    /// user's open tabs.
+    fn record(&self, user: &str, session: &str, entry: Value) {
+        if let Some(mut conversations) = self.sessions.get_mut(user) {
+            if let Some(conversation) = conversations.iter_mut().find(|c| c.id == session) {
+                conversation.entries.push(entry.clone());
+            }
+        }
+        self.push(user, entry);
+    }
+
+    fn push(&self, user: &str, event: Value) {
+        if let Some(mut sockets) = self.sockets.get_mut(user) {
+            sockets.retain(|socket| socket.send(event.clone()).is_ok());
+        }
+    }
+
+    fn session_list(&self, user: &str) -> Value {
+        let sessions = self
+            .sessions
+            .get(user)
+            .map(|conversations| {
+                conversations
+                    .iter()
+                    .map(|c| json!({ "id": c.id, "title": c.title }))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        json!({ "type": "sessions", "sessions": sessions })
+    }
+
+    /// Start a conversation for `user` and tell every tab about it.
+    fn new_session(&self, user: &str, title: &str) -> String {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        self.sessions
+            .entry(user.to_string())
+            .or_default()
+            .push(Conversation {
+                id: id.clone(),
+                title: title.chars().take(MAX_TITLE_CHARS).collect(),
+                entries: Vec::new(),
+            });
+        self.push(user, self.session_list(user));
+        id
+    }
+
+    fn has_session(&self, user: &str, session: &str) -> bool {
+        self.sessions
+            .get(user)
+            .is_some_and(|conversations| conversations.iter().any(|c| c.id == session))
+    }
+
+    fn user_for(&self, headers: &HeaderMap) -> Option<String> {
+        let token = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        self.login_user(token.trim())
+    }
+
+    /// The user a login token belongs to, while it has not expired.
+    fn login_user(&self, token: &str) -> Option<String> {
+        self.logins
+            .get(token)
+            .filter(|login| login.1 > Instant::now())
+            .map(|login| login.0.clone())
+    }
+
+    /// Whether `password` is `user`'s configured password. Both sides are
+    /// hashed first, so the comparison takes the same time for any input.
+    fn check_password(&self, user: &str, password: &str) -> bool {
+        let expected = self.users.get(user).filter(|expected| !expected.is_empty());
+        let matches = secrets_match(password, expected.map_or("", String::as_str));
+        expected.is_some() && matches
+    }
+}
+
+async fn login(State(state): State<AppState>, Json(request): Json<LoginRequest>) -> Response {
+    let user = request.user.trim().to_ascii_lowercase();
+    let valid_name = !user.is_empty()
+        && user.len() <= MAX_USER_NAME_LEN
+        && user
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "names use letters, digits, '-', '_' and '.'" })),
+        )
+            .into_response();
+    }
+    if !state.chat.check_password(&user, &request.password) {
+        warn!("web chat login failed for '{user}'");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "wrong name or password" })),
+        )
+            .into_response();
+    }
+    let now = Instant::now();
+    state.chat.logins.retain(|_, login| login.1 > now);
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    state
+        .chat
+        .logins
+        .insert(token.clone(), (user.clone(), now + LOGIN_TTL));
+    info!("web chat login for '{user}'");
+    Json(json!({ "token": token, "user": user })).into_response()
+}
+
+/// Save the request body as a file in the user's upload folder and return
+/// its path, which the browser adds to the next message.
+async fn upload(
+    State(state): State<AppState>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(user) = state.chat.user_for(&headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    // Keep only the file name, in characters that are safe in any path.
+    let name: String = query
+        .name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "missing file name" })),
+        )
+            .into_response();
+    }
+    let dir = state.chat.uploads_dir.join(&user);
+    let path = dir.join(format!(
+        "{}-{name}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    let saved = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, &body).await
+    };
+    if let Err(err) = saved.await {
+        warn!("web chat upload to {} failed: {err}", path.display());
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "could not save the file" })),
+        )
+            .into_response();
+    }
+    info!(
+        "web chat upload from '{user}': {} ({} bytes)",
+        path.display(),
+        body.len()
+    );
+    Json(json!({ "path": path.display().to_string() })).into_response()
+}
+
+async fn connect(
+    State(state): State<AppState>,
+    Query(query): Query<TokenQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(user) = state.chat.login_user(&query.token) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    ws.on_upgrade(move |socket| serve_socket(state, user, socket))
+}
+
+async fn serve_socket(state: AppState, user: String, mut socket: WebSocket) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let _ = tx.send(state.chat.session_list(&user));
+    state
+        .chat
+        .sockets
+        .entry(user.clone())
+        .or_default()
+        .push(tx.clone());
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+                if socket.send(WsMessage::Text(event.to_string().into())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(message) => handle_client_message(&state, &user, &tx, message).await,
+                    Err(err) => {
+                        let _ = tx.send(json!({ "type": "error", "error": err.to_string() }));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(mut sockets) = state.chat.sockets.get_mut(&user) {
+        sockets.retain(|socket| !socket.same_channel(&tx));
+    }
+}
+
+async fn handle_client_message(
+    state: &AppState,
+    user: &str,
+    tx: &mpsc::UnboundedSender<Value>,
+    message: ClientMessage,
+) {
+    match message {
+        ClientMessage::New => {
+            let session = state.chat.new_session(user, "New chat");
+            let _ = tx.send(json!({ "type": "history", "session": session, "entries": [] }));
+        }
+        ClientMessage::Open { session } => {
+            let entries = state
+                .chat
+                .sessions
+                .get(user)
+                .and_then(|conversations| {
+                    conversations
+                        .iter()
+                        .find(|c| c.id == session)
+                        .map(|c| c.entries.clone())
+                })
+                .unwrap_or_default();
+            let _ = tx.send(json!({ "type": "history", "session": session, "entries": entries }));
+        }
+        ClientMessage::Send { session, text } => {
+            let text = text.trim().to_string();
+            if text.is_empty() {
+                return;
+            }
+            let session = if state.chat.has_session(user, &session) {
+                session
+            } else {
+                state.chat.new_session(user, &text)
+            };
+            state.chat.record(
+                user,
+                &session,
+                json!({ "type": "message", "session": session, "role": "user", "text": text }),
+            );
+            state
+                .bus
+                .publish_inbound(InboundMessage {
+                    channel: "webchat".to_string(),
+                    chat_id: format!("{user}/{session}"),
+                    sender_id: user.to_string(),
+                    sender_name: Some(user.to_string()),
+                    content: text,
+                    message_id: Some(uuid::Uuid::new_v4().to_string()),
+                    session_commands: true,
+                    ..Default::default()
+                })
+                .await;
+        }
+    }
+}
+
+/// Show the agent's tool calls in the conversation that triggered them.
+async fn forward_tool_events(state: Arc<ChatState>, mut events: broadcast::Receiver<AgentEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("web chat skipped {skipped} agent events");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if event.channel != "webchat" {
+            continue;
+        }
+        let Some((user, session)) = event.chat_id.split_once('/') else {
+            continue;
+        };
+        let (phase, name, detail) = match event.kind {
+            AgentEventKind::ToolCall { name, args } => ("call", name, args),
+            AgentEventKind::ToolResult { name, result } => ("result", name, result),
+            AgentEventKind::TurnCompleted { .. } => continue,
+        };
+        state.record(
+            user,
+            session,
+            json!({
+                "type": "tool",
+                "session": session,
+                "phase": phase,
+                "name": name,
+                "detail": detail,
+            }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message as ClientWsMessage;
+
+    async fn next_json<S>(socket: &mut S) -> Value
+    where
+        S: futures_util::Stream<
+                Item = Result<ClientWsMessage, tokio_tungstenite::tungstenite::Error>,
+            > + Unpin,
+    {
+        loop {
+            if let ClientWsMessage::Text(text) = socket.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn chats_over_websocket_with_tool_events_and_uploads() {
+        let workspace = tempfile::tempdir().unwrap();
+        let mut cfg = AppConfig::defaults();
+        cfg.workspace_dir = workspace.path().to_path_buf();
+        cfg.channels
+            .webchat
+            .users
+            .insert("ana".to_string(), "pw".to_string());
+        cfg.channels
+            .webchat
+            .users
+            .insert("ben".to_string(), "other".to_string());
+        let channel = Arc::new(WebChatChannel::new(&cfg));
+        let bus = MessageBus::new();
+        tokio::spawn(forward_tool_events(
+            channel.state.clone(),
+            bus.subscribe_events(),
+        ));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = channel.router(bus.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let page = client.get(format!("http://{addr}/")).send().await.unwrap();
+        assert!(page
+            .text()
+            .await
+            .unwrap()
+            .contains("<title>lightclaw</title>"));
+        let rejected = client
+            .post(format!("http://{addr}/api/login"))
+            .json(&json!({ "user": "ana", "password": "nope" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), 401);
+        // Another user's password does not open ana's account.
+        let rejected = client
+            .post(format!("http://{addr}/api/login"))
+            .json(&json!({ "user": "ana", "password": "other" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), 401);
+        let login: Value = client
+            .post(format!("http://{addr}/api/login"))
+            .json(&json!({ "user": "Ana", "password": "pw" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let token = login["token"].as_str().unwrap();
+
+        let uploaded: Value = client
+            .post(format!("http://{addr}/api/upload?name=../notes.txt"))
+            .bearer_auth(token)
+            .body("remember the milk")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let path = PathBuf::from(uploaded["path"].as_str().unwrap());
+        assert!(path.starts_with(workspace.path().join("uploads/webchat/ana")));
+        assert!(path.to_string_lossy().ends_with("-notes.txt"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "remember the milk");
+
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/ws?token={token}"))
+                .await
+                .unwrap();
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "sessions", "sessions": [] })
+        );
+
+        socket
+            .send(ClientWsMessage::Text(
+                json!({ "type": "send", "text": "what's in my notes?" }).to_string(),
+            ))
+            .await
+            .unwrap();
+        let inbound = bus.consume_inbound().await.unwrap();
+        assert_eq!(inbound.channel, "webchat");
+        assert_eq!(inbound.sender_id, "ana");
+        let (user, session) = inbound.chat_id.split_once('/').unwrap();
+        assert_eq!(user, "ana");
+
+        bus.publish_event(AgentEvent {
+            channel: "webchat".to_string(),
+            chat_id: inbound.chat_id.clone(),
+            kind: AgentEventKind::ToolCall {
+                name: "read_file".to_string(),
+                args: "{\"path\":\"notes.txt\"}".to_string(),
+            },
+        });
+        // Tool events are forwarded asynchronously; let them land before the
+        // reply so the transcript order is deterministic.
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        channel
+            .send(OutboundMessage {
+                channel: "webchat".to_string(),
+                chat_id: inbound.chat_id.clone(),
+                content: "Milk.".to_string(),
+                attachments: Vec::new(),
+                reply_to: inbound.message_id.clone(),
+            })
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        while received.len() < 4 {
+            received.push(next_json(&mut socket).await);
+        }
+        assert_eq!(received[0]["sessions"][0]["title"], "what's in my notes?");
+        assert_eq!(received[1]["role"], "user");
+        assert_eq!(
+            (received[2]["type"].as_str(), received[2]["name"].as_str()),
+            (Some("tool"), Some("read_file"))
+        );
+        assert_eq!(received[3]["text"], "Milk.");
+        assert_eq!(received[3]["done"], true);
+
+        socket
+            .send(ClientWsMessage::Text(
+                json!({ "type": "open", "session": session }).to_string(),
+            ))
+            .await
+            .unwrap();
+        let history = next_json(&mut socket).await;
+        assert_eq!(history["type"], "history");
+        assert_eq!(history["entries"].as_array().unwrap().len(), 3);
+
+        // Expired tokens are refused.
+        channel.state.logins.get_mut(token).unwrap().1 = Instant::now();
+        let expired = client
+            .post(format!("http://{addr}/api/upload?name=late.txt"))
+            .bearer_auth(token)
+            .body("too late")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(expired.status(), 401);
+    }
+}
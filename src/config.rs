@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use etcetera::{choose_base_strategy, BaseStrategy};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -11,6 +11,8 @@ pub enum ProviderKind {
     OpenRouter,
     OpenAI,
     Ollama,
+    /// Scripted responses from `providers.mock.fixture`, for offline tests.
+    Mock,
 }
 
 impl ProviderKind {
@@ -19,6 +21,7 @@ impl ProviderKind {
             "openrouter" => Some(Self::OpenRouter),
             "openai" => Some(Self::OpenAI),
             "ollama" => Some(Self::Ollama),
+            "mock" => Some(Self::Mock),
             _ => None,
         }
     }
@@ -28,6 +31,7 @@ impl ProviderKind {
             Self::OpenRouter => "openrouter",
             Self::OpenAI => "openai",
             Self::Ollama => "ollama",
+            Self::Mock => "mock",
         }
     }
 }
@@ -61,6 +65,12 @@ pub struct MistralEntry {
     pub base_url: String,
 }
 
+/// Mock provider entry (path to the YAML or JSON script it replays).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MockEntry {
+    pub fixture: String,
+}
+
 /// All provider credentials.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProvidersConfig {
@@ -68,6 +78,7 @@ pub struct ProvidersConfig {
     pub openai: ProviderEntry,
     pub ollama: ProviderEntry,
     pub mistral: MistralEntry,
+    pub mock: MockEntry,
 }
 
 /// Model selection & agent configuration.
@@ -78,11 +89,64 @@ pub struct ModelConfig {
     pub max_tool_turns: usize,
 }
 
+/// How the bot behaves in a group chat.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupMode {
+    /// Ignore the group entirely.
+    Off,
+    /// Respond only to @mentions, replies to the bot and commands.
+    Mention,
+    /// Record every message as context, but only answer when addressed.
+    Listen,
+    /// Respond to every message.
+    All,
+}
+
+impl GroupMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "off" | "ignore" | "disabled" => Some(Self::Off),
+            "mention" | "mentions" | "addressed" => Some(Self::Mention),
+            "listen" | "observe" => Some(Self::Listen),
+            "all" | "always" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
 /// Telegram channel settings.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TelegramConfig {
     pub bot_token: String,
     pub allow_from: Vec<String>,
+    /// Default behaviour in groups and supergroups.
+    pub group_mode: GroupMode,
+    /// Per-group overrides keyed by chat id.
+    pub groups: HashMap<String, GroupMode>,
+    /// Replies longer than this many characters are sent as a `.md`
+    /// document instead of split messages (0 = never).
+    pub long_reply_document_chars: usize,
+    pub webhook: TelegramWebhookConfig,
+}
+
+/// Telegram webhook settings. Long polling is used unless `url` is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TelegramWebhookConfig {
+    /// Public HTTPS URL Telegram posts updates to.
+    pub url: String,
+    /// Local socket address the webhook server binds to.
+    pub listen: String,
+    /// Route served locally; defaults to the path of `url`.
+    pub path: String,
+    /// Value Telegram sends in `X-Telegram-Bot-Api-Secret-Token`.
+    pub secret_token: String,
+}
+
+impl TelegramWebhookConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.url.trim().is_empty()
+    }
 }
 
 /// Discord channel settings.
@@ -91,6 +155,120 @@ pub struct DiscordConfig {
     pub bot_token: String,
     pub allow_from: Vec<String>,
     pub allowed_channels: Vec<String>,
+    /// Start a thread for each new conversation in a guild channel, so every
+    /// thread gets its own session and history.
+    pub threads: bool,
+}
+
+/// Slack channel settings (Socket Mode).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SlackConfig {
+    /// App-level token (`xapp-...`) with `connections:write`, used to open
+    /// the Socket Mode connection.
+    pub app_token: String,
+    /// Bot token (`xoxb-...`) used for the Web API.
+    pub bot_token: String,
+    pub allow_from: Vec<String>,
+    pub allowed_channels: Vec<String>,
+}
+
+/// Matrix channel settings. Authenticates with `access_token` when set,
+/// otherwise logs in with `user_id` and `password`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatrixConfig {
+    /// Homeserver base URL, e.g. `https://matrix.example.org`.
+    pub homeserver: String,
+    pub user_id: String,
+    pub password: String,
+    pub access_token: String,
+    pub allow_from: Vec<String>,
+    /// Room ids the bot answers in; DMs are always allowed.
+    pub allowed_rooms: Vec<String>,
+}
+
+/// How the SMTP connection is secured.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Implicit TLS, usually port 465.
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587.
+    StartTls,
+    /// Unencrypted; only for local relays.
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "tls" | "ssl" | "implicit" => Some(Self::Tls),
+            "starttls" => Some(Self::StartTls),
+            "none" | "plain" | "off" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Email channel settings: IMAP for inbound mail, SMTP for replies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub imap_host: String,
+    pub imap_port: u16,
+    /// Connect to IMAP over TLS (port 993); plain TCP otherwise.
+    pub imap_tls: bool,
+    pub mailbox: String,
+    pub poll_interval_secs: u64,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    /// Login for both IMAP and SMTP.
+    pub username: String,
+    pub password: String,
+    /// Address replies are sent from; defaults to `username`.
+    pub from_address: String,
+    /// Sender addresses or `@domain` entries.
+    pub allow_from: Vec<String>,
+    /// The authserv-id your mail server stamps on its `Authentication-Results`
+    /// headers (usually its host name). Headers with any other id were added
+    /// elsewhere, possibly by the sender, and are ignored. Required unless
+    /// `trust_unauthenticated` is set.
+    pub authserv_id: String,
+    /// Accept allowlisted senders without a passing DKIM or DMARC result in
+    /// `Authentication-Results`. `From:` is trivially forged, so only enable
+    /// this when the mail server already rejects spoofed mail.
+    pub trust_unauthenticated: bool,
+}
+
+impl EmailConfig {
+    pub fn sender_address(&self) -> &str {
+        if self.from_address.trim().is_empty() {
+            self.username.trim()
+        } else {
+            self.from_address.trim()
+        }
+    }
+}
+
+/// OpenAI-compatible HTTP API settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Listen address, e.g. `127.0.0.1:8787`. Empty disables the server.
+    pub bind: String,
+    /// Bearer tokens accepted on `Authorization`.
+    pub api_keys: Vec<String>,
+    /// How long a request waits for the agent's reply.
+    pub timeout_secs: u64,
+}
+
+/// Embedded web chat settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebChatConfig {
+    /// Listen address, e.g. `127.0.0.1:8790`. Empty disables the web UI.
+    pub bind: String,
+    /// Login passwords by user name. Each user only sees their own
+    /// conversations and uploads.
+    pub users: BTreeMap<String, String>,
+    pub max_upload_bytes: u64,
 }
 
 /// All channel settings.
@@ -98,6 +276,11 @@ pub struct DiscordConfig {
 pub struct ChannelsConfig {
     pub telegram: TelegramConfig,
     pub discord: DiscordConfig,
+    pub slack: SlackConfig,
+    pub matrix: MatrixConfig,
+    pub email: EmailConfig,
+    pub http: HttpConfig,
+    pub webchat: WebChatConfig,
 }
 
 /// Transcription (speech-to-text) settings.
@@ -111,6 +294,68 @@ pub struct TranscriptionConfig {
     pub mistral_diarize: bool,
     pub mistral_context_bias: Option<String>,
     pub mistral_timestamp_granularities: Vec<String>,
+    /// Local backend command line (whisper.cpp, faster-whisper, ...). Supports
+    /// `{input}`, `{output}`, `{model}` and `{language}` placeholders.
+    pub command: String,
+    pub ffmpeg_path: String,
+    /// Audio larger than `max_bytes` is split into chunks of this length
+    /// (0 disables chunking and such audio is rejected).
+    pub chunk_seconds: u64,
+    pub chunk_overlap_seconds: u64,
+    /// Hard upper bound for audio accepted for chunked transcription.
+    pub max_input_bytes: usize,
+    /// Echo the transcript back to the chat before the answer.
+    pub echo: bool,
+    /// Only echo the first N lines of the transcript (0 = all).
+    pub echo_max_lines: usize,
+}
+
+/// When to answer with a synthesized voice message instead of plain text.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceReplyMode {
+    /// Always reply with text.
+    Off,
+    /// Reply with voice when the user's last message was a voice/audio note.
+    Auto,
+    /// Reply with voice to every message.
+    Always,
+}
+
+impl VoiceReplyMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" | "text" => Some(Self::Off),
+            "auto" | "inbound" | "mirror" => Some(Self::Auto),
+            "always" | "on" | "voice" => Some(Self::Always),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Auto => "auto",
+            Self::Always => "always",
+        }
+    }
+}
+
+/// Text-to-speech settings for voice replies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TtsConfig {
+    pub enabled: bool,
+    /// "openai" (audio/speech API) or "local" (command such as piper).
+    pub provider: String,
+    pub model: String,
+    pub voice: String,
+    /// Local backend command line; text is written to stdin and `{output}`
+    /// is replaced with the path of the audio file to produce.
+    pub command: String,
+    pub reply_mode: VoiceReplyMode,
+    /// Replies longer than this are sent as text only.
+    pub max_chars: usize,
+    pub ffmpeg_path: String,
 }
 
 /// Memory mode: none, simple (file-based), or smart (vector + file).
@@ -136,6 +381,14 @@ impl MemoryMode {
             _ => Option::None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Simple => "simple",
+            Self::Smart => "smart",
+        }
+    }
 }
 
 /// Memory (vector store for Smart mode) settings.
@@ -147,6 +400,43 @@ pub struct MemoryConfig {
     pub max_memories: usize,
 }
 
+/// Durable outbound delivery settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeliveryConfig {
+    /// Attempts before a reply is moved to the dead-letter list.
+    pub max_attempts: u32,
+    /// First retry delay; doubles on every further failure.
+    pub backoff_base_secs: u64,
+    pub backoff_max_secs: u64,
+}
+
+/// Inbound webhook listener settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhooksConfig {
+    /// Listen address, e.g. `0.0.0.0:8788`. Empty disables the listener.
+    pub bind: String,
+    /// How far a signed timestamp may be from now before it is rejected.
+    pub max_skew_secs: u64,
+    /// Endpoints keyed by name, served at `/hooks/<name>`.
+    pub endpoints: BTreeMap<String, WebhookEndpoint>,
+}
+
+/// One named webhook endpoint.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    /// HMAC-SHA256 key the sender signs requests with.
+    pub secret: String,
+    /// Prompt with `{{path.to.field}}` placeholders filled from the JSON
+    /// payload; `{{payload}}` is the whole payload.
+    pub template: String,
+    /// Where the agent's answer is delivered, e.g. `telegram` and a chat id.
+    pub channel: String,
+    pub chat_id: String,
+    /// Tools the triggered turn may call. Payloads come from outside, so the
+    /// default is none; list only tools a crafted payload cannot misuse.
+    pub tools: Vec<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum WebSearchProvider {
@@ -181,7 +471,8 @@ impl WebFetchProvider {
     }
 }
 
-/// Tool-related settings (exec timeout, workspace restriction, web search).
+/// Tool-related settings (exec timeout, workspace restriction, web search,
+/// which tools are enabled, and limits applied to every tool call).
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ToolsConfig {
     pub exec_timeout_secs: u64,
@@ -190,6 +481,16 @@ pub struct ToolsConfig {
     pub web_fetch_provider: WebFetchProvider,
     pub brave_api_key: Option<String>,
     pub firecrawl_api_key: Option<String>,
+    /// Tool names that are never registered.
+    pub disabled: Vec<String>,
+    /// Named tool subsets, e.g. `readonly: [read_file, list_dir]`.
+    pub profiles: BTreeMap<String, Vec<String>>,
+    /// Active profile; empty means every tool that is not disabled.
+    pub profile: String,
+    /// Upper bound for a single tool call, in seconds.
+    pub timeout_secs: u64,
+    /// Tool output longer than this is truncated before the model sees it.
+    pub max_output_chars: usize,
 }
 
 // ---------------------------------------------------------------------------
@@ -203,12 +504,21 @@ pub struct AppConfig {
     pub model: ModelConfig,
     pub channels: ChannelsConfig,
     pub transcription: TranscriptionConfig,
+    pub tts: TtsConfig,
     pub memory: MemoryConfig,
     pub tools: ToolsConfig,
+    pub delivery: DeliveryConfig,
+    pub webhooks: WebhooksConfig,
     pub data_dir: PathBuf,
     pub workspace_dir: PathBuf,
 }
 
+impl TelegramConfig {
+    pub fn group_mode_for(&self, chat_id: &str) -> &GroupMode {
+        self.groups.get(chat_id).unwrap_or(&self.group_mode)
+    }
+}
+
 impl AppConfig {
     pub fn load() -> Result<Self> {
         let cfg = Self::load_relaxed();
@@ -235,7 +545,7 @@ impl AppConfig {
         cfg
     }
 
-    fn defaults() -> Self {
+    pub(crate) fn defaults() -> Self {
         Self {
             provider: ProviderKind::OpenRouter,
             providers: ProvidersConfig {
@@ -260,6 +570,9 @@ impl AppConfig {
                     api_key: String::new(),
                     base_url: "https://api.mistral.ai/v1".to_string(),
                 },
+                mock: MockEntry {
+                    fixture: String::new(),
+                },
             },
             model: ModelConfig {
                 model: "anthropic/claude-opus-4-5".to_string(),
@@ -270,11 +583,61 @@ impl AppConfig {
                 telegram: TelegramConfig {
                     bot_token: String::new(),
                     allow_from: Vec::new(),
+                    group_mode: GroupMode::Mention,
+                    groups: HashMap::new(),
+                    long_reply_document_chars: 0,
+                    webhook: TelegramWebhookConfig {
+                        url: String::new(),
+                        listen: "0.0.0.0:8443".to_string(),
+                        path: String::new(),
+                        secret_token: String::new(),
+                    },
                 },
                 discord: DiscordConfig {
                     bot_token: String::new(),
                     allow_from: Vec::new(),
                     allowed_channels: Vec::new(),
+                    threads: false,
+                },
+                slack: SlackConfig {
+                    app_token: String::new(),
+                    bot_token: String::new(),
+                    allow_from: Vec::new(),
+                    allowed_channels: Vec::new(),
+                },
+                matrix: MatrixConfig {
+                    homeserver: String::new(),
+                    user_id: String::new(),
+                    password: String::new(),
+                    access_token: String::new(),
+                    allow_from: Vec::new(),
+                    allowed_rooms: Vec::new(),
+                },
+                email: EmailConfig {
+                    imap_host: String::new(),
+                    imap_port: 993,
+                    imap_tls: true,
+                    mailbox: "INBOX".to_string(),
+                    poll_interval_secs: 60,
+                    smtp_host: String::new(),
+                    smtp_port: 587,
+                    smtp_security: SmtpSecurity::StartTls,
+                    username: String::new(),
+                    password: String::new(),
+                    from_address: String::new(),
+                    allow_from: Vec::new(),
+                    authserv_id: String::new(),
+                    trust_unauthenticated: false,
+                },
+                http: HttpConfig {
+                    bind: String::new(),
+                    api_keys: Vec::new(),
+                    timeout_secs: 300,
+                },
+                webchat: WebChatConfig {
+                    bind: String::new(),
+                    users: BTreeMap::new(),
+                    max_upload_bytes: 20 * 1024 * 1024,
                 },
             },
             transcription: TranscriptionConfig {
@@ -286,6 +649,23 @@ impl AppConfig {
                 mistral_diarize: false,
                 mistral_context_bias: None,
                 mistral_timestamp_granularities: Vec::new(),
+                command: String::new(),
+                ffmpeg_path: "ffmpeg".to_string(),
+                chunk_seconds: 300,
+                chunk_overlap_seconds: 2,
+                max_input_bytes: 200 * 1024 * 1024,
+                echo: false,
+                echo_max_lines: 3,
+            },
+            tts: TtsConfig {
+                enabled: false,
+                provider: "openai".to_string(),
+                model: "tts-1".to_string(),
+                voice: "alloy".to_string(),
+                command: String::new(),
+                reply_mode: VoiceReplyMode::Auto,
+                max_chars: 1500,
+                ffmpeg_path: "ffmpeg".to_string(),
             },
             memory: MemoryConfig {
                 mode: MemoryMode::Simple,
@@ -299,6 +679,21 @@ impl AppConfig {
                 web_fetch_provider: WebFetchProvider::Native,
                 brave_api_key: None,
                 firecrawl_api_key: None,
+                disabled: Vec::new(),
+                profiles: BTreeMap::new(),
+                profile: String::new(),
+                timeout_secs: 300,
+                max_output_chars: 30_000,
+            },
+            delivery: DeliveryConfig {
+                max_attempts: 8,
+                backoff_base_secs: 2,
+                backoff_max_secs: 600,
+            },
+            webhooks: WebhooksConfig {
+                bind: String::new(),
+                max_skew_secs: 300,
+                endpoints: BTreeMap::new(),
             },
             data_dir: default_data_dir(),
             workspace_dir: default_workspace_dir(),
@@ -310,13 +705,14 @@ impl AppConfig {
             ProviderKind::OpenRouter => &self.providers.openrouter.api_key,
             ProviderKind::OpenAI => &self.providers.openai.api_key,
             ProviderKind::Ollama => &self.providers.ollama.api_key,
+            ProviderKind::Mock => "",
         }
     }
 
     pub fn provider_requires_api_key(&self) -> bool {
         match self.provider {
             ProviderKind::OpenRouter | ProviderKind::OpenAI => true,
-            ProviderKind::Ollama => false,
+            ProviderKind::Ollama | ProviderKind::Mock => false,
         }
     }
 
@@ -328,6 +724,53 @@ impl AppConfig {
         !self.channels.discord.bot_token.trim().is_empty()
     }
 
+    pub fn slack_enabled(&self) -> bool {
+        !self.channels.slack.app_token.trim().is_empty()
+            && !self.channels.slack.bot_token.trim().is_empty()
+    }
+
+    pub fn email_enabled(&self) -> bool {
+        let email = &self.channels.email;
+        !email.imap_host.trim().is_empty()
+            && !email.smtp_host.trim().is_empty()
+            && !email.username.trim().is_empty()
+    }
+
+    /// The HTTP API never starts without at least one API key.
+    pub fn http_enabled(&self) -> bool {
+        let http = &self.channels.http;
+        !http.bind.trim().is_empty() && http.api_keys.iter().any(|key| !key.trim().is_empty())
+    }
+
+    /// The web UI never starts without a password.
+    pub fn webchat_enabled(&self) -> bool {
+        let webchat = &self.channels.webchat;
+        !webchat.bind.trim().is_empty()
+            && webchat.users.values().any(|password| !password.is_empty())
+    }
+
+    pub fn webhooks_enabled(&self) -> bool {
+        !self.webhooks.bind.trim().is_empty() && !self.webhooks.endpoints.is_empty()
+    }
+
+    pub fn matrix_enabled(&self) -> bool {
+        let matrix = &self.channels.matrix;
+        !matrix.homeserver.trim().is_empty()
+            && (!matrix.access_token.trim().is_empty()
+                || (!matrix.user_id.trim().is_empty() && !matrix.password.is_empty()))
+    }
+
+    /// Use only `raw` (`model` or `provider/model`), without fallbacks.
+    pub fn pin_model(&mut self, raw: &str) -> bool {
+        let Some(route) = parse_model_route(raw, &self.provider) else {
+            return false;
+        };
+        self.provider = route.provider;
+        self.model.model = route.model;
+        self.model.fallbacks.clear();
+        true
+    }
+
     pub fn model_routes(&self) -> Vec<ModelRoute> {
         let mut routes = Vec::new();
         let mut seen = HashSet::new();
@@ -455,6 +898,9 @@ fn apply_lightclaw_config(cfg: &mut AppConfig, value: &Value) {
             cfg.providers.mistral.base_url = v.to_string();
         }
     }
+    if let Some(fixture) = get_str(value, &["providers", "mock", "fixture"]) {
+        cfg.providers.mock.fixture = fixture.to_string();
+    }
 
     if let Some(model) = get_str(value, &["agents", "defaults", "model"]) {
         cfg.model.model = model.to_string();
@@ -504,12 +950,70 @@ fn apply_lightclaw_config(cfg: &mut AppConfig, value: &Value) {
     {
         cfg.tools.firecrawl_api_key = Some(firecrawl.to_string());
     }
+    if let Some(disabled) = get_array(value, &["tools", "disabled"]) {
+        cfg.tools.disabled = disabled;
+    }
+    if let Some(profiles) = value
+        .get("tools")
+        .and_then(|v| v.get("profiles"))
+        .and_then(Value::as_object)
+    {
+        for (name, tools) in profiles {
+            if let Some(tools) = get_array(tools, &[]) {
+                cfg.tools.profiles.insert(name.clone(), tools);
+            }
+        }
+    }
+    if let Some(profile) = get_str(value, &["tools", "profile"]) {
+        cfg.tools.profile = profile.trim().to_string();
+    }
+    if let Some(secs) = get_u64(value, &["tools", "timeout_secs"]) {
+        cfg.tools.timeout_secs = secs.max(1);
+    }
+    if let Some(chars) = get_u64(value, &["tools", "max_output_chars"]) {
+        cfg.tools.max_output_chars = chars as usize;
+    }
     if let Some(token) = get_str(value, &["channels", "telegram", "token"]) {
         cfg.channels.telegram.bot_token = token.to_string();
     }
     if let Some(list) = get_array(value, &["channels", "telegram", "allow_from"]) {
         cfg.channels.telegram.allow_from = list;
     }
+    if let Some(mode) = get_str(value, &["channels", "telegram", "group_mode"]) {
+        if let Some(parsed) = GroupMode::parse(mode) {
+            cfg.channels.telegram.group_mode = parsed;
+        }
+    }
+    if let Some(groups) = value
+        .get("channels")
+        .and_then(|v| v.get("telegram"))
+        .and_then(|v| v.get("groups"))
+        .and_then(Value::as_object)
+    {
+        for (chat_id, mode) in groups {
+            if let Some(parsed) = mode.as_str().and_then(GroupMode::parse) {
+                cfg.channels.telegram.groups.insert(chat_id.clone(), parsed);
+            }
+        }
+    }
+    if let Some(chars) = get_u64(
+        value,
+        &["channels", "telegram", "long_reply_document_chars"],
+    ) {
+        cfg.channels.telegram.long_reply_document_chars = chars as usize;
+    }
+    if let Some(url) = get_str(value, &["channels", "telegram", "webhook", "url"]) {
+        cfg.channels.telegram.webhook.url = url.to_string();
+    }
+    if let Some(listen) = get_str(value, &["channels", "telegram", "webhook", "listen"]) {
+        cfg.channels.telegram.webhook.listen = listen.to_string();
+    }
+    if let Some(path) = get_str(value, &["channels", "telegram", "webhook", "path"]) {
+        cfg.channels.telegram.webhook.path = path.to_string();
+    }
+    if let Some(secret) = get_str(value, &["channels", "telegram", "webhook", "secret_token"]) {
+        cfg.channels.telegram.webhook.secret_token = secret.to_string();
+    }
     if let Some(token) = get_str(value, &["channels", "discord", "token"]) {
         cfg.channels.discord.bot_token = token.to_string();
     }
@@ -519,6 +1023,113 @@ fn apply_lightclaw_config(cfg: &mut AppConfig, value: &Value) {
     if let Some(list) = get_array(value, &["channels", "discord", "allowed_channels"]) {
         cfg.channels.discord.allowed_channels = list;
     }
+    if let Some(threads) = get_bool(value, &["channels", "discord", "threads"]) {
+        cfg.channels.discord.threads = threads;
+    }
+    if let Some(token) = get_str(value, &["channels", "slack", "app_token"]) {
+        cfg.channels.slack.app_token = token.to_string();
+    }
+    if let Some(token) = get_str(value, &["channels", "slack", "bot_token"]) {
+        cfg.channels.slack.bot_token = token.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "slack", "allow_from"]) {
+        cfg.channels.slack.allow_from = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "slack", "allowed_channels"]) {
+        cfg.channels.slack.allowed_channels = list;
+    }
+    if let Some(homeserver) = get_str(value, &["channels", "matrix", "homeserver"]) {
+        cfg.channels.matrix.homeserver = homeserver.to_string();
+    }
+    if let Some(user_id) = get_str(value, &["channels", "matrix", "user_id"]) {
+        cfg.channels.matrix.user_id = user_id.to_string();
+    }
+    if let Some(password) = get_str(value, &["channels", "matrix", "password"]) {
+        cfg.channels.matrix.password = password.to_string();
+    }
+    if let Some(token) = get_str(value, &["channels", "matrix", "access_token"]) {
+        cfg.channels.matrix.access_token = token.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "matrix", "allow_from"]) {
+        cfg.channels.matrix.allow_from = list;
+    }
+    if let Some(list) = get_array(value, &["channels", "matrix", "allowed_rooms"]) {
+        cfg.channels.matrix.allowed_rooms = list;
+    }
+    if let Some(host) = get_str(value, &["channels", "email", "imap_host"]) {
+        cfg.channels.email.imap_host = host.to_string();
+    }
+    if let Some(port) = get_u64(value, &["channels", "email", "imap_port"]) {
+        cfg.channels.email.imap_port = port as u16;
+    }
+    if let Some(tls) = get_bool(value, &["channels", "email", "imap_tls"]) {
+        cfg.channels.email.imap_tls = tls;
+    }
+    if let Some(mailbox) = get_str(value, &["channels", "email", "mailbox"]) {
+        cfg.channels.email.mailbox = mailbox.to_string();
+    }
+    if let Some(secs) = get_u64(value, &["channels", "email", "poll_interval_secs"]) {
+        cfg.channels.email.poll_interval_secs = secs.max(1);
+    }
+    if let Some(host) = get_str(value, &["channels", "email", "smtp_host"]) {
+        cfg.channels.email.smtp_host = host.to_string();
+    }
+    if let Some(port) = get_u64(value, &["channels", "email", "smtp_port"]) {
+        cfg.channels.email.smtp_port = port as u16;
+    }
+    if let Some(security) = get_str(value, &["channels", "email", "smtp_security"]) {
+        if let Some(parsed) = SmtpSecurity::parse(security) {
+            cfg.channels.email.smtp_security = parsed;
+        }
+    }
+    if let Some(username) = get_str(value, &["channels", "email", "username"]) {
+        cfg.channels.email.username = username.to_string();
+    }
+    if let Some(password) = get_str(value, &["channels", "email", "password"]) {
+        cfg.channels.email.password = password.to_string();
+    }
+    if let Some(from) = get_str(value, &["channels", "email", "from_address"]) {
+        cfg.channels.email.from_address = from.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "email", "allow_from"]) {
+        cfg.channels.email.allow_from = list;
+    }
+    if let Some(id) = get_str(value, &["channels", "email", "authserv_id"]) {
+        cfg.channels.email.authserv_id = id.to_string();
+    }
+    if let Some(trust) = get_bool(value, &["channels", "email", "trust_unauthenticated"]) {
+        cfg.channels.email.trust_unauthenticated = trust;
+    }
+    if let Some(bind) = get_str(value, &["channels", "http", "bind"]) {
+        cfg.channels.http.bind = bind.to_string();
+    }
+    if let Some(list) = get_array(value, &["channels", "http", "api_keys"]) {
+        cfg.channels.http.api_keys = list;
+    }
+    if let Some(secs) = get_u64(value, &["channels", "http", "timeout_secs"]) {
+        cfg.channels.http.timeout_secs = secs.max(1);
+    }
+    if let Some(bind) = get_str(value, &["channels", "webchat", "bind"]) {
+        cfg.channels.webchat.bind = bind.to_string();
+    }
+    if let Some(users) = value
+        .get("channels")
+        .and_then(|v| v.get("webchat"))
+        .and_then(|v| v.get("users"))
+        .and_then(Value::as_object)
+    {
+        for (name, password) in users {
+            if let Some(password) = password.as_str() {
+                cfg.channels
+                    .webchat
+                    .users
+                    .insert(name.trim().to_ascii_lowercase(), password.to_string());
+            }
+        }
+    }
+    if let Some(bytes) = get_u64(value, &["channels", "webchat", "max_upload_bytes"]) {
+        cfg.channels.webchat.max_upload_bytes = bytes;
+    }
     if let Some(enabled) = get_bool(value, &["channels", "telegram", "transcription", "enabled"]) {
         cfg.transcription.enabled = enabled;
     }
@@ -575,6 +1186,80 @@ fn apply_lightclaw_config(cfg: &mut AppConfig, value: &Value) {
     ) {
         cfg.transcription.mistral_timestamp_granularities = grans;
     }
+    if let Some(command) = get_str(value, &["channels", "telegram", "transcription", "command"]) {
+        cfg.transcription.command = command.to_string();
+    }
+    if let Some(ffmpeg) = get_str(value, &["channels", "telegram", "transcription", "ffmpeg"]) {
+        if !ffmpeg.trim().is_empty() {
+            cfg.transcription.ffmpeg_path = ffmpeg.to_string();
+        }
+    }
+    if let Some(secs) = get_u64(
+        value,
+        &["channels", "telegram", "transcription", "chunk_seconds"],
+    ) {
+        cfg.transcription.chunk_seconds = secs;
+    }
+    if let Some(secs) = get_u64(
+        value,
+        &[
+            "channels",
+            "telegram",
+            "transcription",
+            "chunk_overlap_seconds",
+        ],
+    ) {
+        cfg.transcription.chunk_overlap_seconds = secs;
+    }
+    if let Some(max) = get_u64(
+        value,
+        &["channels", "telegram", "transcription", "max_input_bytes"],
+    ) {
+        cfg.transcription.max_input_bytes = max as usize;
+    }
+    if let Some(echo) = get_bool(value, &["channels", "telegram", "transcription", "echo"]) {
+        cfg.transcription.echo = echo;
+    }
+    if let Some(lines) = get_u64(
+        value,
+        &["channels", "telegram", "transcription", "echo_max_lines"],
+    ) {
+        cfg.transcription.echo_max_lines = lines as usize;
+    }
+    if let Some(enabled) = get_bool(value, &["channels", "telegram", "tts", "enabled"]) {
+        cfg.tts.enabled = enabled;
+    }
+    if let Some(provider) = get_str(value, &["channels", "telegram", "tts", "provider"]) {
+        if !provider.trim().is_empty() {
+            cfg.tts.provider = provider.to_string();
+        }
+    }
+    if let Some(model) = get_str(value, &["channels", "telegram", "tts", "model"]) {
+        if !model.trim().is_empty() {
+            cfg.tts.model = model.to_string();
+        }
+    }
+    if let Some(voice) = get_str(value, &["channels", "telegram", "tts", "voice"]) {
+        if !voice.trim().is_empty() {
+            cfg.tts.voice = voice.to_string();
+        }
+    }
+    if let Some(command) = get_str(value, &["channels", "telegram", "tts", "command"]) {
+        cfg.tts.command = command.to_string();
+    }
+    if let Some(mode) = get_str(value, &["channels", "telegram", "tts", "reply_mode"]) {
+        if let Some(parsed) = VoiceReplyMode::parse(mode) {
+            cfg.tts.reply_mode = parsed;
+        }
+    }
+    if let Some(max_chars) = get_u64(value, &["channels", "telegram", "tts", "max_chars"]) {
+        cfg.tts.max_chars = max_chars as usize;
+    }
+    if let Some(ffmpeg) = get_str(value, &["channels", "telegram", "tts", "ffmpeg"]) {
+        if !ffmpeg.trim().is_empty() {
+            cfg.tts.ffmpeg_path = ffmpeg.to_string();
+        }
+    }
     if let Some(turns) = get_u64(value, &["agents", "defaults", "max_tool_iterations"]) {
         cfg.model.max_tool_turns = turns as usize;
     }
@@ -603,6 +1288,42 @@ fn apply_lightclaw_config(cfg: &mut AppConfig, value: &Value) {
     if let Some(max) = get_u64(value, &["memory", "max_memories"]) {
         cfg.memory.max_memories = max as usize;
     }
+
+    if let Some(attempts) = get_u64(value, &["delivery", "max_attempts"]) {
+        cfg.delivery.max_attempts = attempts.max(1) as u32;
+    }
+    if let Some(secs) = get_u64(value, &["delivery", "backoff_base_secs"]) {
+        cfg.delivery.backoff_base_secs = secs;
+    }
+    if let Some(secs) = get_u64(value, &["delivery", "backoff_max_secs"]) {
+        cfg.delivery.backoff_max_secs = secs;
+    }
+
+    if let Some(bind) = get_str(value, &["webhooks", "bind"]) {
+        cfg.webhooks.bind = bind.to_string();
+    }
+    if let Some(secs) = get_u64(value, &["webhooks", "max_skew_secs"]) {
+        cfg.webhooks.max_skew_secs = secs.max(1);
+    }
+    if let Some(endpoints) = value
+        .get("webhooks")
+        .and_then(|v| v.get("endpoints"))
+        .and_then(Value::as_object)
+    {
+        for (name, endpoint) in endpoints {
+            let field = |key: &str| get_str(endpoint, &[key]).unwrap_or("").to_string();
+            cfg.webhooks.endpoints.insert(
+                name.clone(),
+                WebhookEndpoint {
+                    secret: field("secret"),
+                    template: field("template"),
+                    channel: field("channel"),
+                    chat_id: field("chat_id"),
+                    tools: get_array(endpoint, &["tools"]).unwrap_or_default(),
+                },
+            );
+        }
+    }
 }
 
 fn apply_provider_config(
@@ -662,6 +1383,7 @@ fn apply_provider_config(
                 cfg.providers.ollama.extra_headers = v;
             }
         }
+        ProviderKind::Mock => {}
     }
 }
 
@@ -728,12 +1450,24 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
     if let Ok(base) = std::env::var("MISTRAL_BASE_URL") {
         cfg.providers.mistral.base_url = base;
     }
+    if let Ok(fixture) = std::env::var("LIGHTCLAW_MOCK_FIXTURE") {
+        cfg.providers.mock.fixture = fixture;
+    }
 
     if let Ok(token) =
         std::env::var("TELOXIDE_TOKEN").or_else(|_| std::env::var("TELEGRAM_BOT_TOKEN"))
     {
         cfg.channels.telegram.bot_token = token;
     }
+    if let Ok(url) = std::env::var("LIGHTCLAW_TELEGRAM_WEBHOOK_URL") {
+        cfg.channels.telegram.webhook.url = url;
+    }
+    if let Ok(listen) = std::env::var("LIGHTCLAW_TELEGRAM_WEBHOOK_LISTEN") {
+        cfg.channels.telegram.webhook.listen = listen;
+    }
+    if let Ok(secret) = std::env::var("LIGHTCLAW_TELEGRAM_WEBHOOK_SECRET") {
+        cfg.channels.telegram.webhook.secret_token = secret;
+    }
     if let Ok(token) = std::env::var("DISCORD_BOT_TOKEN") {
         cfg.channels.discord.bot_token = token;
     }
@@ -753,6 +1487,106 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             .map(|s| s.to_string())
             .collect();
     }
+    if let Ok(val) = std::env::var("LIGHTCLAW_DISCORD_THREADS") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.channels.discord.threads = flag;
+        }
+    }
+    if let Ok(token) = std::env::var("SLACK_APP_TOKEN") {
+        cfg.channels.slack.app_token = token;
+    }
+    if let Ok(token) = std::env::var("SLACK_BOT_TOKEN") {
+        cfg.channels.slack.bot_token = token;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_SLACK_ALLOW_FROM") {
+        cfg.channels.slack.allow_from = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_SLACK_ALLOWED_CHANNELS") {
+        cfg.channels.slack.allowed_channels = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(homeserver) = std::env::var("LIGHTCLAW_MATRIX_HOMESERVER") {
+        cfg.channels.matrix.homeserver = homeserver;
+    }
+    if let Ok(user_id) = std::env::var("LIGHTCLAW_MATRIX_USER_ID") {
+        cfg.channels.matrix.user_id = user_id;
+    }
+    if let Ok(password) = std::env::var("LIGHTCLAW_MATRIX_PASSWORD") {
+        cfg.channels.matrix.password = password;
+    }
+    if let Ok(token) = std::env::var("LIGHTCLAW_MATRIX_ACCESS_TOKEN") {
+        cfg.channels.matrix.access_token = token;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_MATRIX_ALLOW_FROM") {
+        cfg.channels.matrix.allow_from = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(host) = std::env::var("LIGHTCLAW_EMAIL_IMAP_HOST") {
+        cfg.channels.email.imap_host = host;
+    }
+    if let Ok(host) = std::env::var("LIGHTCLAW_EMAIL_SMTP_HOST") {
+        cfg.channels.email.smtp_host = host;
+    }
+    if let Ok(username) = std::env::var("LIGHTCLAW_EMAIL_USERNAME") {
+        cfg.channels.email.username = username;
+    }
+    if let Ok(password) = std::env::var("LIGHTCLAW_EMAIL_PASSWORD") {
+        cfg.channels.email.password = password;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_EMAIL_ALLOW_FROM") {
+        cfg.channels.email.allow_from = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(bind) = std::env::var("LIGHTCLAW_WEBHOOKS_BIND") {
+        cfg.webhooks.bind = bind;
+    }
+    if let Ok(bind) = std::env::var("LIGHTCLAW_WEBCHAT_BIND") {
+        cfg.channels.webchat.bind = bind;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_WEBCHAT_USERS") {
+        cfg.channels.webchat.users = val
+            .split(',')
+            .filter_map(|entry| entry.split_once(':'))
+            .map(|(name, password)| (name.trim().to_ascii_lowercase(), password.to_string()))
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+    }
+    if let Ok(bind) = std::env::var("LIGHTCLAW_HTTP_BIND") {
+        cfg.channels.http.bind = bind;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_HTTP_API_KEYS") {
+        cfg.channels.http.api_keys = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_MATRIX_ALLOWED_ROOMS") {
+        cfg.channels.matrix.allowed_rooms = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
     if let Ok(provider) = std::env::var("LIGHTCLAW_WEB_SEARCH_PROVIDER") {
         if let Some(parsed) = WebSearchProvider::parse(&provider) {
             cfg.tools.web_search_provider = parsed;
@@ -797,6 +1631,11 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             cfg.transcription.language = Some(val);
         }
     }
+    if let Ok(val) = std::env::var("LIGHTCLAW_DELIVERY_MAX_ATTEMPTS") {
+        if let Ok(num) = val.parse::<u32>() {
+            cfg.delivery.max_attempts = num.max(1);
+        }
+    }
     if let Ok(val) = std::env::var("LIGHTCLAW_TRANSCRIPTION_MAX_BYTES") {
         if let Ok(num) = val.parse::<usize>() {
             cfg.transcription.max_bytes = num;
@@ -823,6 +1662,48 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             .collect::<Vec<_>>();
         cfg.transcription.mistral_timestamp_granularities = parsed;
     }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TRANSCRIPTION_COMMAND") {
+        cfg.transcription.command = val;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TRANSCRIPTION_ECHO") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.transcription.echo = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_ENABLED") {
+        if let Some(flag) = parse_bool(&val) {
+            cfg.tts.enabled = flag;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_PROVIDER") {
+        if !val.trim().is_empty() {
+            cfg.tts.provider = val;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_MODEL") {
+        if !val.trim().is_empty() {
+            cfg.tts.model = val;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_VOICE") {
+        if !val.trim().is_empty() {
+            cfg.tts.voice = val;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_COMMAND") {
+        cfg.tts.command = val;
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TTS_REPLY_MODE") {
+        if let Some(parsed) = VoiceReplyMode::parse(&val) {
+            cfg.tts.reply_mode = parsed;
+        }
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_FFMPEG") {
+        if !val.trim().is_empty() {
+            cfg.tts.ffmpeg_path = val.clone();
+            cfg.transcription.ffmpeg_path = val;
+        }
+    }
     if let Ok(path) =
         std::env::var("LIGHTCLAW_DATA_DIR").or_else(|_| std::env::var("RUSTBOT_DATA_DIR"))
     {
@@ -846,6 +1727,17 @@ fn apply_env_overrides(cfg: &mut AppConfig) {
             cfg.tools.exec_timeout_secs = num;
         }
     }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TOOLS_DISABLED") {
+        cfg.tools.disabled = val
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+    }
+    if let Ok(val) = std::env::var("LIGHTCLAW_TOOLS_PROFILE") {
+        cfg.tools.profile = val.trim().to_string();
+    }
     if let Ok(val) = std::env::var("LIGHTCLAW_MAX_TOOL_TURNS")
         .or_else(|_| std::env::var("RUSTBOT_MAX_TOOL_TURNS"))
     {
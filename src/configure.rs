@@ -23,7 +23,11 @@ pub fn run() -> Result<()> {
                 "LLM API provider and keys",
             )
             .item(MenuAction::Model, "Model", "Default model and fallbacks")
-            .item(MenuAction::Channels, "Channels", "Telegram, Discord")
+            .item(
+                MenuAction::Channels,
+                "Channels",
+                "Telegram, Discord, Slack, Matrix",
+            )
             .item(
                 MenuAction::Web,
                 "Web Settings",
@@ -34,6 +38,11 @@ pub fn run() -> Result<()> {
                 "Transcription",
                 "Voice/audio transcription settings",
             )
+            .item(
+                MenuAction::VoiceReplies,
+                "Voice replies",
+                "Text-to-speech settings for Telegram voice answers",
+            )
             .item(
                 MenuAction::Memory,
                 "Memory",
@@ -61,10 +70,36 @@ pub fn run() -> Result<()> {
                         "Bot token and allowed users",
                     )
                     .item(ChannelChoice::Discord, "Discord", "Bot token and channels")
+                    .item(ChannelChoice::Slack, "Slack", "App/bot tokens and channels")
+                    .item(
+                        ChannelChoice::Matrix,
+                        "Matrix",
+                        "Homeserver, login and rooms",
+                    )
+                    .item(
+                        ChannelChoice::Email,
+                        "Email",
+                        "IMAP/SMTP servers and senders",
+                    )
+                    .item(
+                        ChannelChoice::Http,
+                        "HTTP API",
+                        "OpenAI-compatible endpoint and API keys",
+                    )
+                    .item(
+                        ChannelChoice::WebChat,
+                        "Web chat",
+                        "Browser UI address and password",
+                    )
                     .interact()?;
                 match channel {
                     ChannelChoice::Telegram => configure_telegram(&mut root),
                     ChannelChoice::Discord => configure_discord(&mut root),
+                    ChannelChoice::Slack => configure_slack(&mut root),
+                    ChannelChoice::Matrix => configure_matrix(&mut root),
+                    ChannelChoice::Email => configure_email(&mut root),
+                    ChannelChoice::Http => configure_http(&mut root),
+                    ChannelChoice::WebChat => configure_webchat(&mut root),
                 }?;
                 dirty = root != initial_root;
             }
@@ -76,6 +111,10 @@ pub fn run() -> Result<()> {
                 configure_transcription(&mut root)?;
                 dirty = root != initial_root;
             }
+            MenuAction::VoiceReplies => {
+                configure_tts(&mut root)?;
+                dirty = root != initial_root;
+            }
             MenuAction::Memory => {
                 configure_memory(&mut root)?;
                 dirty = root != initial_root;
@@ -117,6 +156,7 @@ enum MenuAction {
     Channels,
     Web,
     Transcription,
+    VoiceReplies,
     Memory,
     ShowPath,
     SaveAndExit,
@@ -127,6 +167,11 @@ enum MenuAction {
 enum ChannelChoice {
     Telegram,
     Discord,
+    Slack,
+    Matrix,
+    Email,
+    Http,
+    WebChat,
 }
 
 fn prompt_str(label: &str, current: &str) -> Result<String> {
@@ -301,6 +346,235 @@ fn configure_discord(root: &mut Value) -> Result<bool> {
     Ok(root != &before)
 }
 
+fn configure_slack(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_app_token = get_str_at(root, &["channels", "slack", "app_token"]).unwrap_or("");
+    let current_bot_token = get_str_at(root, &["channels", "slack", "bot_token"]).unwrap_or("");
+    let current_allow = get_array_at(root, &["channels", "slack", "allow_from"]);
+    let current_allow_str = current_allow.join(",");
+    let current_channels = get_array_at(root, &["channels", "slack", "allowed_channels"]);
+    let current_channels_str = current_channels.join(",");
+
+    let app_token = prompt_secret("Slack app-level token (xapp-...)", current_app_token)?;
+    let bot_token = prompt_secret("Slack bot token (xoxb-...)", current_bot_token)?;
+    let allow_from = prompt_str(
+        "Allowed Slack users (member IDs/usernames, comma separated)",
+        &current_allow_str,
+    )?;
+    let allowed_channels = prompt_str_optional(
+        "Allowed Slack channel IDs (comma separated, blank = all)",
+        &current_channels_str,
+    )?;
+
+    let allow_list = parse_comma_list(&allow_from, &current_allow);
+    let channel_list = parse_comma_list(&allowed_channels, &current_channels);
+
+    set_path(
+        root,
+        &["channels", "slack", "app_token"],
+        Value::String(app_token),
+    )?;
+    set_path(
+        root,
+        &["channels", "slack", "bot_token"],
+        Value::String(bot_token),
+    )?;
+    set_path(
+        root,
+        &["channels", "slack", "allow_from"],
+        Value::Array(allow_list.into_iter().map(Value::String).collect()),
+    )?;
+    set_path(
+        root,
+        &["channels", "slack", "allowed_channels"],
+        Value::Array(channel_list.into_iter().map(Value::String).collect()),
+    )?;
+
+    Ok(root != &before)
+}
+
+fn configure_matrix(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_homeserver = get_str_at(root, &["channels", "matrix", "homeserver"]).unwrap_or("");
+    let current_user = get_str_at(root, &["channels", "matrix", "user_id"]).unwrap_or("");
+    let current_token = get_str_at(root, &["channels", "matrix", "access_token"]).unwrap_or("");
+    let current_password = get_str_at(root, &["channels", "matrix", "password"]).unwrap_or("");
+    let current_allow = get_array_at(root, &["channels", "matrix", "allow_from"]);
+    let current_allow_str = current_allow.join(",");
+    let current_rooms = get_array_at(root, &["channels", "matrix", "allowed_rooms"]);
+    let current_rooms_str = current_rooms.join(",");
+
+    let homeserver = prompt_str("Matrix homeserver URL", current_homeserver)?;
+    let user_id = prompt_str("Matrix bot user id (@bot:example.org)", current_user)?;
+    let use_token = select("Matrix authentication")
+        .item(true, "Access token", "Reuses one device across restarts")
+        .item(false, "Password", "Logs in on every start")
+        .initial_value(!current_token.trim().is_empty() || current_password.is_empty())
+        .interact()?;
+    let (access_token, password) = if use_token {
+        (
+            prompt_secret("Matrix access token", current_token)?,
+            current_password.to_string(),
+        )
+    } else {
+        (
+            String::new(),
+            prompt_secret("Matrix password", current_password)?,
+        )
+    };
+    let allow_from = prompt_str(
+        "Allowed Matrix users (user ids, comma separated)",
+        &current_allow_str,
+    )?;
+    let allowed_rooms = prompt_str_optional(
+        "Allowed Matrix room ids (comma separated, blank = all)",
+        &current_rooms_str,
+    )?;
+
+    let allow_list = parse_comma_list(&allow_from, &current_allow);
+    let room_list = parse_comma_list(&allowed_rooms, &current_rooms);
+
+    set_path(
+        root,
+        &["channels", "matrix", "homeserver"],
+        Value::String(homeserver),
+    )?;
+    set_path(
+        root,
+        &["channels", "matrix", "user_id"],
+        Value::String(user_id),
+    )?;
+    set_path(
+        root,
+        &["channels", "matrix", "access_token"],
+        Value::String(access_token),
+    )?;
+    set_path(
+        root,
+        &["channels", "matrix", "password"],
+        Value::String(password),
+    )?;
+    set_path(
+        root,
+        &["channels", "matrix", "allow_from"],
+        Value::Array(allow_list.into_iter().map(Value::String).collect()),
+    )?;
+    set_path(
+        root,
+        &["channels", "matrix", "allowed_rooms"],
+        Value::Array(room_list.into_iter().map(Value::String).collect()),
+    )?;
+
+    Ok(root != &before)
+}
+
+fn configure_email(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_imap = get_str_at(root, &["channels", "email", "imap_host"]).unwrap_or("");
+    let current_smtp = get_str_at(root, &["channels", "email", "smtp_host"]).unwrap_or("");
+    let current_user = get_str_at(root, &["channels", "email", "username"]).unwrap_or("");
+    let current_password = get_str_at(root, &["channels", "email", "password"]).unwrap_or("");
+    let current_allow = get_array_at(root, &["channels", "email", "allow_from"]);
+    let current_allow_str = current_allow.join(",");
+    let current_authserv = get_str_at(root, &["channels", "email", "authserv_id"]).unwrap_or("");
+
+    let imap_host = prompt_str("IMAP server (port 993, TLS)", current_imap)?;
+    let smtp_host = prompt_str("SMTP server (port 587, STARTTLS)", current_smtp)?;
+    let username = prompt_str("Mailbox login (usually the address)", current_user)?;
+    let password = prompt_secret("Mailbox password", current_password)?;
+    let allow_from = prompt_str(
+        "Allowed senders (addresses or @domain, comma separated)",
+        &current_allow_str,
+    )?;
+    let authserv_id = prompt_str(
+        "Mail server name in Authentication-Results headers",
+        current_authserv,
+    )?;
+
+    let allow_list = parse_comma_list(&allow_from, &current_allow);
+
+    set_path(
+        root,
+        &["channels", "email", "imap_host"],
+        Value::String(imap_host),
+    )?;
+    set_path(
+        root,
+        &["channels", "email", "smtp_host"],
+        Value::String(smtp_host),
+    )?;
+    set_path(
+        root,
+        &["channels", "email", "username"],
+        Value::String(username),
+    )?;
+    set_path(
+        root,
+        &["channels", "email", "password"],
+        Value::String(password),
+    )?;
+    set_path(
+        root,
+        &["channels", "email", "allow_from"],
+        Value::Array(allow_list.into_iter().map(Value::String).collect()),
+    )?;
+    set_path(
+        root,
+        &["channels", "email", "authserv_id"],
+        Value::String(authserv_id),
+    )?;
+
+    Ok(root != &before)
+}
+
+fn configure_http(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_bind = get_str_at(root, &["channels", "http", "bind"]).unwrap_or("127.0.0.1:8787");
+    let current_keys = get_array_at(root, &["channels", "http", "api_keys"]);
+    let current_keys_str = current_keys.join(",");
+
+    let bind = prompt_str("Listen address", current_bind)?;
+    let api_keys = prompt_secret("API keys (comma separated)", &current_keys_str)?;
+
+    let key_list = parse_comma_list(&api_keys, &current_keys);
+
+    set_path(root, &["channels", "http", "bind"], Value::String(bind))?;
+    set_path(
+        root,
+        &["channels", "http", "api_keys"],
+        Value::Array(key_list.into_iter().map(Value::String).collect()),
+    )?;
+
+    Ok(root != &before)
+}
+
+fn configure_webchat(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_bind =
+        get_str_at(root, &["channels", "webchat", "bind"]).unwrap_or("127.0.0.1:8790");
+    let current_user = root
+        .pointer("/channels/webchat/users")
+        .and_then(Value::as_object)
+        .and_then(|users| users.keys().next().cloned())
+        .unwrap_or_else(|| "me".to_string());
+
+    let bind = prompt_str("Listen address", current_bind)?;
+    let user = prompt_str("User name", &current_user)?
+        .trim()
+        .to_ascii_lowercase();
+    let current_password = get_str_at(root, &["channels", "webchat", "users", &user]).unwrap_or("");
+    let password = prompt_secret("Login password", current_password)?;
+
+    set_path(root, &["channels", "webchat", "bind"], Value::String(bind))?;
+    set_path(
+        root,
+        &["channels", "webchat", "users", &user],
+        Value::String(password),
+    )?;
+
+    Ok(root != &before)
+}
+
 fn configure_model(root: &mut Value) -> Result<bool> {
     let before = root.clone();
     let current_model =
@@ -496,6 +770,7 @@ fn configure_transcription(root: &mut Value) -> Result<bool> {
     let provider = select("Transcription provider")
         .item("openai", "OpenAI", "whisper")
         .item("mistral", "Mistral", "")
+        .item("local", "Local command", "whisper.cpp, faster-whisper, ...")
         .initial_value(&current_provider)
         .interact()?;
     let model = prompt_str("Transcription model", &current_model)?;
@@ -559,6 +834,22 @@ fn configure_transcription(root: &mut Value) -> Result<bool> {
         Value::Array(current_grans.iter().cloned().map(Value::String).collect()),
     )?;
 
+    if provider == "local" {
+        let current_command =
+            get_str_at(root, &["channels", "telegram", "transcription", "command"])
+                .unwrap_or("whisper-cli -m {model} -l {language} -nt -f {input}")
+                .to_string();
+        let command = prompt_str(
+            "Command ({input}, {output}, {model}, {language} placeholders)",
+            &current_command,
+        )?;
+        set_path(
+            root,
+            &["channels", "telegram", "transcription", "command"],
+            Value::String(command),
+        )?;
+    }
+
     if provider == "mistral" {
         let diarize = confirm("Enable diarization")
             .initial_value(current_diarize)
@@ -613,6 +904,96 @@ fn configure_transcription(root: &mut Value) -> Result<bool> {
     Ok(root != &before)
 }
 
+fn configure_tts(root: &mut Value) -> Result<bool> {
+    let before = root.clone();
+    let current_enabled =
+        get_bool_at(root, &["channels", "telegram", "tts", "enabled"]).unwrap_or(false);
+    let current_provider = get_str_at(root, &["channels", "telegram", "tts", "provider"])
+        .unwrap_or("openai")
+        .to_string();
+    let current_reply_mode = get_str_at(root, &["channels", "telegram", "tts", "reply_mode"])
+        .unwrap_or("auto")
+        .to_string();
+    let current_model = get_str_at(root, &["channels", "telegram", "tts", "model"])
+        .unwrap_or("tts-1")
+        .to_string();
+    let current_voice = get_str_at(root, &["channels", "telegram", "tts", "voice"])
+        .unwrap_or("alloy")
+        .to_string();
+    let current_command = get_str_at(root, &["channels", "telegram", "tts", "command"])
+        .unwrap_or("")
+        .to_string();
+
+    let enabled = confirm("Enable voice replies")
+        .initial_value(current_enabled)
+        .interact()?;
+    set_path(
+        root,
+        &["channels", "telegram", "tts", "enabled"],
+        Value::Bool(enabled),
+    )?;
+    if !enabled {
+        return Ok(root != &before);
+    }
+
+    let provider = select("Speech provider")
+        .item("openai", "OpenAI", "audio/speech API")
+        .item(
+            "local",
+            "Local command",
+            "e.g. piper, converted with ffmpeg",
+        )
+        .initial_value(&current_provider)
+        .interact()?;
+    let reply_mode = select("When to reply with voice")
+        .item("auto", "Auto", "When the user sent a voice note")
+        .item("always", "Always", "Every reply")
+        .item("off", "Off", "Only after /voice on in a chat")
+        .initial_value(&current_reply_mode)
+        .interact()?;
+    set_path(
+        root,
+        &["channels", "telegram", "tts", "provider"],
+        Value::String(provider.to_string()),
+    )?;
+    set_path(
+        root,
+        &["channels", "telegram", "tts", "reply_mode"],
+        Value::String(reply_mode.to_string()),
+    )?;
+
+    if provider == "local" {
+        let command = prompt_str(
+            "Command (text on stdin, {output} = audio file path)",
+            if current_command.is_empty() {
+                "piper --model en_US-lessac-medium.onnx --output_file {output}"
+            } else {
+                &current_command
+            },
+        )?;
+        set_path(
+            root,
+            &["channels", "telegram", "tts", "command"],
+            Value::String(command),
+        )?;
+    } else {
+        let model = prompt_str("Speech model", &current_model)?;
+        let voice = prompt_str("Voice", &current_voice)?;
+        set_path(
+            root,
+            &["channels", "telegram", "tts", "model"],
+            Value::String(model),
+        )?;
+        set_path(
+            root,
+            &["channels", "telegram", "tts", "voice"],
+            Value::String(voice),
+        )?;
+    }
+
+    Ok(root != &before)
+}
+
 fn configure_memory(root: &mut Value) -> Result<bool> {
     let before = root.clone();
     let current_mode = get_str_at(root, &["memory", "mode"])
@@ -167,6 +167,7 @@ impl CronService {
                 content: job.payload.message.clone(),
                 // TODO: Propagate job.payload.model when InboundMessage supports it
                 // For now, we just ensure the field exists in CronPayload
+                ..Default::default()
             };
             self.inner.bus.publish_inbound(msg).await;
 
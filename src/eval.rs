@@ -0,0 +1,467 @@
+use crate::agent::AgentLoop;
+use crate::bus::{AgentEventKind, InboundMessage, MessageBus};
+use crate::channels::ChannelCapabilities;
+use crate::config::AppConfig;
+use crate::cron::CronService;
+use crate::memory::smart::vector_store;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::broadcast;
+
+const CHANNEL: &str = "eval";
+/// Replaced with the case's scratch workspace in messages, mock scripts and
+/// file checks.
+const WORKSPACE_PLACEHOLDER: &str = "{workspace}";
+
+/// A YAML suite of conversations to replay through the agent.
+#[derive(Deserialize)]
+struct Suite {
+    name: Option<String>,
+    /// Route for every case (`model` or `provider/model`); `--model` wins.
+    model: Option<String>,
+    cases: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+struct Case {
+    name: String,
+    /// Scripted responses for the mock provider (see `providers.mock`). A
+    /// case with a script runs against `mock/mock` unless a route is set.
+    #[serde(default)]
+    mock: Option<Vec<serde_yaml::Value>>,
+    turns: Vec<Turn>,
+    #[serde(default)]
+    files: Vec<FileCheck>,
+    /// Text that must appear in the memory files or the vector memories
+    /// after the last turn.
+    #[serde(default)]
+    memory: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Turn {
+    user: String,
+    #[serde(default)]
+    expect: Expect,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Expect {
+    contains: Vec<String>,
+    not_contains: Vec<String>,
+    /// Regexes the answer must match; `(?i)` makes one case-insensitive.
+    matches: Vec<String>,
+    tools_called: Vec<String>,
+    tools_not_called: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct FileCheck {
+    /// Absolute, or relative to the case workspace.
+    path: String,
+    #[serde(default = "default_true")]
+    exists: bool,
+    #[serde(default)]
+    contains: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize)]
+struct CaseReport {
+    name: String,
+    passed: bool,
+    failures: Vec<String>,
+    duration_ms: u128,
+    turns: Vec<TurnReport>,
+}
+
+#[derive(Default, Serialize)]
+struct TurnReport {
+    user: String,
+    answer: Option<String>,
+    error: Option<String>,
+    tools: Vec<String>,
+    /// Sent with `send_message` or `send_file` during the turn.
+    messages: Vec<String>,
+    route: Option<String>,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+pub struct EvalOptions {
+    pub suite: PathBuf,
+    pub model: Option<String>,
+    pub output: Option<PathBuf>,
+}
+
+/// Run every case of a suite, print a report and write it as JSON. Returns
+/// the exit status: 0 when all cases passed, 1 otherwise.
+pub async fn run(opts: EvalOptions) -> Result<i32> {
+    let text = std::fs::read_to_string(&opts.suite)
+        .with_context(|| format!("cannot read suite {}", opts.suite.display()))?;
+    let suite: Suite = serde_yaml::from_str(&text)
+        .with_context(|| format!("invalid suite {}", opts.suite.display()))?;
+    let name = suite.name.clone().unwrap_or_else(|| {
+        opts.suite
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "suite".to_string())
+    });
+    let model = opts.model.or(suite.model);
+    let base = AppConfig::load_relaxed();
+    let started_at = chrono::Local::now();
+
+    println!("suite {name} ({} cases)", suite.cases.len());
+    let mut reports = Vec::new();
+    for case in &suite.cases {
+        let report = run_case(&base, model.as_deref(), case).await;
+        println!(
+            "  {} {} ({:.1}s)",
+            if report.passed { "PASS" } else { "FAIL" },
+            report.name,
+            report.duration_ms as f64 / 1000.0
+        );
+        for failure in &report.failures {
+            println!("       {failure}");
+        }
+        reports.push(report);
+    }
+    let failed = reports.iter().filter(|report| !report.passed).count();
+    println!("{} passed, {failed} failed", reports.len() - failed);
+
+    let output = opts.output.unwrap_or_else(|| {
+        base.data_dir.join("eval").join(format!(
+            "{name}-{}.json",
+            started_at.format("%Y%m%d-%H%M%S")
+        ))
+    });
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let artifact = json!({
+        "suite": name,
+        "model": model,
+        "started_at": started_at.to_rfc3339(),
+        "passed": reports.len() - failed,
+        "failed": failed,
+        "cases": reports,
+    });
+    std::fs::write(&output, serde_json::to_string_pretty(&artifact)?)
+        .with_context(|| format!("cannot write {}", output.display()))?;
+    println!("report written to {}", output.display());
+    Ok(if failed == 0 { 0 } else { 1 })
+}
+
+/// Run one case with a fresh agent in a scratch workspace seeded with the
+/// configured skills, so cases cannot affect each other or real data.
+async fn run_case(base: &AppConfig, model: Option<&str>, case: &Case) -> CaseReport {
+    let started = Instant::now();
+    let mut report = CaseReport {
+        name: case.name.clone(),
+        passed: false,
+        failures: Vec::new(),
+        duration_ms: 0,
+        turns: Vec::new(),
+    };
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            report.failures.push(format!("setup: {err}"));
+            return report;
+        }
+    };
+    let workspace = dir.path().join("workspace");
+    let fill = |text: &str| text.replace(WORKSPACE_PLACEHOLDER, &workspace.display().to_string());
+
+    let cfg = match case_config(base, model, case, dir.path(), &workspace, &fill) {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            report.failures.push(format!("setup: {err:#}"));
+            return report;
+        }
+    };
+    let bus = MessageBus::new();
+    let mut outbound = bus.register_channel(
+        CHANNEL,
+        ChannelCapabilities {
+            max_message_chars: None,
+            attachments: true,
+        },
+    );
+    let mut events = bus.subscribe_events();
+    let cron_service = CronService::new(&cfg, bus.clone());
+    let agent = AgentLoop::new(cfg, bus.clone(), cron_service);
+
+    for (index, turn) in case.turns.iter().enumerate() {
+        let user = fill(&turn.user);
+        let mut turn_report = TurnReport {
+            user: user.clone(),
+            ..TurnReport::default()
+        };
+        let result = agent
+            .ask(InboundMessage {
+                channel: CHANNEL.to_string(),
+                chat_id: case.name.clone(),
+                sender_id: CHANNEL.to_string(),
+                content: user,
+                ..Default::default()
+            })
+            .await;
+        // Events are published during the turn and buffered until now.
+        loop {
+            let event = match events.try_recv() {
+                Ok(event) => event,
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            };
+            match event.kind {
+                AgentEventKind::ToolCall { name, .. } => turn_report.tools.push(name),
+                AgentEventKind::TurnCompleted {
+                    route,
+                    input_tokens,
+                    output_tokens,
+                    ..
+                } => {
+                    turn_report.route = Some(route);
+                    turn_report.input_tokens = input_tokens;
+                    turn_report.output_tokens = output_tokens;
+                }
+                AgentEventKind::ToolResult { .. } => {}
+            }
+        }
+        while let Ok(msg) = outbound.try_recv() {
+            turn_report.messages.push(msg.content);
+        }
+
+        let label = format!("turn {}", index + 1);
+        match result {
+            Ok(answer) => {
+                report.failures.extend(
+                    check_turn(&turn.expect, &answer, &turn_report.tools)
+                        .into_iter()
+                        .map(|failure| format!("{label}: {failure}")),
+                );
+                turn_report.answer = Some(answer);
+                report.turns.push(turn_report);
+            }
+            Err(err) => {
+                report.failures.push(format!("{label}: agent error: {err}"));
+                turn_report.error = Some(err);
+                report.turns.push(turn_report);
+                break;
+            }
+        }
+    }
+
+    // Smart memory summarizes in the background; let it land before the
+    // memory checks.
+    agent.wait_for_memory().await;
+
+    for check in &case.files {
+        let path = PathBuf::from(fill(&check.path));
+        let path = if path.is_absolute() {
+            path
+        } else {
+            workspace.join(path)
+        };
+        report.failures.extend(check_file(check, &path));
+    }
+    if !case.memory.is_empty() {
+        let memory = read_memory(&workspace.join("memory"));
+        for expected in &case.memory {
+            if !memory.contains(expected.as_str()) {
+                report
+                    .failures
+                    .push(format!("memory does not contain {expected:?}"));
+            }
+        }
+    }
+
+    report.passed = report.failures.is_empty();
+    report.duration_ms = started.elapsed().as_millis();
+    report
+}
+
+fn case_config(
+    base: &AppConfig,
+    model: Option<&str>,
+    case: &Case,
+    dir: &Path,
+    workspace: &Path,
+    fill: &dyn Fn(&str) -> String,
+) -> Result<AppConfig> {
+    let mut cfg = base.clone();
+    cfg.data_dir = dir.join("data");
+    cfg.workspace_dir = workspace.to_path_buf();
+    std::fs::create_dir_all(workspace)?;
+    let skills = base.workspace_dir.join("skills");
+    if skills.is_dir() {
+        crate::skills::hub::copy_directory(&skills, &workspace.join("skills"))?;
+    }
+
+    let mut model = model.map(str::to_string);
+    if let Some(responses) = &case.mock {
+        let fixture = dir.join("mock.yaml");
+        let script = serde_yaml::to_string(&json!({ "responses": responses }))?;
+        std::fs::write(&fixture, fill(&script))?;
+        cfg.providers.mock.fixture = fixture.display().to_string();
+        model.get_or_insert_with(|| "mock/mock".to_string());
+    }
+    if let Some(model) = &model {
+        if !cfg.pin_model(model) {
+            return Err(anyhow!("invalid model '{model}'"));
+        }
+    }
+    Ok(cfg)
+}
+
+fn check_turn(expect: &Expect, answer: &str, tools: &[String]) -> Vec<String> {
+    let mut failures = Vec::new();
+    for text in &expect.contains {
+        if !answer.contains(text.as_str()) {
+            failures.push(format!("answer does not contain {text:?}"));
+        }
+    }
+    for text in &expect.not_contains {
+        if answer.contains(text.as_str()) {
+            failures.push(format!("answer contains {text:?}"));
+        }
+    }
+    for pattern in &expect.matches {
+        match Regex::new(pattern) {
+            Ok(regex) if regex.is_match(answer) => {}
+            Ok(_) => failures.push(format!("answer does not match /{pattern}/")),
+            Err(err) => failures.push(format!("invalid regex /{pattern}/: {err}")),
+        }
+    }
+    for tool in &expect.tools_called {
+        if !tools.contains(tool) {
+            failures.push(format!("{tool} was not called (called: {tools:?})"));
+        }
+    }
+    for tool in &expect.tools_not_called {
+        if tools.contains(tool) {
+            failures.push(format!("{tool} was called"));
+        }
+    }
+    failures
+}
+
+fn check_file(check: &FileCheck, path: &Path) -> Vec<String> {
+    match (std::fs::read_to_string(path), check.exists) {
+        (Ok(_), false) => vec![format!("{} exists", path.display())],
+        (Err(_), false) => Vec::new(),
+        (Err(err), true) => vec![format!("cannot read {}: {err}", path.display())],
+        (Ok(content), true) => check
+            .contains
+            .iter()
+            .filter(|text| !content.contains(text.as_str()))
+            .map(|text| format!("{} does not contain {text:?}", path.display()))
+            .collect(),
+    }
+}
+
+/// The text of every Markdown file in the memory directory, in name order,
+/// followed by the vector memories (Smart mode).
+fn read_memory(dir: &Path) -> String {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return String::new();
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    let mut texts = paths
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect::<Vec<_>>();
+    let vectors = dir.join("vectors.db");
+    if vectors.is_file() {
+        match vector_store::stored_contents(&vectors) {
+            Ok(contents) => texts.extend(contents),
+            Err(err) => texts.push(format!("(could not read {}: {err})", vectors.display())),
+        }
+    }
+    texts.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_mock_cases_and_reports_failed_assertions() {
+        let suite: Suite = serde_yaml::from_str(
+            r#"
+cases:
+  - name: notes
+    mock:
+      - tool_calls:
+          - name: write_file
+            args: { path: "{workspace}/todo.md", content: "- buy milk" }
+      - text: "Saved to todo.md."
+      - text: "You asked me to buy milk."
+    turns:
+      - user: "I prefer short answers. Note that I need milk."
+        expect:
+          contains: ["Saved"]
+          tools_called: [write_file]
+      - user: "What did I ask?"
+        expect:
+          matches: ["(?i)BUY MILK"]
+          tools_called: [exec]
+          not_contains: ["milk"]
+    files:
+      - path: todo.md
+        contains: ["buy milk", "eggs"]
+      - path: "{workspace}/other.md"
+        exists: false
+    memory: ["I prefer short answers"]
+"#,
+        )
+        .unwrap();
+        let mut base = AppConfig::defaults();
+        base.memory.mode = crate::config::MemoryMode::Simple;
+        let workspace = tempfile::tempdir().unwrap();
+        base.workspace_dir = workspace.path().to_path_buf();
+
+        let report = run_case(&base, None, &suite.cases[0]).await;
+        assert!(!report.passed);
+        assert_eq!(report.turns.len(), 2);
+        assert_eq!(report.turns[0].tools, ["write_file"]);
+        assert_eq!(report.turns[0].route.as_deref(), Some("mock / mock"));
+        assert_eq!(report.failures.len(), 3, "{:?}", report.failures);
+        assert_eq!(report.failures[0], "turn 2: answer contains \"milk\"");
+        assert_eq!(
+            report.failures[1],
+            "turn 2: exec was not called (called: [])"
+        );
+        assert!(report.failures[2].ends_with("todo.md does not contain \"eggs\""));
+    }
+
+    #[test]
+    fn memory_checks_read_notes_and_vector_memories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("MEMORY.md"), "- likes tea").unwrap();
+        let conn = rusqlite::Connection::open(dir.path().join("vectors.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE memories (content TEXT NOT NULL, created_at TEXT NOT NULL);
+             INSERT INTO memories VALUES ('Ships on Fridays', '2026-01-01T00:00:00Z');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let memory = read_memory(dir.path());
+        assert!(memory.contains("likes tea"), "{memory}");
+        assert!(memory.contains("Ships on Fridays"), "{memory}");
+    }
+}
@@ -1,21 +1,27 @@
 mod agent;
+mod ask;
+mod audio;
 mod bus;
 mod channels;
 mod config;
 mod configure;
 mod cron;
+mod eval;
 mod memory;
+mod outbox;
 mod providers;
 mod service;
 mod session_compaction;
 mod skills;
 mod tools;
 mod transcription;
+mod tts;
 mod uninstall;
+mod webhooks;
 
 use anyhow::{anyhow, Result};
 use clap::{CommandFactory, Parser, Subcommand};
-use tokio::io::{self, AsyncBufReadExt};
+use std::sync::Arc;
 use tracing::{info, warn};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::EnvFilter;
@@ -32,6 +38,37 @@ struct Cli {
 enum Commands {
     Run,
     Tui,
+    /// Run one agent turn without the daemon and print the answer
+    Ask {
+        /// The prompt; read from stdin when omitted or `-`
+        prompt: Option<String>,
+        /// Also read stdin and append it to the prompt
+        #[arg(long, default_value_t = false)]
+        stdin: bool,
+        /// Memory namespace for the turn; history is not kept between runs
+        #[arg(long, default_value = "default")]
+        session: String,
+        /// Use this model (`model` or `provider/model`) without fallbacks
+        #[arg(long)]
+        model: Option<String>,
+        /// Answer without tools
+        #[arg(long, default_value_t = false)]
+        no_tools: bool,
+        /// Print the answer, tool calls, route and token usage as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Replay a YAML suite of conversations and check the agent's behaviour
+    Eval {
+        /// Path to the suite file
+        suite: std::path::PathBuf,
+        /// Route for every case (`model` or `provider/model`), overriding the suite
+        #[arg(long)]
+        model: Option<String>,
+        /// Where to write the JSON report (default: <data dir>/eval/)
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
     Configure,
     Uninstall,
     Skills {
@@ -48,6 +85,37 @@ enum Commands {
         #[command(subcommand)]
         command: ServiceCommands,
     },
+    Outbox {
+        /// Inspect and replay queued or failed outbound deliveries
+        #[command(subcommand)]
+        command: OutboxCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum OutboxCommands {
+    List {
+        /// Filter by status: pending or dead
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+    Show {
+        #[arg(long)]
+        id: i64,
+    },
+    /// Re-queue dead-lettered deliveries (one by id, or all)
+    Replay {
+        #[arg(long, conflicts_with = "all")]
+        id: Option<i64>,
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
+    Remove {
+        #[arg(long)]
+        id: i64,
+    },
 }
 
 #[derive(Subcommand)]
@@ -110,12 +178,61 @@ pub async fn run_cli() -> Result<()> {
         println!();
         return Ok(());
     };
-    let write_runtime_logs = matches!(&command, Commands::Run | Commands::Tui);
-    init_logging(write_runtime_logs);
+    let write_runtime_logs = matches!(
+        &command,
+        Commands::Run | Commands::Tui | Commands::Ask { .. } | Commands::Eval { .. }
+    );
+    // The TUI owns the terminal and `ask` and `eval` print their own output,
+    // so they only log to the file.
+    init_logging(
+        write_runtime_logs,
+        !matches!(
+            &command,
+            Commands::Tui | Commands::Ask { .. } | Commands::Eval { .. }
+        ),
+    );
 
     match command {
         Commands::Run => run().await,
         Commands::Tui => run_tui().await,
+        Commands::Ask {
+            prompt,
+            stdin,
+            session,
+            model,
+            no_tools,
+            json,
+        } => {
+            let status = ask::run(ask::AskOptions {
+                prompt,
+                stdin,
+                session,
+                model,
+                tools: !no_tools,
+                json,
+            })
+            .await?;
+            if status != 0 {
+                std::process::exit(status);
+            }
+            Ok(())
+        }
+        Commands::Eval {
+            suite,
+            model,
+            output,
+        } => {
+            let status = eval::run(eval::EvalOptions {
+                suite,
+                model,
+                output,
+            })
+            .await?;
+            if status != 0 {
+                std::process::exit(status);
+            }
+            Ok(())
+        }
         Commands::Configure => configure::run(),
         Commands::Uninstall => uninstall::run(),
         Commands::Skills { command } => {
@@ -125,13 +242,20 @@ pub async fn run_cli() -> Result<()> {
         }
         Commands::Cron { command } => handle_cron(command).await,
         Commands::Service { command } => handle_service(command).await,
+        Commands::Outbox { command } => handle_outbox(command).await,
     }
 }
 
 async fn run() -> Result<()> {
     let cfg = config::AppConfig::load()?;
 
-    let bus = bus::MessageBus::new();
+    let bus = match outbox::Outbox::open(&outbox_path(&cfg), &cfg.delivery) {
+        Ok(outbox) => bus::MessageBus::with_outbox(outbox),
+        Err(err) => {
+            warn!("durable outbound queue unavailable, replies will not be retried: {err}");
+            bus::MessageBus::new()
+        }
+    };
 
     // Start Cron Service
     let cron_service = cron::CronService::new(&cfg, bus.clone());
@@ -142,40 +266,20 @@ async fn run() -> Result<()> {
         agent.run().await;
     });
 
-    let mut enabled_channels = 0usize;
-
-    if cfg.telegram_enabled() {
-        enabled_channels += 1;
-        let telegram_cfg = cfg.clone();
-        let telegram_bus = bus.clone();
-        tokio::spawn(async move {
-            if let Err(err) = channels::telegram::start(telegram_cfg, telegram_bus).await {
-                warn!("telegram disabled: {err}");
-            }
-        });
-    } else {
-        info!("Telegram token not configured; running without Telegram input/output");
-        info!("Set TELOXIDE_TOKEN or channels.telegram.token to enable Telegram");
+    let channels = channels::from_config(&cfg);
+    if channels.is_empty() {
+        warn!("lightclaw is running without chat input/output; press Ctrl+C to exit");
     }
+    channels::start_all(&channels, &bus);
+    bus.dead_letter_unserved().await;
 
-    if cfg.discord_enabled() {
-        enabled_channels += 1;
-        let discord_cfg = cfg.clone();
-        let discord_bus = bus.clone();
-        tokio::spawn(async move {
-            if let Err(err) = channels::discord::start(discord_cfg, discord_bus).await {
-                warn!("discord disabled: {err}");
-            }
-        });
-    } else {
-        info!("Discord token not configured; running without Discord input/output");
-        info!("Set DISCORD_BOT_TOKEN or channels.discord.token to enable Discord");
+    if cfg.webhooks_enabled() {
+        webhooks::spawn(&cfg, bus.clone());
     }
 
-    if enabled_channels == 0 {
-        warn!("lightclaw is running without chat input/output; press Ctrl+C to exit");
-    }
     wait_for_shutdown().await?;
+    info!("shutting down channels");
+    channels::shutdown_all(&channels).await;
 
     Ok(())
 }
@@ -256,6 +360,86 @@ async fn handle_cron(cmd: CronCommands) -> Result<()> {
     Ok(())
 }
 
+fn outbox_path(cfg: &config::AppConfig) -> std::path::PathBuf {
+    cfg.data_dir.join("outbox.db")
+}
+
+async fn handle_outbox(cmd: OutboxCommands) -> Result<()> {
+    let cfg = config::AppConfig::load_relaxed();
+    let outbox = outbox::Outbox::open(&outbox_path(&cfg), &cfg.delivery)?;
+
+    match cmd {
+        OutboxCommands::List { status, limit } => {
+            let status = match status {
+                Some(raw) => Some(
+                    outbox::DeliveryStatus::parse(&raw)
+                        .ok_or_else(|| anyhow!("unknown status '{raw}' (use pending or dead)"))?,
+                ),
+                None => None,
+            };
+            let entries = outbox.list(status, limit).await?;
+            if entries.is_empty() {
+                println!("Outbox is empty.");
+            } else {
+                println!(
+                    "{:<8} {:<10} {:<8} {:<24} {:<9} {:<40}",
+                    "ID", "Channel", "Status", "Chat", "Attempts", "Last error"
+                );
+                println!("{:-<100}", "");
+                for entry in entries {
+                    let error = entry.last_error.as_deref().unwrap_or("-");
+                    println!(
+                        "{:<8} {:<10} {:<8} {:<24} {:<9} {:<40}",
+                        entry.id,
+                        entry.message.channel,
+                        entry.status.as_str(),
+                        entry.message.chat_id,
+                        entry.attempts,
+                        error.chars().take(40).collect::<String>()
+                    );
+                }
+            }
+        }
+        OutboxCommands::Show { id } => match outbox.get(id).await? {
+            Some(entry) => {
+                let next = chrono::DateTime::<chrono::Utc>::from_timestamp_millis(
+                    entry.next_attempt_at_ms,
+                )
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_else(|| "N/A".to_string());
+                println!("ID: {}", entry.id);
+                println!("Channel: {}", entry.message.channel);
+                println!("Chat: {}", entry.message.chat_id);
+                println!("Status: {}", entry.status.as_str());
+                println!("Attempts: {}", entry.attempts);
+                println!("Created: {}", entry.created_at);
+                println!("Next attempt: {next}");
+                println!("Last error: {}", entry.last_error.as_deref().unwrap_or("-"));
+                for attachment in &entry.message.attachments {
+                    println!("Attachment: {}", attachment.path.display());
+                }
+                println!("\n{}", entry.message.content);
+            }
+            None => println!("Entry not found."),
+        },
+        OutboxCommands::Replay { id, all } => {
+            if id.is_none() && !all {
+                return Err(anyhow!("pass --id <ID> or --all"));
+            }
+            let count = outbox.replay(id).await?;
+            println!("Re-queued {count} delivery(ies).");
+        }
+        OutboxCommands::Remove { id } => {
+            if outbox.remove(id).await? {
+                println!("Entry removed.");
+            } else {
+                println!("Entry not found.");
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn handle_service(cmd: ServiceCommands) -> Result<()> {
     let scope = |system: bool| {
         if system {
@@ -283,56 +467,23 @@ async fn run_tui() -> Result<()> {
     let cron_service = cron::CronService::new(&cfg, bus.clone());
     cron_service.start().await;
 
+    let tui: Arc<dyn channels::Channel> = Arc::new(channels::tui::TuiChannel::new(&cfg));
     let agent = agent::AgentLoop::new(cfg, bus.clone(), cron_service);
     tokio::spawn(async move {
         agent.run().await;
     });
 
-    let bus_for_outbound = bus.clone();
-    tokio::spawn(async move {
-        let mut outbound_rx = bus_for_outbound.subscribe_outbound();
-        loop {
-            let msg = match outbound_rx.recv().await {
-                Ok(msg) => msg,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
-            };
-            if msg.channel != "tui" {
-                continue;
-            }
-            println!("\nassistant> {}\n", msg.content.trim());
-        }
-    });
-
-    println!("lightclaw TUI mode");
-    println!("Type messages and press Enter. Type /exit to quit.\n");
-
-    let mut lines = io::BufReader::new(io::stdin()).lines();
-    while let Some(line) = lines.next_line().await? {
-        let content = line.trim().to_string();
-        if content.is_empty() {
-            continue;
-        }
-        if content == "/exit" {
-            break;
-        }
-        bus.publish_inbound(bus::InboundMessage {
-            channel: "tui".to_string(),
-            chat_id: "local".to_string(),
-            sender_id: "local".to_string(),
-            content,
-        })
-        .await;
-    }
-
-    Ok(())
+    channels::attach(&tui, &bus);
+    tui.start(bus).await
 }
 
-fn init_logging(write_runtime_logs: bool) {
+fn init_logging(write_runtime_logs: bool, log_to_stdout: bool) {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    let stdout_layer = tracing_subscriber::fmt::layer()
-        .with_target(false)
-        .compact();
+    let stdout_layer = log_to_stdout.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_target(false)
+            .compact()
+    });
 
     if write_runtime_logs {
         let log_path = config::log_file_path();
@@ -78,6 +78,9 @@ impl LlmClient {
                 None,
                 cfg.providers.ollama.extra_headers.clone(),
             ),
+            ProviderKind::Mock => Err(anyhow!(
+                "the mock provider has no embeddings or summarization API"
+            )),
         }
     }
 
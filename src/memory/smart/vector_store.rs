@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock, Mutex};
 
 use anyhow::{anyhow, Result};
@@ -473,6 +473,17 @@ fn parse_memory_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<MemoryItem> {
     })
 }
 
+/// Content of every memory in the database at `db_path`, across namespaces,
+/// oldest first. Opens it read-only, without an embedder.
+pub fn stored_contents(db_path: &Path) -> Result<Vec<String>> {
+    let conn = Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT content FROM memories ORDER BY created_at")?;
+    let contents = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(contents)
+}
+
 fn init_db(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS memories (\
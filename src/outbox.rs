@@ -0,0 +1,640 @@
+use crate::bus::OutboundMessage;
+use crate::config::DeliveryConfig;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use dashmap::DashMap;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// Upper bound on how long an idle worker sleeps before re-checking the
+/// queue, so rows replayed from the CLI are picked up by a running daemon.
+const IDLE_POLL: Duration = Duration::from_secs(15);
+const CLAIM_BATCH: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "pending" => Some(Self::Pending),
+            "dead" | "failed" => Some(Self::Dead),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+/// A queued outbound message as stored in the outbox.
+#[derive(Clone, Debug)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at_ms: i64,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub message: OutboundMessage,
+}
+
+/// Durable outbound queue backed by SQLite. Replies are written here before
+/// delivery and removed once a channel worker has sent them; failures are
+/// retried with exponential backoff and dead-lettered after
+/// `max_attempts`. Delivery is at-least-once: a reply that failed halfway
+/// (e.g. after its first chunk) is resent in full.
+#[derive(Clone)]
+pub struct Outbox {
+    conn: Arc<Mutex<Connection>>,
+    notify: Arc<Notify>,
+    cfg: DeliveryConfig,
+    /// Generation of the live worker per channel; a worker whose generation
+    /// is no longer current exits.
+    workers: Arc<DashMap<String, u64>>,
+}
+
+impl Outbox {
+    pub fn open(path: &Path, cfg: &DeliveryConfig) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::from_connection(Connection::open(path)?, cfg)
+    }
+
+    #[cfg(test)]
+    fn open_in_memory(cfg: &DeliveryConfig) -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?, cfg)
+    }
+
+    fn from_connection(conn: Connection, cfg: &DeliveryConfig) -> Result<Self> {
+        init_db(&conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            notify: Arc::new(Notify::new()),
+            cfg: cfg.clone(),
+            workers: Arc::new(DashMap::new()),
+        })
+    }
+
+    /// Run a blocking closure against the database connection on Tokio's
+    /// blocking thread pool, avoiding stalls on the async runtime.
+    async fn with_conn<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().map_err(|e| anyhow!("mutex poisoned: {e}"))?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| anyhow!("blocking task failed: {e}"))?
+    }
+
+    pub async fn enqueue(&self, msg: &OutboundMessage) -> Result<i64> {
+        let payload = serde_json::to_string(msg)?;
+        let channel = msg.channel.clone();
+        let chat_id = msg.chat_id.clone();
+        let now = Utc::now();
+        let id = self
+            .with_conn(move |conn| {
+                conn.execute(
+                    "INSERT INTO outbound (channel, chat_id, payload, status, attempts, next_attempt_at, created_at) VALUES (?1, ?2, ?3, 'pending', 0, ?4, ?5)",
+                    params![channel, chat_id, payload, now.timestamp_millis(), now.to_rfc3339()],
+                )?;
+                Ok(conn.last_insert_rowid())
+            })
+            .await?;
+        self.notify.notify_waiters();
+        Ok(id)
+    }
+
+    /// Due pending entries for `channel`. Only the oldest pending entry of
+    /// each chat is eligible, so replies to one chat keep their order while a
+    /// failing chat does not hold up the others.
+    async fn claim_due(&self, channel: &str, limit: usize) -> Result<Vec<OutboxEntry>> {
+        let channel = channel.to_string();
+        let now = Utc::now().timestamp_millis();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, status, attempts, next_attempt_at, last_error, created_at, payload FROM outbound o \
+                 WHERE o.status = 'pending' AND o.channel = ?1 AND o.next_attempt_at <= ?2 \
+                 AND NOT EXISTS (SELECT 1 FROM outbound p WHERE p.status = 'pending' AND p.channel = o.channel AND p.chat_id = o.chat_id AND p.id < o.id) \
+                 ORDER BY o.id LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![channel, now, limit as i64], parse_entry_row)?;
+            collect_entries(conn, rows)
+        })
+        .await
+    }
+
+    /// Milliseconds until the next entry [`Outbox::claim_due`] would return
+    /// becomes due. Only the head of each chat counts: a later entry that is
+    /// already due waits for its chat's head anyway.
+    async fn next_due_in(&self, channel: &str) -> Result<Option<i64>> {
+        let channel = channel.to_string();
+        let now = Utc::now().timestamp_millis();
+        self.with_conn(move |conn| {
+            let next: Option<i64> = conn
+                .query_row(
+                    "SELECT MIN(o.next_attempt_at) FROM outbound o WHERE o.status = 'pending' AND o.channel = ?1 \
+                     AND NOT EXISTS (SELECT 1 FROM outbound p WHERE p.status = 'pending' AND p.channel = o.channel AND p.chat_id = o.chat_id AND p.id < o.id)",
+                    params![channel],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+            Ok(next.map(|at| (at - now).max(0)))
+        })
+        .await
+    }
+
+    async fn mark_delivered(&self, id: i64) -> Result<()> {
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM outbound WHERE id = ?1", params![id])?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Record a failed attempt; returns the entry's new status.
+    async fn mark_failed(&self, entry: &OutboxEntry, error: &str) -> Result<DeliveryStatus> {
+        let attempts = entry.attempts + 1;
+        let status = if attempts >= self.cfg.max_attempts {
+            DeliveryStatus::Dead
+        } else {
+            DeliveryStatus::Pending
+        };
+        let delay = backoff_delay(
+            attempts,
+            self.cfg.backoff_base_secs,
+            self.cfg.backoff_max_secs,
+        );
+        let next_attempt_at = Utc::now().timestamp_millis() + delay.as_millis() as i64;
+        let id = entry.id;
+        let error = error.to_string();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE outbound SET status = ?2, attempts = ?3, next_attempt_at = ?4, last_error = ?5 WHERE id = ?1",
+                params![id, status.as_str(), attempts, next_attempt_at, error],
+            )?;
+            Ok(())
+        })
+        .await?;
+        Ok(status)
+    }
+
+    pub async fn list(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEntry>> {
+        let status = status.map(|status| status.as_str().to_string());
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, status, attempts, next_attempt_at, last_error, created_at, payload FROM outbound \
+                 WHERE (?1 IS NULL OR status = ?1) ORDER BY id LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![status, limit as i64], parse_entry_row)?;
+            collect_entries(conn, rows)
+        })
+        .await
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<OutboxEntry>> {
+        self.with_conn(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT id, status, attempts, next_attempt_at, last_error, created_at, payload FROM outbound WHERE id = ?1",
+                    params![id],
+                    parse_entry_row,
+                )
+                .optional()?;
+            row.map(|(entry, payload)| finish_entry(entry, &payload))
+                .transpose()
+        })
+        .await
+    }
+
+    /// Put dead-lettered entries back in the queue with a fresh attempt
+    /// budget: one entry by id, or all of them. Returns how many were reset.
+    pub async fn replay(&self, id: Option<i64>) -> Result<usize> {
+        let now = Utc::now().timestamp_millis();
+        let count = self
+            .with_conn(move |conn| {
+                let count = conn.execute(
+                    "UPDATE outbound SET status = 'pending', attempts = 0, next_attempt_at = ?1 \
+                     WHERE status = 'dead' AND (?2 IS NULL OR id = ?2)",
+                    params![now, id],
+                )?;
+                Ok(count)
+            })
+            .await?;
+        self.notify.notify_waiters();
+        Ok(count)
+    }
+
+    /// Dead-letter the pending entries of every channel not in `running`,
+    /// e.g. replies queued for a channel that is no longer configured.
+    /// Returns how many entries were dead-lettered.
+    pub async fn dead_letter_unserved(&self, running: &[String]) -> Result<usize> {
+        let running = running.to_vec();
+        self.with_conn(move |conn| {
+            let mut stmt =
+                conn.prepare("SELECT DISTINCT channel FROM outbound WHERE status = 'pending'")?;
+            let channels = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut count = 0;
+            for channel in channels.iter().filter(|c| !running.contains(c)) {
+                count += conn.execute(
+                    "UPDATE outbound SET status = 'dead', last_error = ?2 \
+                     WHERE status = 'pending' AND channel = ?1",
+                    params![channel, format!("channel '{channel}' is not running")],
+                )?;
+            }
+            Ok(count)
+        })
+        .await
+    }
+
+    /// Stop the worker for `channel` and dead-letter its pending entries,
+    /// after the channel failed to start. `outbox replay` requeues them.
+    pub async fn retire_channel(&self, channel: &str, reason: &str) -> Result<usize> {
+        if let Some(mut generation) = self.workers.get_mut(channel) {
+            *generation += 1;
+        }
+        self.notify.notify_waiters();
+        let channel = channel.to_string();
+        let reason = reason.to_string();
+        self.with_conn(move |conn| {
+            let count = conn.execute(
+                "UPDATE outbound SET status = 'dead', last_error = ?2 \
+                 WHERE status = 'pending' AND channel = ?1",
+                params![channel, reason],
+            )?;
+            Ok(count)
+        })
+        .await
+    }
+
+    /// Delete an entry regardless of its status.
+    pub async fn remove(&self, id: i64) -> Result<bool> {
+        self.with_conn(move |conn| {
+            let count = conn.execute("DELETE FROM outbound WHERE id = ?1", params![id])?;
+            Ok(count > 0)
+        })
+        .await
+    }
+
+    /// Spawn the delivery worker for `channel`. It sends due entries through
+    /// `deliver` until the process exits, sleeping until new entries are
+    /// enqueued or a retry becomes due.
+    pub fn spawn_worker<F, Fut>(&self, channel: &str, deliver: F)
+    where
+        F: Fn(OutboundMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let outbox = self.clone();
+        let channel = channel.to_string();
+        let generation = {
+            let mut current = self.workers.entry(channel.clone()).or_insert(0);
+            *current += 1;
+            *current
+        };
+        tokio::spawn(async move {
+            loop {
+                if outbox.workers.get(&channel).map(|g| *g) != Some(generation) {
+                    break;
+                }
+                let notified = outbox.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let entries = match outbox.claim_due(&channel, CLAIM_BATCH).await {
+                    Ok(entries) => entries,
+                    Err(err) => {
+                        warn!("{channel} outbox read failed: {err}");
+                        tokio::time::sleep(IDLE_POLL).await;
+                        continue;
+                    }
+                };
+                if !entries.is_empty() {
+                    for entry in entries {
+                        outbox.attempt(&channel, entry, &deliver).await;
+                    }
+                    continue;
+                }
+
+                let wait = match outbox.next_due_in(&channel).await {
+                    Ok(Some(ms)) => Duration::from_millis(ms as u64).min(IDLE_POLL),
+                    _ => IDLE_POLL,
+                };
+                tokio::select! {
+                    _ = &mut notified => {}
+                    _ = tokio::time::sleep(wait) => {}
+                }
+            }
+        });
+    }
+
+    async fn attempt<F, Fut>(&self, channel: &str, entry: OutboxEntry, deliver: &F)
+    where
+        F: Fn(OutboundMessage) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let result = deliver(entry.message.clone()).await;
+        let outcome = match result {
+            Ok(()) => self.mark_delivered(entry.id).await,
+            Err(err) => {
+                let error = format!("{err:#}");
+                match self.mark_failed(&entry, &error).await {
+                    Ok(DeliveryStatus::Dead) => {
+                        warn!(
+                            "{channel} delivery #{} to {} dead-lettered after {} attempts: {error}",
+                            entry.id,
+                            entry.message.chat_id,
+                            entry.attempts + 1
+                        );
+                        Ok(())
+                    }
+                    Ok(DeliveryStatus::Pending) => {
+                        info!(
+                            "{channel} delivery #{} to {} failed (attempt {}), will retry: {error}",
+                            entry.id,
+                            entry.message.chat_id,
+                            entry.attempts + 1
+                        );
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+        if let Err(err) = outcome {
+            warn!("{channel} outbox update for #{} failed: {err}", entry.id);
+        }
+    }
+}
+
+/// Delay before retry number `attempts` (1-based): `base * 2^(attempts-1)`,
+/// capped at `max`.
+fn backoff_delay(attempts: u32, base_secs: u64, max_secs: u64) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(32);
+    let secs = base_secs.saturating_mul(1u64 << exponent).min(max_secs);
+    Duration::from_secs(secs)
+}
+
+type EntryRow = (OutboxEntry, String);
+
+fn parse_entry_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<EntryRow> {
+    let status: String = row.get(1)?;
+    Ok((
+        OutboxEntry {
+            id: row.get(0)?,
+            status: DeliveryStatus::parse(&status).unwrap_or(DeliveryStatus::Pending),
+            attempts: row.get(2)?,
+            next_attempt_at_ms: row.get(3)?,
+            last_error: row.get(4)?,
+            created_at: row.get(5)?,
+            message: OutboundMessage {
+                channel: String::new(),
+                chat_id: String::new(),
+                content: String::new(),
+                attachments: Vec::new(),
+                reply_to: None,
+            },
+        },
+        row.get(6)?,
+    ))
+}
+
+fn finish_entry(mut entry: OutboxEntry, payload: &str) -> Result<OutboxEntry> {
+    entry.message = serde_json::from_str(payload)
+        .map_err(|err| anyhow!("outbox entry #{} has an invalid payload: {err}", entry.id))?;
+    Ok(entry)
+}
+
+/// Parse fetched rows. A pending row whose payload cannot be parsed would
+/// never be delivered and would block its chat, so it is dead-lettered with
+/// the parse error.
+fn collect_entries(
+    conn: &Connection,
+    rows: impl Iterator<Item = rusqlite::Result<EntryRow>>,
+) -> Result<Vec<OutboxEntry>> {
+    let mut entries = Vec::new();
+    for row in rows {
+        let (entry, payload) = row?;
+        let id = entry.id;
+        match finish_entry(entry, &payload) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => {
+                warn!("{err}; dead-lettering it");
+                conn.execute(
+                    "UPDATE outbound SET status = 'dead', last_error = ?2 WHERE id = ?1 AND status = 'pending'",
+                    params![id, err.to_string()],
+                )?;
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn init_db(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbound (\
+            id INTEGER PRIMARY KEY AUTOINCREMENT,\
+            channel TEXT NOT NULL,\
+            chat_id TEXT NOT NULL,\
+            payload TEXT NOT NULL,\
+            status TEXT NOT NULL DEFAULT 'pending',\
+            attempts INTEGER NOT NULL DEFAULT 0,\
+            next_attempt_at INTEGER NOT NULL,\
+            last_error TEXT,\
+            created_at TEXT NOT NULL\
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_outbound_due ON outbound(status, channel, next_attempt_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff_delay, DeliveryStatus, Outbox};
+    use crate::bus::OutboundMessage;
+    use crate::config::DeliveryConfig;
+    use std::time::Duration;
+
+    fn message(chat_id: &str, content: &str) -> OutboundMessage {
+        OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: chat_id.to_string(),
+            content: content.to_string(),
+            attachments: Vec::new(),
+            reply_to: None,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        assert_eq!(backoff_delay(1, 2, 600), Duration::from_secs(2));
+        assert_eq!(backoff_delay(4, 2, 600), Duration::from_secs(16));
+        assert_eq!(backoff_delay(20, 2, 600), Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn failing_entries_block_only_their_chat_and_dead_letter() {
+        let cfg = DeliveryConfig {
+            max_attempts: 2,
+            backoff_base_secs: 0,
+            backoff_max_secs: 0,
+        };
+        let outbox = Outbox::open_in_memory(&cfg).unwrap();
+        outbox.enqueue(&message("1", "first")).await.unwrap();
+        outbox.enqueue(&message("1", "second")).await.unwrap();
+        outbox.enqueue(&message("2", "other chat")).await.unwrap();
+
+        let due = outbox.claim_due("telegram", 10).await.unwrap();
+        let contents = due
+            .iter()
+            .map(|entry| entry.message.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, vec!["first", "other chat"]);
+        assert!(outbox.claim_due("discord", 10).await.unwrap().is_empty());
+
+        let head = &due[0];
+        assert_eq!(
+            outbox.mark_failed(head, "network down").await.unwrap(),
+            DeliveryStatus::Pending
+        );
+        let retry = outbox.get(head.id).await.unwrap().unwrap();
+        assert_eq!(
+            outbox.mark_failed(&retry, "network down").await.unwrap(),
+            DeliveryStatus::Dead
+        );
+
+        // With the head dead-lettered, the next reply for chat 1 is released.
+        let due = outbox.claim_due("telegram", 10).await.unwrap();
+        assert_eq!(due[0].message.content, "second");
+
+        let dead = outbox.list(Some(DeliveryStatus::Dead), 10).await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].last_error.as_deref(), Some("network down"));
+        assert_eq!(outbox.replay(None).await.unwrap(), 1);
+        assert!(outbox
+            .list(Some(DeliveryStatus::Dead), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_backing_off_head_of_a_chat() {
+        let cfg = DeliveryConfig {
+            max_attempts: 8,
+            backoff_base_secs: 60,
+            backoff_max_secs: 600,
+        };
+        let outbox = Outbox::open_in_memory(&cfg).unwrap();
+        outbox.enqueue(&message("1", "first")).await.unwrap();
+        outbox.enqueue(&message("1", "second")).await.unwrap();
+        assert_eq!(outbox.next_due_in("telegram").await.unwrap(), Some(0));
+
+        let due = outbox.claim_due("telegram", 10).await.unwrap();
+        outbox.mark_failed(&due[0], "network down").await.unwrap();
+
+        // "second" is past its time but queued behind "first".
+        assert!(outbox.claim_due("telegram", 10).await.unwrap().is_empty());
+        let wait = outbox.next_due_in("telegram").await.unwrap().unwrap();
+        assert!(wait > 50_000, "{wait}");
+    }
+
+    #[tokio::test]
+    async fn entries_with_an_invalid_payload_are_dead_lettered() {
+        let cfg = DeliveryConfig {
+            max_attempts: 8,
+            backoff_base_secs: 0,
+            backoff_max_secs: 0,
+        };
+        let outbox = Outbox::open_in_memory(&cfg).unwrap();
+        let broken = outbox.enqueue(&message("1", "broken")).await.unwrap();
+        outbox.enqueue(&message("1", "next")).await.unwrap();
+        outbox
+            .with_conn(move |conn| {
+                conn.execute("UPDATE outbound SET payload = '{' WHERE id = ?1", [broken])?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert!(outbox.claim_due("telegram", 10).await.unwrap().is_empty());
+        let due = outbox.claim_due("telegram", 10).await.unwrap();
+        assert_eq!(due[0].message.content, "next");
+        let (status, error) = outbox
+            .with_conn(move |conn| {
+                Ok(conn.query_row(
+                    "SELECT status, last_error FROM outbound WHERE id = ?1",
+                    [broken],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                )?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(status, "dead");
+        assert!(error.contains("invalid payload"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn entries_without_a_running_channel_are_dead_lettered() {
+        let cfg = DeliveryConfig {
+            max_attempts: 8,
+            backoff_base_secs: 0,
+            backoff_max_secs: 0,
+        };
+        let outbox = Outbox::open_in_memory(&cfg).unwrap();
+        outbox.enqueue(&message("1", "to telegram")).await.unwrap();
+        let mut orphan = message("2", "to irc");
+        orphan.channel = "irc".to_string();
+        outbox.enqueue(&orphan).await.unwrap();
+
+        let running = vec!["telegram".to_string()];
+        assert_eq!(outbox.dead_letter_unserved(&running).await.unwrap(), 1);
+        let dead = outbox.list(Some(DeliveryStatus::Dead), 10).await.unwrap();
+        assert_eq!(dead[0].message.channel, "irc");
+        assert_eq!(
+            dead[0].last_error.as_deref(),
+            Some("channel 'irc' is not running")
+        );
+
+        assert_eq!(
+            outbox
+                .retire_channel("telegram", "channel 'telegram' failed to start")
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(outbox
+            .list(Some(DeliveryStatus::Pending), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
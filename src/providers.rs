@@ -1,3 +1,5 @@
+pub mod mock;
+
 use rig::providers::openai;
 
 /// Build an OpenAI-compatible client (works for OpenAI and Ollama).
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Result};
+use rig::completion::message::AssistantContent;
+use rig::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, Usage,
+};
+use rig::one_or_many::OneOrMany;
+use rig::streaming::StreamingCompletionResponse;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One scripted model response. Exactly what the fixture says is returned:
+/// text, tool calls, or an error such as `429 Too Many Requests` or
+/// `request timed out` (which the agent classifies like real failures).
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct ScriptedResponse {
+    /// Only served to this model; any mock model when unset.
+    model: Option<String>,
+    /// Delay before the response (or error) is returned.
+    latency_ms: u64,
+    text: String,
+    tool_calls: Vec<ScriptedToolCall>,
+    error: Option<String>,
+    /// Reported usage; estimated from the request size when unset.
+    usage: Option<ScriptedUsage>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ScriptedToolCall {
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct ScriptedUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    responses: Vec<ScriptedResponse>,
+}
+
+/// The responses left to replay, shared by every mock route so that a
+/// fallback picks up where the failed route stopped.
+#[derive(Clone)]
+pub struct MockScript {
+    responses: Arc<Mutex<VecDeque<ScriptedResponse>>>,
+    tool_call_ids: Arc<AtomicUsize>,
+}
+
+impl MockScript {
+    /// Read a fixture file. YAML is accepted, and so is JSON as a subset.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("cannot read mock fixture {}: {err}", path.display()))?;
+        Self::parse(&text).map_err(|err| anyhow!("invalid mock fixture {}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let fixture: Fixture = serde_yaml::from_str(text)?;
+        Ok(Self {
+            responses: Arc::new(Mutex::new(fixture.responses.into())),
+            tool_call_ids: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Take the next response meant for `model`.
+    fn next_for(&self, model: &str) -> Option<ScriptedResponse> {
+        let mut responses = self.responses.lock().unwrap();
+        let index = responses
+            .iter()
+            .position(|response| response.model.as_deref().is_none_or(|m| m == model))?;
+        responses.remove(index)
+    }
+}
+
+#[derive(Clone)]
+pub struct MockCompletionModel {
+    script: MockScript,
+    model: String,
+}
+
+impl CompletionModel for MockCompletionModel {
+    type Response = ();
+    type StreamingResponse = ();
+    type Client = MockScript;
+
+    fn make(client: &Self::Client, model: impl Into<String>) -> Self {
+        Self {
+            script: client.clone(),
+            model: model.into(),
+        }
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<()>, CompletionError> {
+        let response = self.script.next_for(&self.model).ok_or_else(|| {
+            CompletionError::ProviderError(format!(
+                "mock fixture has no response left for model '{}'",
+                self.model
+            ))
+        })?;
+        if response.latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(response.latency_ms)).await;
+        }
+        if let Some(error) = response.error {
+            return Err(CompletionError::ProviderError(error));
+        }
+
+        let mut content = Vec::new();
+        if !response.text.is_empty() {
+            content.push(AssistantContent::text(&response.text));
+        }
+        for call in response.tool_calls {
+            let id = self.script.tool_call_ids.fetch_add(1, Ordering::Relaxed) + 1;
+            content.push(AssistantContent::tool_call(
+                format!("mock-call-{id}"),
+                call.name,
+                call.args,
+            ));
+        }
+        let choice =
+            OneOrMany::many(content).unwrap_or_else(|_| OneOrMany::one(AssistantContent::text("")));
+
+        let (input_tokens, output_tokens) = match response.usage {
+            Some(usage) => (usage.input_tokens, usage.output_tokens),
+            None => {
+                let sent = request.preamble.as_deref().map_or(0, str::len)
+                    + serde_json::to_string(&request.chat_history).map_or(0, |h| h.len());
+                (sent as u64 / 4, response.text.len() as u64 / 4)
+            }
+        };
+        let mut usage = Usage::new();
+        usage.input_tokens = input_tokens;
+        usage.output_tokens = output_tokens;
+        usage.total_tokens = input_tokens + output_tokens;
+        Ok(CompletionResponse {
+            choice,
+            usage,
+            raw_response: (),
+        })
+    }
+
+    async fn stream(
+        &self,
+        _request: CompletionRequest,
+    ) -> Result<StreamingCompletionResponse<()>, CompletionError> {
+        Err(CompletionError::ProviderError(
+            "the mock provider does not stream".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_responses_in_order_per_model() {
+        let script = MockScript::parse(
+            r#"
+responses:
+  - model: primary
+    error: "429 Too Many Requests"
+  - text: "any model"
+  - model: backup
+    tool_calls:
+      - name: read_file
+        args: { path: notes.md }
+"#,
+        )
+        .unwrap();
+        let backup = script.next_for("backup").unwrap();
+        assert_eq!(backup.text, "any model");
+        let backup = script.next_for("backup").unwrap();
+        assert_eq!(backup.tool_calls[0].name, "read_file");
+        assert_eq!(backup.tool_calls[0].args["path"], "notes.md");
+        assert!(script.next_for("backup").is_none());
+        let primary = script.next_for("primary").unwrap();
+        assert_eq!(primary.error.as_deref(), Some("429 Too Many Requests"));
+
+        assert!(MockScript::parse(r#"{"responses": [{"text": "json works too"}]}"#).is_ok());
+    }
+}
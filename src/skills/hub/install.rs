@@ -122,7 +122,7 @@ pub(super) fn ensure_skill_md_exists(dir: &Path) -> Result<()> {
     )
 }
 
-pub(super) fn copy_directory(source: &Path, target: &Path) -> Result<()> {
+pub(crate) fn copy_directory(source: &Path, target: &Path) -> Result<()> {
     fs::create_dir_all(target)
         .with_context(|| format!("failed to create target directory: {}", target.display()))?;
 
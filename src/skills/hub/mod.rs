@@ -12,6 +12,7 @@ mod http;
 mod install;
 mod source;
 
+pub(crate) use install::copy_directory;
 pub use install::sanitize_name;
 
 pub const DEFAULT_CLAWHUB_BASE_URL: &str = "https://clawhub.ai";
@@ -27,7 +27,8 @@ pub struct CronArgs {
     pub schedule: Option<String>,
     /// Delivery channel for add (e.g. "telegram")
     pub channel: Option<String>,
-    /// Delivery target for add (e.g. Telegram chat id)
+    /// Delivery target for add: the chat_id from [Conversation context],
+    /// including any `:<topic>` suffix for Telegram forum topics
     pub to: Option<String>,
     /// Job id (required for remove)
     pub id: Option<String>,
@@ -1,9 +1,15 @@
 use crate::bus::MessageBus;
-use crate::config::{AppConfig, MemoryMode};
+use crate::config::{AppConfig, MemoryMode, ToolsConfig};
 use crate::cron::CronService;
 use crate::memory::simple::file_store::MemoryStore;
 use crate::memory::smart::vector_store::VectorMemoryStore;
 use crate::skills::SkillManager;
+use rig::completion::request::ToolDefinition;
+use rig::tool::{ToolDyn, ToolError as RigToolError};
+use rig::wasm_compat::WasmBoxedFuture;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
 pub mod activate_skill;
 pub mod cron;
@@ -30,21 +36,14 @@ impl std::fmt::Display for ToolError {
 
 impl std::error::Error for ToolError {}
 
+/// The agent's tools as Rig trait objects. Built-in tools are filtered by
+/// `tools.disabled` and `tools.profile`; more can be added with
+/// [`ToolRegistry::register`]. Every call goes through [`ManagedTool`].
 #[derive(Clone)]
 pub struct ToolRegistry {
-    pub read_file: fs::ReadFileTool,
-    pub write_file: fs::WriteFileTool,
-    pub edit_file: fs::EditFileTool,
-    pub list_dir: fs::ListDirTool,
-    pub exec: shell::ExecTool,
-    pub web_search: web::WebSearchTool,
-    pub web_fetch: web::WebFetchTool,
-    pub activate_skill: activate_skill::ActivateSkillTool,
-    pub cron: cron::CronTool,
-    pub send_message: send::SendMessageTool,
-    pub memory_search: memory::MemorySearchTool,
-    pub memory_get: memory::MemoryGetTool,
-    pub remember: Option<memory::RememberTool>,
+    tools: Vec<Arc<dyn ToolDyn>>,
+    timeout: Duration,
+    max_output_chars: usize,
 }
 
 impl ToolRegistry {
@@ -60,42 +59,243 @@ impl ToolRegistry {
         } else {
             None
         };
-        let memory_search =
-            memory::MemorySearchTool::new(memory_store.clone(), vector_store.clone());
-        let memory_get = memory::MemoryGetTool::new(memory_store.clone(), vector_store.clone());
         let remember = match cfg.memory.mode {
             MemoryMode::None => None,
             MemoryMode::Simple => Some(memory::RememberTool::new_file(memory_store.clone())),
             MemoryMode::Smart => vector_store
+                .clone()
                 .map(|store| memory::RememberTool::new_hybrid(store, memory_store.clone()))
                 .or_else(|| Some(memory::RememberTool::new_file(memory_store.clone()))),
         };
         let skill_manager = SkillManager::from_workspace_dir(cfg.workspace_dir.as_path());
+        let mut registry = Self::empty(&cfg.tools);
+        registry.register(fs::ReadFileTool::new(allowed_dir.clone()));
+        registry.register(fs::WriteFileTool::new(allowed_dir.clone()));
+        registry.register(fs::EditFileTool::new(allowed_dir.clone()));
+        registry.register(fs::ListDirTool::new(allowed_dir.clone()));
+        // The call timeout would otherwise cut exec off before its own limit.
+        registry.register(shell::ExecTool::new(
+            cfg.tools
+                .exec_timeout_secs
+                .min(cfg.tools.timeout_secs.max(1)),
+            cfg.workspace_dir.clone(),
+            allowed_dir.clone(),
+        ));
+        registry.register(web::WebSearchTool::new(
+            cfg.tools.web_search_provider.clone(),
+            cfg.tools.brave_api_key.clone(),
+            cfg.tools.firecrawl_api_key.clone(),
+        ));
+        registry.register(web::WebFetchTool::new(
+            cfg.tools.web_fetch_provider.clone(),
+            cfg.tools.firecrawl_api_key.clone(),
+        ));
+        registry.register(activate_skill::ActivateSkillTool::new(skill_manager));
+        registry.register(cron::CronTool::new(cron_service));
+        registry.register(send::SendMessageTool::new(bus.clone()));
+        registry.register(send::SendFileTool::new(
+            bus,
+            cfg.workspace_dir.clone(),
+            allowed_dir,
+        ));
+        registry.register(memory::MemorySearchTool::new(
+            memory_store.clone(),
+            vector_store.clone(),
+        ));
+        registry.register(memory::MemoryGetTool::new(memory_store, vector_store));
+        if let Some(remember) = remember {
+            registry.register(remember);
+        }
+
+        warn_unknown_tools(&cfg.tools, &registry.names());
+        registry
+            .tools
+            .retain(|tool| is_enabled(&cfg.tools, &tool.name()));
+        registry
+    }
+
+    /// A registry without tools that applies the configured call limits.
+    pub fn empty(cfg: &ToolsConfig) -> Self {
         Self {
-            read_file: fs::ReadFileTool::new(allowed_dir.clone()),
-            write_file: fs::WriteFileTool::new(allowed_dir.clone()),
-            edit_file: fs::EditFileTool::new(allowed_dir.clone()),
-            list_dir: fs::ListDirTool::new(allowed_dir.clone()),
-            exec: shell::ExecTool::new(
-                cfg.tools.exec_timeout_secs,
-                cfg.workspace_dir.clone(),
-                allowed_dir,
-            ),
-            web_search: web::WebSearchTool::new(
-                cfg.tools.web_search_provider.clone(),
-                cfg.tools.brave_api_key.clone(),
-                cfg.tools.firecrawl_api_key.clone(),
-            ),
-            web_fetch: web::WebFetchTool::new(
-                cfg.tools.web_fetch_provider.clone(),
-                cfg.tools.firecrawl_api_key.clone(),
-            ),
-            activate_skill: activate_skill::ActivateSkillTool::new(skill_manager),
-            cron: cron::CronTool::new(cron_service),
-            send_message: send::SendMessageTool::new(bus),
-            memory_search,
-            memory_get,
-            remember,
+            tools: Vec::new(),
+            timeout: Duration::from_secs(cfg.timeout_secs.max(1)),
+            max_output_chars: cfg.max_output_chars,
+        }
+    }
+
+    /// Add a tool, replacing any registered tool with the same name.
+    pub fn register(&mut self, tool: impl ToolDyn + 'static) {
+        let tool: Arc<dyn ToolDyn> = Arc::new(tool);
+        let name = tool.name();
+        self.tools.retain(|existing| existing.name() != name);
+        self.tools.push(tool);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Wrap a tool with this registry's timeout, logging and truncation.
+    pub fn manage(&self, tool: Arc<dyn ToolDyn>) -> ManagedTool {
+        ManagedTool {
+            inner: tool,
+            timeout: self.timeout,
+            max_output_chars: self.max_output_chars,
+        }
+    }
+
+    /// Every tool, managed and boxed for a Rig agent builder.
+    pub fn boxed(&self) -> Vec<Box<dyn ToolDyn>> {
+        self.tools
+            .iter()
+            .map(|tool| Box::new(self.manage(tool.clone())) as Box<dyn ToolDyn>)
+            .collect()
+    }
+}
+
+/// Whether the tools config lets `name` be registered. An unknown profile
+/// enables nothing, so a typo never widens what the agent may do.
+fn is_enabled(cfg: &ToolsConfig, name: &str) -> bool {
+    if cfg.disabled.iter().any(|disabled| disabled == name) {
+        return false;
+    }
+    if cfg.profile.is_empty() {
+        return true;
+    }
+    cfg.profiles
+        .get(&cfg.profile)
+        .is_some_and(|tools| tools.iter().any(|tool| tool == name))
+}
+
+fn warn_unknown_tools(cfg: &ToolsConfig, known: &[String]) {
+    if !cfg.profile.is_empty() && !cfg.profiles.contains_key(&cfg.profile) {
+        warn!(
+            "tools profile '{}' is not defined; no tools enabled",
+            cfg.profile
+        );
+    }
+    let listed = cfg.disabled.iter().chain(cfg.profiles.values().flatten());
+    for name in listed {
+        if !known.contains(name) {
+            warn!("unknown tool '{name}' in tools config");
+        }
+    }
+}
+
+/// Cross-cutting behavior for every tool call: a time limit, a log line
+/// with the outcome, and truncation of oversized output.
+#[derive(Clone)]
+pub struct ManagedTool {
+    inner: Arc<dyn ToolDyn>,
+    timeout: Duration,
+    max_output_chars: usize,
+}
+
+impl ToolDyn for ManagedTool {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn definition<'a>(&'a self, prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+        self.inner.definition(prompt)
+    }
+
+    fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, RigToolError>> {
+        Box::pin(async move {
+            let name = self.inner.name();
+            let started = Instant::now();
+            let result = match tokio::time::timeout(self.timeout, self.inner.call(args)).await {
+                Ok(result) => result,
+                Err(_) => Err(RigToolError::ToolCallError(Box::new(ToolError::msg(
+                    format!("{name} timed out after {}s", self.timeout.as_secs()),
+                )))),
+            };
+            let elapsed = started.elapsed().as_millis();
+            match &result {
+                Ok(output) => info!("tool {name} ok in {elapsed}ms ({} chars)", output.len()),
+                Err(err) => warn!("tool {name} failed in {elapsed}ms: {err}"),
+            }
+            result.map(|output| truncate_output(output, self.max_output_chars))
+        })
+    }
+}
+
+fn truncate_output(output: String, max_chars: usize) -> String {
+    let total = output.chars().count();
+    if total <= max_chars {
+        return output;
+    }
+    let kept: String = output.chars().take(max_chars).collect();
+    format!(
+        "{kept}\n... (truncated {} of {total} chars)",
+        total - max_chars
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SlowTool;
+
+    impl ToolDyn for SlowTool {
+        fn name(&self) -> String {
+            "slow".to_string()
+        }
+
+        fn definition<'a>(&'a self, _prompt: String) -> WasmBoxedFuture<'a, ToolDefinition> {
+            Box::pin(async {
+                ToolDefinition {
+                    name: "slow".to_string(),
+                    description: String::new(),
+                    parameters: serde_json::json!({}),
+                }
+            })
         }
+
+        fn call<'a>(&'a self, args: String) -> WasmBoxedFuture<'a, Result<String, RigToolError>> {
+            Box::pin(async move {
+                if args == "hang" {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Ok("x".repeat(50))
+            })
+        }
+    }
+
+    #[test]
+    fn disabled_tools_and_profiles_select_tools() {
+        let mut cfg = AppConfig::defaults().tools;
+        cfg.disabled = vec!["exec".to_string()];
+        assert!(is_enabled(&cfg, "read_file"));
+        assert!(!is_enabled(&cfg, "exec"));
+
+        cfg.profiles.insert(
+            "readonly".to_string(),
+            vec!["read_file".to_string(), "exec".to_string()],
+        );
+        cfg.profile = "readonly".to_string();
+        assert!(is_enabled(&cfg, "read_file"));
+        assert!(!is_enabled(&cfg, "write_file"));
+        assert!(!is_enabled(&cfg, "exec"));
+
+        cfg.profile = "missing".to_string();
+        assert!(!is_enabled(&cfg, "read_file"));
+    }
+
+    #[tokio::test]
+    async fn managed_tools_time_out_and_truncate_output() {
+        let mut cfg = AppConfig::defaults().tools;
+        cfg.timeout_secs = 1;
+        cfg.max_output_chars = 10;
+        let mut registry = ToolRegistry::empty(&cfg);
+        registry.register(SlowTool);
+        assert_eq!(registry.names(), ["slow"]);
+
+        let tools = registry.boxed();
+        let output = tools[0].call("{}".to_string()).await.unwrap();
+        assert_eq!(output, "xxxxxxxxxx\n... (truncated 40 of 50 chars)");
+        let err = tools[0].call("hang".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("slow timed out after 1s"));
     }
 }
@@ -1,8 +1,9 @@
-use crate::bus::{MessageBus, OutboundMessage};
-use crate::tools::ToolError;
+use crate::bus::{MessageBus, OutboundAttachment, OutboundMessage};
+use crate::tools::{fs, ToolError};
 use rig::completion::request::ToolDefinition;
 use rig::tool::Tool;
 use serde::Deserialize;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 pub struct SendMessageTool {
@@ -68,10 +69,149 @@ impl Tool for SendMessageTool {
                 channel,
                 chat_id,
                 content,
+                attachments: Vec::new(),
+                reply_to: None,
             })
-            .await;
+            .await
+            .map_err(|err| ToolError::msg(err.to_string()))?;
 
             Ok("Message sent.".to_string())
         }
     }
 }
+
+#[derive(Clone)]
+pub struct SendFileTool {
+    bus: MessageBus,
+    workspace_dir: PathBuf,
+    /// When set, the file must resolve to a path under this directory.
+    allowed_dir: Option<PathBuf>,
+}
+
+impl SendFileTool {
+    pub fn new(bus: MessageBus, workspace_dir: PathBuf, allowed_dir: Option<PathBuf>) -> Self {
+        Self {
+            bus,
+            workspace_dir,
+            allowed_dir,
+        }
+    }
+
+    fn resolve(&self, raw: &str) -> Result<PathBuf, ToolError> {
+        let candidate = Path::new(raw);
+        let joined = if candidate.is_absolute() || raw.starts_with('~') {
+            raw.to_string()
+        } else {
+            self.workspace_dir
+                .join(candidate)
+                .to_string_lossy()
+                .to_string()
+        };
+        let path = fs::resolve_path(&joined, self.allowed_dir.as_deref(), false)
+            .map_err(ToolError::msg)?;
+        if !path.is_file() {
+            return Err(ToolError::msg(format!("Not a file: {raw}")));
+        }
+        Ok(path)
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+pub struct SendFileArgs {
+    /// Destination channel (e.g. "telegram")
+    pub channel: String,
+    /// Destination chat id (e.g. Telegram chat id)
+    pub chat_id: String,
+    /// File path to send; relative paths are resolved against the workspace
+    pub path: String,
+    /// Optional text sent with the file
+    pub caption: Option<String>,
+}
+
+impl Tool for SendFileTool {
+    const NAME: &'static str = "send_file";
+    type Args = SendFileArgs;
+    type Output = String;
+    type Error = ToolError;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Send a file from the workspace to a specific channel/chat as an attachment (images are shown inline where supported). Write the file first, then call this tool with its path.".to_string(),
+            parameters: serde_json::to_value(schemars::schema_for!(SendFileArgs)).unwrap(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let channel = args.channel.trim().to_string();
+        let chat_id = args.chat_id.trim().to_string();
+        let raw_path = args.path.trim();
+
+        if channel.is_empty() {
+            return Err(ToolError::msg("Missing required field: channel"));
+        }
+        if chat_id.is_empty() {
+            return Err(ToolError::msg("Missing required field: chat_id"));
+        }
+        if raw_path.is_empty() {
+            return Err(ToolError::msg("Missing required field: path"));
+        }
+
+        let path = self.resolve(raw_path)?;
+        let attachment = OutboundAttachment { path };
+        let file_name = attachment.file_name();
+
+        self.bus
+            .publish_outbound(OutboundMessage {
+                channel,
+                chat_id,
+                content: args.caption.unwrap_or_default().trim().to_string(),
+                attachments: vec![attachment],
+                reply_to: None,
+            })
+            .await
+            .map_err(|err| ToolError::msg(err.to_string()))?;
+
+        Ok(format!("File {file_name} sent."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SendFileTool;
+    use crate::bus::MessageBus;
+
+    #[tokio::test]
+    async fn send_file_resolves_relative_paths_against_workspace() {
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("report.csv"), "a,b\n1,2\n").unwrap();
+        let tool = SendFileTool::new(
+            MessageBus::new(),
+            workspace.path().to_path_buf(),
+            Some(workspace.path().to_path_buf()),
+        );
+
+        let resolved = tool.resolve("report.csv").unwrap();
+        assert_eq!(
+            resolved,
+            workspace.path().join("report.csv").canonicalize().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn send_file_rejects_paths_outside_workspace() {
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let tool = SendFileTool::new(
+            MessageBus::new(),
+            workspace.path().to_path_buf(),
+            Some(workspace.path().to_path_buf()),
+        );
+
+        let err = tool
+            .resolve(outside.path().to_str().unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("outside allowed directory"));
+    }
+}
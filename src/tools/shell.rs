@@ -115,11 +115,14 @@ fn build_shell_command(command: &str, cwd: &Path) -> Result<(Command, Option<Com
     primary.arg("/C").arg(command).current_dir(cwd);
     primary.stdout(std::process::Stdio::piped());
     primary.stderr(std::process::Stdio::piped());
+    // A call dropped by the registry's timeout must not leave the shell running.
+    primary.kill_on_drop(true);
 
     let mut fallback = Command::new("cmd.exe");
     fallback.arg("/C").arg(command).current_dir(cwd);
     fallback.stdout(std::process::Stdio::piped());
     fallback.stderr(std::process::Stdio::piped());
+    fallback.kill_on_drop(true);
 
     Ok((primary, Some(fallback)))
 }
@@ -136,12 +139,15 @@ fn build_shell_command(command: &str, cwd: &Path) -> Result<(Command, Option<Com
     primary.arg("-c").arg(command).current_dir(cwd);
     primary.stdout(std::process::Stdio::piped());
     primary.stderr(std::process::Stdio::piped());
+    // A call dropped by the registry's timeout must not leave the shell running.
+    primary.kill_on_drop(true);
 
     let fallback = if shell == "/bin/sh" { "sh" } else { "/bin/sh" };
     let mut fallback_cmd = Command::new(fallback);
     fallback_cmd.arg("-c").arg(command).current_dir(cwd);
     fallback_cmd.stdout(std::process::Stdio::piped());
     fallback_cmd.stderr(std::process::Stdio::piped());
+    fallback_cmd.kill_on_drop(true);
 
     Ok((primary, Some(fallback_cmd)))
 }
@@ -5,6 +5,10 @@ use rig::prelude::TranscriptionClient;
 use rig::providers::openai;
 use rig::transcription::TranscriptionModel;
 use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
 use tracing::warn;
 
 #[derive(Clone)]
@@ -18,6 +22,45 @@ enum Backend {
         context_bias: Option<String>,
         timestamp_granularities: Vec<String>,
     },
+    Local {
+        program: String,
+        args: Vec<String>,
+    },
+}
+
+/// 16 kHz mono 16-bit PCM, the format long audio is split into.
+const WAV_BYTES_PER_SEC: usize = 16_000 * 2;
+/// A chunk this small holds no audio beyond the WAV header.
+const EMPTY_WAV_BYTES: usize = 1024;
+/// Safety cap on chunks per audio message.
+const MAX_CHUNKS: u64 = 200;
+const TRANSCRIPT_CACHE_CAPACITY: usize = 256;
+/// How many trailing words are compared when stitching overlapping chunks.
+const STITCH_MAX_WORDS: usize = 20;
+
+/// Transcripts keyed by a platform file id (e.g. Telegram `file_unique_id`)
+/// so re-sent audio is not transcribed twice.
+#[derive(Default)]
+struct TranscriptCache {
+    entries: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl TranscriptCache {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: String, transcript: String) {
+        if self.entries.insert(key.clone(), transcript).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > TRANSCRIPT_CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -26,6 +69,13 @@ pub struct Transcriber {
     model: String,
     language: Option<String>,
     max_bytes: usize,
+    ffmpeg: String,
+    chunk_seconds: u64,
+    chunk_overlap_seconds: u64,
+    max_input_bytes: usize,
+    echo: bool,
+    echo_max_lines: usize,
+    cache: Arc<Mutex<TranscriptCache>>,
 }
 
 impl Transcriber {
@@ -68,6 +118,21 @@ impl Transcriber {
                         .clone(),
                 }
             }
+            "local" | "whisper.cpp" | "whisper-cpp" | "command" => {
+                let mut parts = cfg
+                    .transcription
+                    .command
+                    .split_whitespace()
+                    .map(str::to_string);
+                let Some(program) = parts.next() else {
+                    warn!("transcription disabled: local provider requires transcription.command");
+                    return None;
+                };
+                Backend::Local {
+                    program,
+                    args: parts.collect(),
+                }
+            }
             other => {
                 warn!("transcription disabled: unsupported provider '{other}'");
                 return None;
@@ -79,11 +144,120 @@ impl Transcriber {
             model: cfg.transcription.model.clone(),
             language: cfg.transcription.language.clone(),
             max_bytes: cfg.transcription.max_bytes.max(1),
+            ffmpeg: cfg.transcription.ffmpeg_path.clone(),
+            chunk_seconds: cfg.transcription.chunk_seconds,
+            chunk_overlap_seconds: cfg.transcription.chunk_overlap_seconds,
+            max_input_bytes: cfg.transcription.max_input_bytes,
+            echo: cfg.transcription.echo,
+            echo_max_lines: cfg.transcription.echo_max_lines,
+            cache: Arc::new(Mutex::new(TranscriptCache::default())),
         })
     }
 
-    pub fn max_bytes(&self) -> usize {
-        self.max_bytes
+    /// Largest audio file accepted: `max_bytes` per request, or
+    /// `max_input_bytes` when long audio is chunked.
+    pub fn max_input_bytes(&self) -> usize {
+        if self.chunk_seconds == 0 {
+            self.max_bytes
+        } else {
+            self.max_input_bytes.max(self.max_bytes)
+        }
+    }
+
+    pub fn cached(&self, cache_key: &str) -> Option<String> {
+        self.cache.lock().ok()?.get(cache_key)
+    }
+
+    /// Transcribe audio of any accepted size, splitting it into overlapping
+    /// chunks when it exceeds `max_bytes`. Non-empty results are cached under
+    /// `cache_key`.
+    pub async fn transcribe(
+        &self,
+        cache_key: Option<&str>,
+        filename: String,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        if let Some(hit) = cache_key.and_then(|key| self.cached(key)) {
+            return Ok(hit);
+        }
+        if data.len() > self.max_input_bytes() {
+            return Err(anyhow!(
+                "audio payload too large: {} bytes (max {})",
+                data.len(),
+                self.max_input_bytes()
+            ));
+        }
+
+        let transcript = if data.len() > self.max_bytes {
+            self.transcribe_chunked(&filename, data).await?
+        } else {
+            self.transcribe_bytes(filename, data).await?
+        };
+
+        if let (Some(key), false) = (cache_key, transcript.is_empty()) {
+            if let Ok(mut cache) = self.cache.lock() {
+                cache.insert(key.to_string(), transcript.clone());
+            }
+        }
+        Ok(transcript)
+    }
+
+    /// Text to post back to the chat before the answer, if echo is enabled.
+    pub fn echo_text(&self, transcript: &str) -> Option<String> {
+        if !self.echo || transcript.trim().is_empty() {
+            return None;
+        }
+        Some(format_transcript_echo(transcript, self.echo_max_lines))
+    }
+
+    async fn transcribe_chunked(&self, filename: &str, data: Vec<u8>) -> Result<String> {
+        let dir = tempfile::tempdir().context("failed to create transcription temp dir")?;
+        let source = dir.path().join(sanitize_filename(filename));
+        tokio::fs::write(&source, &data).await?;
+
+        // Keep each chunk (plus overlap) under the per-request byte limit.
+        let overlap = self.chunk_overlap_seconds;
+        let fits = ((self.max_bytes / WAV_BYTES_PER_SEC) as u64)
+            .saturating_sub(overlap)
+            .max(1);
+        let chunk = self.chunk_seconds.min(fits).max(1);
+        let full_chunk_bytes = ((chunk + overlap) as usize) * WAV_BYTES_PER_SEC;
+
+        let mut stitched = String::new();
+        let mut complete = false;
+        for index in 0..MAX_CHUNKS {
+            let chunk_name = format!("chunk_{index:03}.wav");
+            let chunk_path = dir.path().join(&chunk_name);
+            crate::audio::extract_wav_segment(
+                &self.ffmpeg,
+                &source,
+                &chunk_path,
+                index * chunk,
+                chunk + overlap,
+            )
+            .await?;
+            let bytes = tokio::fs::read(&chunk_path).await?;
+            if bytes.len() <= EMPTY_WAV_BYTES {
+                complete = true;
+                break;
+            }
+            let is_last = bytes.len() < full_chunk_bytes - EMPTY_WAV_BYTES;
+            let text = self
+                .transcribe_bytes(chunk_name, bytes)
+                .await
+                .with_context(|| format!("chunk {} failed", index + 1))?;
+            stitched = stitch_transcripts(&stitched, &text);
+            if is_last {
+                complete = true;
+                break;
+            }
+        }
+        if !complete {
+            let seconds = MAX_CHUNKS * chunk;
+            warn!("transcript of {filename} truncated after {MAX_CHUNKS} chunks ({seconds}s)");
+            stitched.push_str(&truncation_note(seconds));
+        }
+        Ok(stitched)
     }
 
     pub async fn transcribe_bytes(&self, filename: String, data: Vec<u8>) -> Result<String> {
@@ -163,8 +337,175 @@ impl Transcriber {
                     )
                 })
             }
+            Backend::Local { program, args } => {
+                self.transcribe_local(program, args, &filename, data).await
+            }
         }
     }
+
+    /// Run a local speech-to-text binary on the audio. Non-WAV input is first
+    /// converted to 16 kHz mono WAV with ffmpeg. The transcript is read from
+    /// `{output}` when the command line has that placeholder, otherwise from
+    /// stdout.
+    async fn transcribe_local(
+        &self,
+        program: &str,
+        args: &[String],
+        filename: &str,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let dir = tempfile::tempdir().context("failed to create transcription temp dir")?;
+        let original = dir.path().join(sanitize_filename(filename));
+        tokio::fs::write(&original, &data).await?;
+
+        let is_wav = original
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("wav"))
+            .unwrap_or(false);
+        let input = if is_wav {
+            original
+        } else {
+            let converted = dir.path().join("input.wav");
+            crate::audio::convert_to_wav_16k_mono(&self.ffmpeg, &original, &converted).await?;
+            converted
+        };
+        let output = dir.path().join("transcript.txt");
+
+        let language = self.language.as_deref().unwrap_or("auto");
+        let writes_file = args.iter().any(|arg| arg.contains("{output}"));
+        let args = args
+            .iter()
+            .map(|arg| {
+                arg.replace("{input}", &input.to_string_lossy())
+                    .replace("{output}", &output.to_string_lossy())
+                    .replace("{model}", &self.model)
+                    .replace("{language}", language)
+            })
+            .collect::<Vec<_>>();
+
+        let result = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .with_context(|| format!("failed to launch transcription command {program}"))?;
+        if !result.status.success() {
+            return Err(anyhow!(
+                "transcription command exited with {}: {}",
+                result.status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&result.stderr).trim()
+            ));
+        }
+
+        let raw = if writes_file {
+            tokio::fs::read_to_string(&output)
+                .await
+                .context("transcription command did not write {output}")?
+        } else {
+            String::from_utf8_lossy(&result.stdout).to_string()
+        };
+        Ok(parse_local_transcript(&raw))
+    }
+}
+
+fn sanitize_filename(filename: &str) -> String {
+    let name = std::path::Path::new(filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name.is_empty() {
+        "audio.ogg".to_string()
+    } else {
+        name
+    }
+}
+
+/// Appended when audio runs past [`MAX_CHUNKS`], so the cut is visible.
+fn truncation_note(seconds: u64) -> String {
+    format!("\n\n[transcript truncated after {} minutes]", seconds / 60)
+}
+
+/// Append `next` to `prev`, dropping words repeated across the chunk overlap.
+fn stitch_transcripts(prev: &str, next: &str) -> String {
+    let prev = prev.trim();
+    let next = next.trim();
+    if prev.is_empty() {
+        return next.to_string();
+    }
+    if next.is_empty() {
+        return prev.to_string();
+    }
+
+    fn normalize(word: &str) -> String {
+        word.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    }
+
+    let prev_words = prev.split_whitespace().collect::<Vec<_>>();
+    let next_words = next.split_whitespace().collect::<Vec<_>>();
+    let max = STITCH_MAX_WORDS.min(prev_words.len()).min(next_words.len());
+    let overlap = (1..=max)
+        .rev()
+        .find(|&n| {
+            prev_words[prev_words.len() - n..]
+                .iter()
+                .map(|w| normalize(w))
+                .eq(next_words[..n].iter().map(|w| normalize(w)))
+        })
+        .unwrap_or(0);
+
+    let rest = next_words[overlap..].join(" ");
+    if rest.is_empty() {
+        prev.to_string()
+    } else {
+        format!("{prev} {rest}")
+    }
+}
+
+/// Render a transcript echo, one sentence per line, limited to `max_lines`
+/// (0 = no limit).
+fn format_transcript_echo(transcript: &str, max_lines: usize) -> String {
+    let mut lines = Vec::new();
+    for line in transcript.lines() {
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+            if word.ends_with(['.', '!', '?']) {
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+    }
+
+    let mut out = String::from("Transcript:\n");
+    if max_lines == 0 || lines.len() <= max_lines {
+        out.push_str(&lines.join("\n"));
+    } else {
+        out.push_str(&lines[..max_lines].join("\n"));
+        out.push_str("\n…");
+    }
+    out
+}
+
+/// Collapse CLI output into plain text, dropping whisper-style
+/// `[00:00:00.000 --> 00:00:02.000]` timestamp prefixes.
+fn parse_local_transcript(raw: &str) -> String {
+    raw.lines()
+        .map(|line| {
+            let line = line.trim();
+            match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                Some((stamp, text)) if stamp.contains("-->") => text.trim(),
+                _ => line,
+            }
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 fn build_openai_client(
@@ -198,3 +539,73 @@ fn extract_text_from_response(body: &Value) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_transcript_echo, parse_local_transcript, stitch_transcripts, truncation_note,
+        TranscriptCache, TRANSCRIPT_CACHE_CAPACITY,
+    };
+
+    #[test]
+    fn stitching_drops_words_repeated_in_overlap() {
+        let prev = "we should move the meeting to Friday";
+        let next = "to friday, and invite the whole team";
+        assert_eq!(
+            stitch_transcripts(prev, next),
+            "we should move the meeting to Friday and invite the whole team"
+        );
+    }
+
+    #[test]
+    fn stitching_without_overlap_joins_with_space() {
+        assert_eq!(
+            stitch_transcripts("first part.", "Second part."),
+            "first part. Second part."
+        );
+        assert_eq!(stitch_transcripts("", "only"), "only");
+    }
+
+    #[test]
+    fn truncation_note_reports_minutes() {
+        assert_eq!(
+            truncation_note(200 * 300),
+            "\n\n[transcript truncated after 1000 minutes]"
+        );
+    }
+
+    #[test]
+    fn echo_limits_to_first_sentences() {
+        let echo = format_transcript_echo("One. Two! Three? Four.", 2);
+        assert_eq!(echo, "Transcript:\nOne.\nTwo!\n…");
+        let full = format_transcript_echo("One. Two", 0);
+        assert_eq!(full, "Transcript:\nOne.\nTwo");
+    }
+
+    #[test]
+    fn transcript_cache_evicts_oldest_entries() {
+        let mut cache = TranscriptCache::default();
+        for i in 0..=TRANSCRIPT_CACHE_CAPACITY {
+            cache.insert(format!("file-{i}"), format!("text {i}"));
+        }
+        assert!(cache.get("file-0").is_none());
+        assert_eq!(cache.get("file-1").as_deref(), Some("text 1"));
+    }
+
+    #[test]
+    fn local_transcript_strips_whisper_timestamps() {
+        let raw = "\n[00:00:00.000 --> 00:00:02.500]   Remind me to call Ana\n[00:00:02.500 --> 00:00:04.000]  tomorrow at nine.\n";
+        assert_eq!(
+            parse_local_transcript(raw),
+            "Remind me to call Ana tomorrow at nine."
+        );
+    }
+
+    #[test]
+    fn local_transcript_keeps_plain_output() {
+        assert_eq!(
+            parse_local_transcript(" hello world \n"),
+            "hello world".to_string()
+        );
+    }
+}
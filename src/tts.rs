@@ -0,0 +1,203 @@
+use crate::config::{AppConfig, VoiceReplyMode};
+use anyhow::{anyhow, Context, Result};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde_json::json;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::warn;
+
+#[derive(Clone)]
+enum Backend {
+    OpenAI {
+        http: reqwest::Client,
+        api_key: String,
+        base_url: String,
+        voice: String,
+    },
+    Local {
+        program: String,
+        args: Vec<String>,
+    },
+}
+
+/// Text-to-speech for voice replies. Always produces OGG/Opus audio.
+#[derive(Clone)]
+pub struct Synthesizer {
+    backend: Backend,
+    model: String,
+    reply_mode: VoiceReplyMode,
+    max_chars: usize,
+    ffmpeg: String,
+}
+
+impl Synthesizer {
+    pub fn from_config(cfg: &AppConfig) -> Option<Self> {
+        if !cfg.tts.enabled {
+            return None;
+        }
+
+        let provider = cfg.tts.provider.trim().to_ascii_lowercase();
+        let backend = match provider.as_str() {
+            "" | "openai" => {
+                if cfg.providers.openai.api_key.trim().is_empty() {
+                    warn!("voice replies disabled: missing OpenAI API key");
+                    return None;
+                }
+                Backend::OpenAI {
+                    http: reqwest::Client::new(),
+                    api_key: cfg.providers.openai.api_key.clone(),
+                    base_url: cfg.providers.openai.base_url.clone(),
+                    voice: cfg.tts.voice.clone(),
+                }
+            }
+            "local" | "piper" | "command" => {
+                let mut parts = cfg.tts.command.split_whitespace().map(str::to_string);
+                let Some(program) = parts.next() else {
+                    warn!("voice replies disabled: local TTS provider requires tts.command");
+                    return None;
+                };
+                Backend::Local {
+                    program,
+                    args: parts.collect(),
+                }
+            }
+            other => {
+                warn!("voice replies disabled: unsupported TTS provider '{other}'");
+                return None;
+            }
+        };
+
+        Some(Self {
+            backend,
+            model: cfg.tts.model.clone(),
+            reply_mode: cfg.tts.reply_mode.clone(),
+            max_chars: cfg.tts.max_chars.max(1),
+            ffmpeg: cfg.tts.ffmpeg_path.clone(),
+        })
+    }
+
+    pub fn reply_mode(&self) -> &VoiceReplyMode {
+        &self.reply_mode
+    }
+
+    /// Synthesize `text` (markdown is flattened first) into OGG/Opus bytes.
+    pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>> {
+        let spoken = markdown_to_speech_text(text);
+        if spoken.is_empty() {
+            return Err(anyhow!("nothing to synthesize"));
+        }
+        if spoken.chars().count() > self.max_chars {
+            return Err(anyhow!(
+                "reply too long for voice: {} chars (max {})",
+                spoken.chars().count(),
+                self.max_chars
+            ));
+        }
+
+        match &self.backend {
+            Backend::OpenAI {
+                http,
+                api_key,
+                base_url,
+                voice,
+            } => {
+                let endpoint = format!("{}/audio/speech", base_url.trim_end_matches('/'));
+                let response = http
+                    .post(endpoint)
+                    .bearer_auth(api_key)
+                    .json(&json!({
+                        "model": self.model,
+                        "input": spoken,
+                        "voice": voice,
+                        "response_format": "opus",
+                    }))
+                    .send()
+                    .await
+                    .context("OpenAI speech request failed")?
+                    .error_for_status()
+                    .context("OpenAI speech request returned non-success status")?;
+                let bytes = response
+                    .bytes()
+                    .await
+                    .context("failed to read OpenAI speech response")?;
+                Ok(bytes.to_vec())
+            }
+            Backend::Local { program, args } => {
+                let dir = tempfile::tempdir().context("failed to create TTS temp dir")?;
+                let raw_path = dir.path().join("speech.wav");
+                let ogg_path = dir.path().join("speech.ogg");
+                let raw_str = raw_path.to_string_lossy().to_string();
+
+                let writes_file = args.iter().any(|arg| arg.contains("{output}"));
+                let args = args
+                    .iter()
+                    .map(|arg| arg.replace("{output}", &raw_str))
+                    .collect::<Vec<_>>();
+
+                let mut child = Command::new(program)
+                    .args(&args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .with_context(|| format!("failed to launch TTS command {program}"))?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(spoken.as_bytes()).await?;
+                    stdin.shutdown().await?;
+                }
+                let output = child.wait_with_output().await?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "TTS command exited with {}: {}",
+                        output.status.code().unwrap_or(-1),
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                if !writes_file {
+                    tokio::fs::write(&raw_path, &output.stdout).await?;
+                }
+
+                crate::audio::convert_to_ogg_opus(&self.ffmpeg, &raw_path, &ogg_path).await?;
+                Ok(tokio::fs::read(&ogg_path).await?)
+            }
+        }
+    }
+}
+
+/// Flatten markdown into plain sentences suitable for reading aloud. Code
+/// blocks are dropped since they are unreadable as speech.
+fn markdown_to_speech_text(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut in_code_block = false;
+    for event in Parser::new(input) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(text) | Event::Code(text) if !in_code_block => out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => out.push(' '),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item)
+                if !out.ends_with('\n') =>
+            {
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::markdown_to_speech_text;
+
+    #[test]
+    fn speech_text_drops_markup_and_code_blocks() {
+        let input =
+            "# Weather\n\nIt is **sunny** today.\n\n```sh\ncurl wttr.in\n```\n\n- bring `water`";
+        assert_eq!(
+            markdown_to_speech_text(input),
+            "Weather\nIt is sunny today.\nbring water"
+        );
+    }
+}
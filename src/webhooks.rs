@@ -0,0 +1,304 @@
+use crate::bus::{InboundMessage, MessageBus};
+use crate::config::{AppConfig, WebhookEndpoint, WebhooksConfig};
+use anyhow::{anyhow, Result};
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use ring::hmac;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Listener for the `webhooks.endpoints` in config. A verified request
+/// renders its JSON payload through the endpoint's template and starts a
+/// one-off agent turn for the endpoint's target chat, which receives the
+/// answer. The turn keeps no history and may only call the endpoint's tools.
+struct WebhookServer {
+    cfg: WebhooksConfig,
+    bus: MessageBus,
+    /// Signatures accepted within the skew window, with their timestamps,
+    /// so a captured request cannot be replayed.
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+/// Serve the configured endpoints in the background.
+pub fn spawn(cfg: &AppConfig, bus: MessageBus) {
+    let server = Arc::new(WebhookServer::new(cfg, bus));
+    tokio::spawn(async move {
+        if let Err(err) = server.serve().await {
+            error!("webhook listener stopped: {err:#}");
+        }
+    });
+}
+
+impl WebhookServer {
+    fn new(cfg: &AppConfig, bus: MessageBus) -> Self {
+        let mut cfg = cfg.webhooks.clone();
+        cfg.endpoints.retain(|name, endpoint| {
+            let usable = !endpoint.secret.trim().is_empty()
+                && !endpoint.channel.trim().is_empty()
+                && !endpoint.chat_id.trim().is_empty();
+            if !usable {
+                warn!("webhook '{name}' needs secret, channel and chat_id; not serving it");
+            }
+            usable
+        });
+        Self {
+            cfg,
+            bus,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn serve(self: Arc<Self>) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(self.cfg.bind.trim())
+            .await
+            .map_err(|err| anyhow!("cannot listen on {}: {err}", self.cfg.bind))?;
+        info!(
+            "webhooks listening on {} ({} endpoints)",
+            listener.local_addr()?,
+            self.cfg.endpoints.len()
+        );
+        axum::serve(
+            listener,
+            self.router()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/hooks/{name}", post(receive))
+            .with_state(self)
+    }
+
+    /// Check the signature of `body` and reject timestamps outside the skew
+    /// window or signatures already used.
+    fn verify(
+        &self,
+        endpoint: &WebhookEndpoint,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), Rejection> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let timestamp = header(TIMESTAMP_HEADER)
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .ok_or(Rejection::Unauthorized("missing or invalid timestamp"))?;
+        let now = chrono::Utc::now().timestamp();
+        let max_skew = self.cfg.max_skew_secs as i64;
+        if (now - timestamp).abs() > max_skew {
+            return Err(Rejection::Unauthorized(
+                "timestamp outside the allowed window",
+            ));
+        }
+
+        let signature = header(SIGNATURE_HEADER)
+            .and_then(|value| value.trim().strip_prefix("sha256="))
+            .and_then(|digest| hex::decode(digest).ok())
+            .ok_or(Rejection::Unauthorized("missing or malformed signature"))?;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, endpoint.secret.trim().as_bytes());
+        hmac::verify(&key, &signed_content(timestamp, body), &signature)
+            .map_err(|_| Rejection::Unauthorized("signature mismatch"))?;
+
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, seen_at| now - *seen_at <= max_skew);
+        if seen.insert(hex::encode(&signature), timestamp).is_some() {
+            return Err(Rejection::Replayed);
+        }
+        Ok(())
+    }
+}
+
+enum Rejection {
+    Unauthorized(&'static str),
+    Replayed,
+}
+
+/// What senders sign: the timestamp header, a dot, then the raw body.
+fn signed_content(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut content = format!("{timestamp}.").into_bytes();
+    content.extend_from_slice(body);
+    content
+}
+
+async fn receive(
+    State(server): State<Arc<WebhookServer>>,
+    Path(name): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(endpoint) = server.cfg.endpoints.get(&name) else {
+        warn!("webhook request from {peer} for unknown endpoint '{name}'");
+        return reply(StatusCode::NOT_FOUND, "unknown endpoint");
+    };
+    match server.verify(endpoint, &headers, &body) {
+        Ok(()) => {}
+        Err(Rejection::Unauthorized(reason)) => {
+            warn!("webhook '{name}' rejected request from {peer}: {reason}");
+            return reply(StatusCode::UNAUTHORIZED, reason);
+        }
+        Err(Rejection::Replayed) => {
+            warn!("webhook '{name}' rejected replayed request from {peer}");
+            return reply(StatusCode::CONFLICT, "request already received");
+        }
+    }
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            warn!("webhook '{name}' got invalid JSON from {peer}: {err}");
+            return reply(StatusCode::BAD_REQUEST, "body must be JSON");
+        }
+    };
+
+    info!(
+        "webhook '{name}' accepted {} bytes from {peer}; notifying {}:{}",
+        body.len(),
+        endpoint.channel,
+        endpoint.chat_id
+    );
+    server
+        .bus
+        .publish_inbound(InboundMessage {
+            channel: endpoint.channel.clone(),
+            chat_id: endpoint.chat_id.clone(),
+            sender_id: format!("webhook:{name}"),
+            content: render_template(&name, &endpoint.template, &payload),
+            ephemeral: true,
+            allowed_tools: Some(endpoint.tools.clone()),
+            ..Default::default()
+        })
+        .await;
+    reply(StatusCode::ACCEPTED, "accepted")
+}
+
+fn reply(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "status": message }))).into_response()
+}
+
+/// Fill `{{path.to.field}}` placeholders from `payload`. Array elements are
+/// addressed by index, strings are inserted as-is and other values as JSON;
+/// missing fields render empty. An empty template shows the whole payload.
+fn render_template(name: &str, template: &str, payload: &Value) -> String {
+    if template.trim().is_empty() {
+        return format!(
+            "Webhook '{name}' received:\n{}",
+            serde_json::to_string_pretty(payload).unwrap_or_default()
+        );
+    }
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + len].trim();
+        let value = if path == "payload" {
+            Some(payload)
+        } else {
+            path.split('.').try_fold(payload, |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => value.get(key),
+            })
+        };
+        match value {
+            Some(Value::String(text)) => out.push_str(text),
+            Some(Value::Null) | None => {}
+            Some(value) => out.push_str(&serde_json::to_string_pretty(value).unwrap_or_default()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_payload_fields_into_template() {
+        let payload = json!({
+            "workflow": { "name": "ci", "runs": [{ "url": "https://ci/1" }] },
+            "attempt": 2,
+            "note": null,
+        });
+        let rendered = render_template(
+            "ci",
+            "{{ workflow.name }} failed (attempt {{attempt}}): {{workflow.runs.0.url}}{{note}}{{missing.field}} {{ unclosed",
+            &payload,
+        );
+        assert_eq!(rendered, "ci failed (attempt 2): https://ci/1 {{ unclosed");
+        assert!(render_template("ci", "", &payload).starts_with("Webhook 'ci' received:\n{"));
+    }
+
+    #[tokio::test]
+    async fn accepts_signed_requests_once() {
+        let mut cfg = AppConfig::defaults();
+        cfg.webhooks.endpoints.insert(
+            "grafana".to_string(),
+            WebhookEndpoint {
+                secret: "s3cret".to_string(),
+                template: "Alert: {{title}}".to_string(),
+                channel: "telegram".to_string(),
+                chat_id: "42".to_string(),
+                tools: vec!["web_search".to_string()],
+            },
+        );
+        let bus = MessageBus::new();
+        let server = Arc::new(WebhookServer::new(&cfg, bus.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks/grafana", listener.local_addr().unwrap());
+        let app = server
+            .router()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let body = br#"{"title":"disk full"}"#;
+        let sign = |timestamp: i64, secret: &str| {
+            let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+            let tag = hmac::sign(&key, &signed_content(timestamp, body));
+            format!("sha256={}", hex::encode(tag.as_ref()))
+        };
+        let client = reqwest::Client::new();
+        let post = |timestamp: i64, signature: String| {
+            client
+                .post(&url)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, signature)
+                .body(body.to_vec())
+                .send()
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        let accepted = post(now, sign(now, "s3cret")).await.unwrap();
+        assert_eq!(accepted.status(), 202);
+        let msg = bus.consume_inbound().await.unwrap();
+        assert_eq!(
+            (msg.channel.as_str(), msg.chat_id.as_str()),
+            ("telegram", "42")
+        );
+        assert_eq!(msg.sender_id, "webhook:grafana");
+        assert_eq!(msg.content, "Alert: disk full");
+        assert!(msg.ephemeral);
+        assert_eq!(msg.allowed_tools, Some(vec!["web_search".to_string()]));
+
+        let replayed = post(now, sign(now, "s3cret")).await.unwrap();
+        assert_eq!(replayed.status(), 409);
+        let forged = post(now, sign(now, "wrong")).await.unwrap();
+        assert_eq!(forged.status(), 401);
+        let stale = post(now - 3600, sign(now - 3600, "s3cret")).await.unwrap();
+        assert_eq!(stale.status(), 401);
+    }
+}